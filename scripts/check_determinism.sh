@@ -0,0 +1,20 @@
+#!/usr/bin/env bash
+# Check that debug and release builds compute bit-identical timelines with the
+# fixed-point feature, which lockstep play relies on
+set -euo pipefail
+
+test=physics::timeline::tests::test_fixed_point_timeline_digest
+
+digest=$(
+    cargo test --lib --features fixed-point -- --exact "$test" --nocapture |
+        sed -n 's/^timeline digest: //p'
+)
+if [ -z "$digest" ]; then
+    echo "Debug build didn't report a timeline digest" >&2
+    exit 1
+fi
+echo "Debug build timeline digest: $digest"
+
+PARALLAX_TIMELINE_DIGEST=$digest \
+    cargo test --release --lib --features fixed-point -- --exact "$test"
+echo "Release build computed the same timelines"
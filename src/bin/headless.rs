@@ -0,0 +1,175 @@
+//! Run a scenario headless for a number of ticks as fast as possible, then
+//! report tick throughput and the final state of every body
+//!
+//! Usage: headless <snapshot.json | --generate BODIES> [ticks]
+//!
+//! Scenarios are snapshots, e.g. saved from the game with F5, or a field of
+//! drifting bodies generated from a fixed seed
+
+use std::{f32::consts::TAU, time::Instant};
+
+use anyhow::Context;
+use parallax_protocol_arena::{
+    physics::{
+        collisions::Material,
+        PhysicsEnabled,
+        PhysicsSimulationPlugin,
+        SimulationConfig,
+    },
+    prelude::*,
+    snapshot::Snapshot,
+    subsystems::{
+        plasma_cannon::PlasmaCannonPlugin,
+        unguided_missile::UnguidedMissilePlugin,
+    },
+    time_control::TimeController,
+    ParallaxProtocolArenaPlugin,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const USAGE: &str =
+    "Usage: headless <snapshot.json | --generate BODIES> [ticks]";
+
+/// Ticks to run when none are given
+const DEFAULT_TICKS: u64 = 600;
+
+/// Seed of generated scenarios, so that runs can be compared
+const SEED: u64 = 123;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let snapshot = match args.next().context(USAGE)?.as_str() {
+        "--generate" => {
+            let bodies = args.next().context(USAGE)?.parse().context(USAGE)?;
+            generate_scenario(bodies, SEED)
+        }
+        path => Snapshot::load(path)?,
+    };
+    let ticks = args
+        .next()
+        .map(|ticks| ticks.parse::<u64>())
+        .transpose()
+        .context(USAGE)?
+        .unwrap_or(DEFAULT_TICKS);
+
+    let mut app = headless_app(&snapshot);
+    let start_tick = snapshot.config.current_tick;
+    println!(
+        "Running {} bodies for {ticks} ticks from tick {start_tick}",
+        snapshot.bodies.len()
+    );
+    let start = Instant::now();
+    for _ in 0..ticks {
+        app.update();
+    }
+    let elapsed = start.elapsed();
+
+    let world = app.world_mut();
+    let end_tick = world.resource::<SimulationConfig>().current_tick;
+    let mut states = world.query::<(Entity, &PhysicsState)>();
+    let mut states = states.iter(world).collect::<Vec<_>>();
+    states.sort_by_key(|(entity, _)| *entity);
+    for (entity, state) in &states {
+        println!(
+            "{entity}: pos ({:.3}, {:.3}) vel ({:.3}, {:.3}) rotation {:.3} \
+             health {:.3}",
+            state.pos.x,
+            state.pos.y,
+            state.vel.x,
+            state.vel.y,
+            state.rotation,
+            state.health
+        );
+    }
+    println!(
+        "Ran ticks {start_tick}..{end_tick} in {:.3}s, {:.1} ticks/s, {} \
+         bodies left",
+        elapsed.as_secs_f64(),
+        ticks as f64 / elapsed.as_secs_f64(),
+        states.len()
+    );
+    Ok(())
+}
+
+/// App running `snapshot` a tick per update
+fn headless_app(snapshot: &Snapshot) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            ParallaxProtocolArenaPlugin {
+                config: snapshot.config.clone(),
+                // Run a tick per update instead of waiting on the fixed
+                // timestep
+                physics: PhysicsSimulationPlugin {
+                    should_keep_alive: false,
+                    is_test: true,
+                },
+                client: None,
+            },
+            PlasmaCannonPlugin,
+            UnguidedMissilePlugin,
+        ))
+        .insert_resource(PhysicsEnabled);
+    snapshot.restore(app.world_mut());
+    // A snapshot taken while paused would never advance
+    app.world_mut().resource_mut::<TimeController>().resume();
+    app
+}
+
+/// Field of `bodies` drifting bodies on a jittered grid, so that they start
+/// apart but run into each other over time
+fn generate_scenario(bodies: usize, seed: u64) -> Snapshot {
+    const SPACING: f32 = 40.;
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut world = World::new();
+    world.insert_resource(SimulationConfig::default());
+    let columns = (bodies as f32).sqrt().ceil() as usize;
+    for index in 0..bodies {
+        let cell =
+            Vec2::new((index % columns) as f32, (index / columns) as f32);
+        let jitter =
+            Vec2::new(rng.gen_range(-10. ..10.), rng.gen_range(-10. ..10.));
+        let vel =
+            Vec2::new(rng.gen_range(-20. ..20.), rng.gen_range(-20. ..20.));
+        let size = rng.gen_range(2. ..8.);
+        world.spawn((
+            PhysicsBundle::new_basic(
+                0,
+                cell * SPACING + jitter,
+                vel,
+                rng.gen_range(0. ..TAU),
+                0.,
+                size * size,
+                Vec2::splat(size),
+            ),
+            Material::default(),
+        ));
+    }
+    Snapshot::capture(&mut world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_scenario_runs() {
+        let snapshot = generate_scenario(50, SEED);
+        assert_eq!(snapshot.bodies.len(), 50);
+        // Generated from the seed alone
+        assert_eq!(generate_scenario(50, SEED), snapshot);
+
+        let mut app = headless_app(&snapshot);
+        for _ in 0..5 {
+            app.update();
+        }
+        let world = app.world_mut();
+        assert_eq!(world.resource::<SimulationConfig>().current_tick, 5);
+        let mut states = world.query::<&PhysicsState>();
+        assert!(states.iter(world).any(|state| state.vel != RealVec2::ZERO));
+        assert!(states
+            .iter(world)
+            .all(|state| state.pos.to_vec2().is_finite()));
+    }
+}
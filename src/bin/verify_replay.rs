@@ -0,0 +1,46 @@
+//! Replay a match recording headless and check it ends in the recorded states
+//!
+//! Usage: verify_replay <recording.json>
+
+use std::process::ExitCode;
+
+use anyhow::Context;
+use parallax_protocol_arena::{
+    replay::{verify, Recording, ReplayError},
+    utils::VersionedJson,
+};
+
+fn main() -> anyhow::Result<ExitCode> {
+    let path = std::env::args()
+        .nth(1)
+        .context("Usage: verify_replay <recording.json>")?;
+    let recording = Recording::load(&path)?;
+
+    match verify(&recording) {
+        Ok(()) => {
+            println!(
+                "Replay matches: {} bodies over ticks {}..={}",
+                recording.final_states.len(),
+                recording.start_tick,
+                recording.end_tick
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(ReplayError::UnknownEntity(entity)) => {
+            eprintln!(
+                "Recording refers to entity {entity} with no recorded body"
+            );
+            Ok(ExitCode::FAILURE)
+        }
+        Err(ReplayError::Diverged(mismatches)) => {
+            for mismatch in &mismatches {
+                eprintln!(
+                    "Entity {} diverged\n  recorded: {:?}\n  replayed: {:?}",
+                    mismatch.entity, mismatch.recorded, mismatch.replayed
+                );
+            }
+            eprintln!("Replay diverged for {} bodies", mismatches.len());
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
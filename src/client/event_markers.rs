@@ -1,7 +1,7 @@
 //! Timeline event visualization and editing
 //!
-//! This module provides visual representation and interaction for historical control
-//! points in the spacecraft timeline. It manages:
+//! This module provides visual representation and interaction for historical
+//! control points in the spacecraft timeline. It manages:
 //!
 //! - Visual markers for past control inputs (thrust, rotation, etc.)
 //! - Drag-and-drop editing of historical control points
@@ -9,7 +9,8 @@
 //! - Timeline event synchronization
 //!
 //! # Marker Types
-//! Different types of control inputs are represented by distinct visual markers:
+//! Different types of control inputs are represented by distinct visual
+//! markers:
 //! - Arrows for thrust inputs
 //! - Arc arrows for rotation changes
 //! - Crosses for collision events
@@ -26,7 +27,7 @@
 //! - Removes markers for deleted events
 //! - Updates marker positions based on physics state
 
-use super::{EntityTimeline, ScreenLenToWorld};
+use super::{GenericSparseTimeline, ScreenLenToWorld};
 use crate::{
     client::trajectory::TrajectoryPreview,
     physics::{
@@ -56,23 +57,19 @@ impl Plugin for EventMarkerPlugin {
             .add_systems(PreUpdate, add_marker_map)
             .add_systems(
                 FixedPostUpdate,
-                (
-                    EntityTimeline::<TimelineEventMarker>::clear_system,
-                    sync_timeline_markers,
-                ),
+                (MarkerEntityTimeline::clear_system, sync_timeline_markers),
             )
             .add_systems(Update, render_timeline_events);
     }
 }
 
-type MarkerEntityTimeline = EntityTimeline<TimelineEventMarker>;
+/// Marker entities for each tick, in the same order as the tick's inputs
+type MarkerEntityTimeline =
+    GenericSparseTimeline<Vec<Entity>, TimelineEventMarker>;
 
 fn add_marker_map(
     mut commands: Commands,
-    timelines: Query<
-        Entity,
-        (Without<EntityTimeline<TimelineEventMarker>>, With<Timeline>),
-    >,
+    timelines: Query<Entity, (Without<MarkerEntityTimeline>, With<Timeline>)>,
 ) {
     for entity in timelines.iter() {
         commands
@@ -96,11 +93,11 @@ impl TimelineEventMarker {
                 tick,
                 craft,
                 input,
-                pos: phys.pos,
-                rot: phys.rotation,
+                pos: phys.pos.to_vec2(),
+                rot: phys.rotation.to_f32(),
             },
             Sprite::from_color(Srgba::new(0.1, 0.1, 0.1, 0.9), vec2(1., 1.)),
-            Transform::from_translation(phys.pos.extend(10.)),
+            Transform::from_translation(phys.pos.to_vec2().extend(10.)),
         )
     }
 }
@@ -109,11 +106,7 @@ impl TimelineEventMarker {
 /// Should be run after simulation update
 fn sync_timeline_markers(
     mut commands: Commands,
-    mut timelines: Query<(
-        Entity,
-        &Timeline,
-        &mut EntityTimeline<TimelineEventMarker>,
-    )>,
+    mut timelines: Query<(Entity, &Timeline, &mut MarkerEntityTimeline)>,
     mut markers: Query<(Entity, &mut TimelineEventMarker)>,
     mut alive: Local<EntityHashSet>,
 ) {
@@ -123,56 +116,70 @@ fn sync_timeline_markers(
     for (craft_entity, timeline, mut marker_entity_timeline) in
         timelines.iter_mut()
     {
-        for (&tick, input) in timeline.input_events.iter() {
-            let mut spawn =
-                |marker_entity_timeline: &mut MarkerEntityTimeline| {
-                    let Some(phys) = timeline.future_states.get(&tick) else {
-                        warn!(
-                            "Trying to create event marker entity w/o state \
-                             for tick"
+        for (&tick, inputs) in timeline.input_events.iter() {
+            for (index, input) in inputs.iter().enumerate() {
+                let mut spawn =
+                    |marker_entity_timeline: &mut MarkerEntityTimeline| {
+                        let Some(phys) = timeline.state(tick) else {
+                            warn!(
+                                "Trying to create event marker entity w/o \
+                                 state for tick"
+                            );
+                            return;
+                        };
+
+                        let mut entity_commands =
+                            commands.spawn(TimelineEventMarker::bundle(
+                                &phys,
+                                craft_entity,
+                                input.clone(),
+                                tick,
+                            ));
+
+                        // add click handlers if
+                        // event is a control event
+                        configure_marker_observers(
+                            craft_entity,
+                            input.clone(),
+                            &mut entity_commands,
                         );
-                        return;
+                        let marker_e = entity_commands.id();
+                        alive.insert(marker_e);
+                        let tick_markers =
+                            marker_entity_timeline.map.entry(tick).or_default();
+                        match tick_markers.get_mut(index) {
+                            Some(slot) => *slot = marker_e,
+                            None => tick_markers.push(marker_e),
+                        }
                     };
 
-                    let mut entity_commands =
-                        commands.spawn(TimelineEventMarker::bundle(
-                            phys,
-                            craft_entity,
-                            input.clone(),
-                            tick,
-                        ));
-
-                    // add click handlers if
-                    // event is a control event
-                    configure_marker_observers(
-                        craft_entity,
-                        input.clone(),
-                        &mut entity_commands,
-                    );
-                    let marker_e = entity_commands.id();
-                    alive.insert(marker_e);
-                    marker_entity_timeline.insert(tick, marker_e);
+                let Some(marker_e) = marker_entity_timeline
+                    .get(tick)
+                    .and_then(|tick_markers| tick_markers.get(index))
+                    .copied()
+                else {
+                    spawn(&mut marker_entity_timeline);
+                    continue;
                 };
 
-            let Some(marker_e) = marker_entity_timeline.get(tick) else {
-                spawn(&mut marker_entity_timeline);
-                continue;
-            };
-
-            let Ok((_, mut marker)) = markers.get_mut(*marker_e) else {
-                spawn(&mut marker_entity_timeline);
-                continue;
-            };
+                let Ok((_, mut marker)) = markers.get_mut(marker_e) else {
+                    spawn(&mut marker_entity_timeline);
+                    continue;
+                };
 
-            alive.insert(*marker_e);
-            let Some(phys) = timeline.future_states.get(&tick) else {
-                warn!("Event marker exists, but state does not");
-                panic!("Event marker exists, but state does not");
-            };
-            if marker.input != *input {
-                marker.input = input.clone();
-                marker.pos = phys.pos;
-                marker.rot = phys.rotation;
+                alive.insert(marker_e);
+                let Some(phys) = timeline.state(tick) else {
+                    warn!("Event marker exists, but state does not");
+                    panic!("Event marker exists, but state does not");
+                };
+                if marker.input != *input {
+                    marker.input = input.clone();
+                    marker.pos = phys.pos.to_vec2();
+                    marker.rot = phys.rotation.to_f32();
+                }
+            }
+            if let Some(tick_markers) = marker_entity_timeline.get_mut(tick) {
+                tick_markers.truncate(inputs.len());
             }
         }
     }
@@ -235,12 +242,16 @@ fn configure_marker_observers(
                 timeline: Timeline {
                     input_events: timeline.input_events.clone(),
                     sim_events: default(),
-                    future_states: BTreeMap::from_iter(
-                        timeline
-                            .future_states
-                            .range(0..=last_computed_tick)
-                            .map(|(k, v)| (k.clone(), v.clone())),
-                    ),
+                    broken_beams: default(),
+                    rejected_connects: default(),
+                    fragmentations: default(),
+                    future_states: timeline
+                        .states()
+                        .take_while(|(tick, _)| *tick <= last_computed_tick)
+                        .map(|(tick, state)| (tick, state.into_owned()))
+                        .collect(),
+                    coasts: default(),
+                    coarse_from: None,
                     last_computed_tick,
                     last_updated_range: None,
                 },
@@ -270,24 +281,21 @@ fn configure_marker_observers(
 
             let mut marker = markers.get_mut(trigger.entity()).unwrap();
             let old_tick = marker.tick;
-            let (new_tick, err_dist) =
-                preview.timeline.future_states.iter().fold(
-                    (marker.tick, f32::INFINITY),
-                    |(best_tick, shortest_dist), (tick, phys)| {
-                        let dist = phys.pos.distance_squared(new_marker_pos);
-                        if dist < shortest_dist {
-                            (*tick, dist)
-                        } else {
-                            (best_tick, shortest_dist)
-                        }
-                    },
-                );
+            let (new_tick, err_dist) = preview.timeline.states().fold(
+                (marker.tick, f32::INFINITY),
+                |(best_tick, shortest_dist), (tick, phys)| {
+                    let dist =
+                        phys.pos.to_vec2().distance_squared(new_marker_pos);
+                    if dist < shortest_dist {
+                        (tick, dist)
+                    } else {
+                        (best_tick, shortest_dist)
+                    }
+                },
+            );
 
-            preview.timeline.input_events.remove(&old_tick);
-            preview
-                .timeline
-                .input_events
-                .insert(new_tick, marker.input.clone());
+            preview.timeline.remove_input_event(old_tick, marker.input);
+            preview.timeline.add_input_event(new_tick, marker.input);
 
             // preview.timeline.lookahead(
             //     craft_entity,
@@ -296,10 +304,10 @@ fn configure_marker_observers(
             //     simulation_config.prediction_ticks,
             // );
 
-            let phys = preview.timeline.future_states.get(&new_tick).unwrap();
+            let phys = preview.timeline.state(new_tick).unwrap().into_owned();
             marker.tick = new_tick;
-            marker.pos = phys.pos;
-            marker.rot = phys.rotation;
+            marker.pos = phys.pos.to_vec2();
+            marker.rot = phys.rotation.to_f32();
 
             preview.timeline.last_computed_tick = (new_tick.min(old_tick)) - 1;
         },
@@ -307,10 +315,7 @@ fn configure_marker_observers(
     cmds.observe(
         move |mut trigger: Trigger<Pointer<DragEnd>>,
               mut commands: Commands,
-              mut timelines: Query<(
-            &Timeline,
-            &mut EntityTimeline<TimelineEventMarker>,
-        )>,
+              mut timelines: Query<(&Timeline, &mut MarkerEntityTimeline)>,
               camera_q: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
               mut markers: Query<(
             &mut TimelineEventMarker,
@@ -347,16 +352,26 @@ fn configure_marker_observers(
 
             // let phys = timeline.future_states.get(&new_tick).unwrap();
 
-            debug_assert_eq!(
-                tick_to_marker_e.map.remove(&tick),
-                Some(trigger.entity())
-            );
+            let old_tick_markers =
+                tick_to_marker_e.map.entry(tick).or_default();
+            let removed = old_tick_markers
+                .iter()
+                .position(|e| *e == trigger.entity())
+                .map(|index| old_tick_markers.remove(index));
+            debug_assert_eq!(removed, Some(trigger.entity()));
+            if old_tick_markers.is_empty() {
+                tick_to_marker_e.map.remove(&tick);
+            }
             // TODO: this is error prone, we should come up with something
             // better abstracted
             // marker.tick = new_tick;
             // marker.pos = phys.pos;
             // marker.rot = phys.rotation;
-            tick_to_marker_e.insert(marker.tick, trigger.entity());
+            tick_to_marker_e
+                .map
+                .entry(marker.tick)
+                .or_default()
+                .push(trigger.entity());
             commands.send_event(TimelineEventRemovalRequest {
                 input,
                 entity: craft_entity,
@@ -416,7 +431,7 @@ impl MarkerVisual {
                 relative_rot: 0.,
                 color: css::PALE_GREEN,
             },
-            SetRotation(new_rot) => ArcArrow {
+            SetRotation(new_rot) | RotateTo(new_rot) => ArcArrow {
                 sweep: (new_rot - event.rot) % (2. * PI),
                 color: css::DARK_BLUE,
             },
@@ -429,7 +444,7 @@ impl MarkerVisual {
                 relative_rot: new_rot - event.rot,
                 color: css::LIGHT_GREEN,
             },
-            ElasticBeamConnect(entity) => todo!(),
+            ElasticBeamConnect(entity, _) => todo!(),
             ElasticBeamDisconnect(entity) => todo!(),
             // Collision(collision) => Cross { color: css::RED },
         }
@@ -36,14 +36,19 @@ use super::{
 use crate::{
     physics::{
         collisions::{Collider, SpatialIndex},
+        rewind,
         ControlInput,
         SimulationConfig,
         TimelineEventRemovalRequest,
         TimelineEventRequest,
     },
     prelude::*,
+    time_control::TimeController,
 };
 
+/// How long changing speed with the bracket keys takes to ease in
+const DILATION_RAMP: Duration = Duration::from_millis(250);
+
 #[derive(Default, Clone, Copy)]
 pub struct InputHandlerPlugin;
 
@@ -152,32 +157,35 @@ fn handle_input_mode(
 fn handle_plasma_cannon_mode() {}
 
 fn time_dilation_control(
+    mut commands: Commands,
     keys: Res<ButtonInput<KeyCode>>,
-    mut config: ResMut<SimulationConfig>,
-    mut time: ResMut<Time<Fixed>>,
+    config: Res<SimulationConfig>,
+    mut time: ResMut<TimeController>,
 ) {
-    let mut changed = false;
+    let mut target = None;
 
     if keys.just_pressed(KeyCode::BracketRight) {
-        config.time_dilation *= 2.0;
-        changed = true;
+        target = Some(time.target() * 2.0);
     }
     if keys.just_pressed(KeyCode::BracketLeft) {
-        config.time_dilation *= 0.5;
-        changed = true;
+        target = Some(time.target() * 0.5);
     }
     if keys.just_pressed(KeyCode::KeyP) {
-        config.paused = !config.paused;
+        time.toggle_pause();
+    }
+    // Jump back as far as history allows to retry a maneuver
+    if keys.just_pressed(KeyCode::KeyR) {
+        commands.queue(rewind(config.oldest_retained_tick()));
     }
 
-    if changed {
-        time.set_timestep_hz(
-            config.ticks_per_second as f64 * config.time_dilation as f64,
-        );
+    if let Some(target) = target {
+        time.ramp_to(target, DILATION_RAMP);
+        // Clamped to the supported range
+        let target = time.target();
         info!(
             "Simulation speed: {:.1}x ({}Hz)",
-            config.time_dilation,
-            config.ticks_per_second as f64 * config.time_dilation as f64
+            target,
+            config.ticks_per_second as f64 * target as f64
         );
     }
 }
@@ -213,12 +221,16 @@ fn handle_engine_input(
             timeline: Timeline {
                 input_events: timeline.input_events.clone(),
                 sim_events: default(),
-                future_states: BTreeMap::from_iter(
-                    timeline
-                        .future_states
-                        .range(0..=seg.end_tick)
-                        .map(|(k, v)| (k.clone(), v.clone())),
-                ),
+                broken_beams: default(),
+                rejected_connects: default(),
+                fragmentations: default(),
+                future_states: timeline
+                    .states()
+                    .take_while(|(tick, _)| *tick <= seg.end_tick)
+                    .map(|(tick, state)| (tick, state.into_owned()))
+                    .collect(),
+                coasts: default(),
+                coarse_from: None,
                 last_computed_tick: seg.start_tick,
                 last_updated_range: None,
             },
@@ -245,8 +257,9 @@ fn handle_engine_input(
         let mut world_drag = drag.distance;
         world_drag.y *= -1.;
 
-        // Patch preview timeline
-        preview.timeline.add_input_event(
+        // Patch preview timeline, replacing the input from the previous drag
+        // event
+        preview.timeline.replace_input_event(
             seg.end_tick,
             ControlInput::SetThrustAndRotation(
                 (world_drag.length() / THRUST_SCALE).min(1.),
@@ -1,6 +1,9 @@
 use std::marker::PhantomData;
 
-use crate::{physics::collisions::Collider, prelude::*};
+use crate::{
+    physics::{collisions::Collider, ArenaBounds, BoundaryMode},
+    prelude::*,
+};
 
 pub mod event_markers;
 pub mod input_handler;
@@ -32,6 +35,7 @@ impl Plugin for ClientPlugin {
             self.trajectory,
         ))
         .insert_resource(ScreenLenToWorld(1.))
+        .add_systems(Update, render_arena_bounds)
         .add_systems(
             PreUpdate,
             (calc_screen_length_to_world.pipe(eat_error),)
@@ -61,6 +65,24 @@ fn calc_screen_length_to_world(
     Ok(())
 }
 
+/// Outline the arena, colored by what happens at its edge
+fn render_arena_bounds(mut gizmos: Gizmos, bounds: Option<Res<ArenaBounds>>) {
+    let Some(bounds) = bounds else {
+        return;
+    };
+    let color = match bounds.mode {
+        BoundaryMode::Wrap => css::DEEP_SKY_BLUE,
+        BoundaryMode::Reflect => css::GOLD,
+        BoundaryMode::Destroy => css::CRIMSON,
+    };
+    let rect = bounds.rect();
+    gizmos.rect_2d(
+        Isometry2d::from_translation(rect.center()),
+        rect.size(),
+        color,
+    );
+}
+
 pub fn eat_error<T>(r: In<Result<(), T>>) {
     let _ = r;
 }
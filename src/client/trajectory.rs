@@ -6,8 +6,20 @@ use bevy::{
 use super::{ensure_added, EntityTimeline, ScreenLenToWorld};
 use crate::{
     physics::{
+        bounds::sweep_start,
+        collisions::{
+            calculate_collision_result,
+            find_contact,
+            is_closing,
+            Collider,
+            Material,
+            SpatialItem,
+        },
         timeline::apply_inputs_and_integrate_phys,
+        ArenaBounds,
         ControlInput,
+        GravityField,
+        GravitySource,
         SimulationConfig,
     },
     prelude::*,
@@ -79,13 +91,16 @@ impl Plugin for TrajectoryPlugin {
 }
 
 fn preview_lookahead(
-    colliders: Query<&crate::physics::collisions::Collider>,
+    colliders: Query<&Collider>,
+    materials: Query<&Material>,
+    gravity_sources: Query<(Entity, &GravitySource, &Timeline)>,
     mut preview: ResMut<TrajectoryPreview>,
     simulation_config: Res<SimulationConfig>,
     spatial_index: Res<crate::physics::collisions::SpatialIndex>,
+    bounds: Option<Res<ArenaBounds>>,
 ) {
+    let bounds = bounds.as_deref();
     let entity = preview.entity;
-    let seconds_per_tick = 1.0 / simulation_config.ticks_per_second as f32;
     let collider = colliders.get(preview.entity).unwrap();
 
     let timeline = &mut preview.timeline;
@@ -99,19 +114,59 @@ fn preview_lookahead(
         simulation_config.current_tick + simulation_config.prediction_ticks;
 
     for tick in start_tick..=end_tick {
+        let gravity = GravityField::at_tick(tick - 1, gravity_sources.iter());
         apply_inputs_and_integrate_phys(
             tick,
-            seconds_per_tick,
+            &simulation_config,
+            &gravity,
             entity,
             timeline,
             collider,
             None,
         );
+        if let Some(bounds) = bounds {
+            bounds.apply_to_timeline(tick, timeline);
+        }
 
-        if let Some((_, item)) = spatial_index.collides(entity, tick, timeline.state(tick).unwrap().pos, collider) {
-            info!("Preview collision at tick {tick}");
-            timeline.state_mut(tick).unwrap().alive = false;
+        let from = timeline.state(tick - 1).unwrap().pos.to_vec2();
+        let to = timeline.state(tick).unwrap().pos.to_vec2();
+        let from = sweep_start(bounds, from, to);
+        let state = timeline.state_mut(tick).unwrap();
+        if !state.alive {
+            continue;
         }
+        let Some((_, other)) = spatial_index.sweep(
+            entity,
+            tick,
+            from,
+            to,
+            state.rotation.to_f32(),
+            collider,
+        ) else {
+            continue;
+        };
+        let item = SpatialItem::swept(entity, from, state);
+        if !is_closing(&item, &other) {
+            continue;
+        }
+        let Ok(other_col) = colliders.get(other.entity) else {
+            continue;
+        };
+        let Some(contact) =
+            find_contact((&item, collider), (&other, other_col))
+        else {
+            continue;
+        };
+        info!("Preview collision at tick {tick}");
+        let material =
+            |e: Entity| materials.get(e).copied().unwrap_or_default();
+        let (result, _) = calculate_collision_result(
+            (&item, &material(entity)),
+            (&other, &material(other.entity)),
+            &contact,
+            simulation_config.seconds_per_tick(),
+        );
+        state.apply_collision_result(&result);
     }
 }
 
@@ -145,8 +200,10 @@ fn sync_trajectory_segments(
     mut crafts: Query<(Entity, &Timeline, &mut TrajectorySegmentTimeline)>,
     mut segments: Query<(Entity, &mut TrajectorySegment, &mut Transform)>,
     sim_config: Res<SimulationConfig>,
+    bounds: Option<Res<ArenaBounds>>,
     // TODO: create multi-tick segments based off ticks_per_second
 ) {
+    let bounds = bounds.as_deref();
     for (craft_entity, timeline, mut segment_timeline) in crafts.iter_mut() {
         // STEP 1: ensure there is a segment for each updated tick
         let Some(range) = timeline.last_updated_range.clone() else {
@@ -159,14 +216,21 @@ fn sync_trajectory_segments(
                 break;
             }
 
+            // Wrapped segments start beyond the edge they come in through
+            let end_pos = timeline.state(tick).unwrap().pos.to_vec2();
+            let start_pos = sweep_start(
+                bounds,
+                timeline.state(tick - 1).unwrap().pos.to_vec2(),
+                end_pos,
+            );
             let mut spawn =
                 |segment_timeline: &mut TrajectorySegmentTimeline| {
                     let segment = TrajectorySegment {
                         craft_entity,
                         start_tick: tick - 1,
                         end_tick: tick,
-                        start_pos: timeline.state(tick - 1).unwrap().pos,
-                        end_pos: timeline.state(tick).unwrap().pos,
+                        start_pos,
+                        end_pos,
                         is_preview: false,
                     };
                     segment_timeline.insert(tick, segment.spawn(&mut commands));
@@ -183,8 +247,8 @@ fn sync_trajectory_segments(
                 continue;
             };
 
-            segment.start_pos = timeline.state(tick - 1).unwrap().pos;
-            segment.end_pos = timeline.state(tick).unwrap().pos;
+            segment.start_pos = start_pos;
+            segment.end_pos = end_pos;
             transform.translation =
                 ((segment.start_pos + segment.end_pos) / 2.0).to3();
         }
@@ -219,6 +283,7 @@ fn sync_trajectory_segments(
 fn sync_preview_segments(
     mut commands: Commands,
     preview: Option<Res<TrajectoryPreview>>,
+    bounds: Option<Res<ArenaBounds>>,
     mut seg_ents: Local<EntityHashSet>,
 ) {
     // Despawn all preview segments 
@@ -234,14 +299,13 @@ fn sync_preview_segments(
 
     let mut iter = preview
         .timeline
-        .future_states
-        .iter()
+        .states()
         .take_while(|s| s.1.alive)
-        .map(|(t, s)| (t, s.pos))
+        .map(|(t, s)| (t, s.pos.to_vec2()))
         .peekable();
 
-    while let Some((&start_tick, start_pos)) = iter.next() {
-        let Some((&end_tick, end_pos)) = iter.peek().copied() else {
+    while let Some((start_tick, start_pos)) = iter.next() {
+        let Some((end_tick, end_pos)) = iter.peek().copied() else {
             break;
         };
 
@@ -249,7 +313,7 @@ fn sync_preview_segments(
             craft_entity: preview.entity,
             start_tick,
             end_tick,
-            start_pos,
+            start_pos: sweep_start(bounds.as_deref(), start_pos, end_pos),
             end_pos,
             is_preview: true,
         };
@@ -9,7 +9,15 @@ use serde::Deserialize;
 use serde_json::Value;
 
 use crate::{
-    physics::{PhysicsBundle, PhysicsState, SimulationConfig},
+    physics::{
+        collisions::{Collider, Material},
+        Breakable,
+        Fragment,
+        PhysicsBundle,
+        PhysicsState,
+        Real,
+        SimulationConfig,
+    },
     prelude::*,
 };
 
@@ -18,16 +26,43 @@ use crate::{
 pub struct AsteroidAssets {
     texture: Handle<Image>,
     layout: Handle<TextureAtlasLayout>,
-    _rects: Vec<URect>,
+    rects: Vec<URect>,
+}
+
+impl AsteroidAssets {
+    /// Collision radius of the sprite at `index` when drawn at unit scale
+    ///
+    /// Asteroids are roughly round, so this is the mean of the slice's half
+    /// width and half height
+    pub fn radius(&self, index: usize) -> f32 {
+        self.rects[index].size().as_vec2().element_sum() / 4.
+    }
 }
 
 #[derive(Component, Reflect, Debug, Default)]
+#[require(Breakable)]
 pub struct Asteroid;
 
 #[derive(Component, Reflect, Debug)]
 #[require(Asteroid)]
 pub struct SmallAsteroid;
 
+/// Asteroid mass in kg per unit of sprite scale
+const MASS_PER_SIZE: f32 = 10.;
+
+/// Asteroid health per unit of sprite scale
+const HEALTH_PER_SIZE: f32 = 2.;
+
+/// Rock bounces a little and shrugs off twice the default impact energy
+const ROCK: Material = Material {
+    restitution: 0.3,
+    friction: 0.6,
+    toughness: 200.,
+};
+
+/// Sprite sheet slice drawn for small asteroids
+const SPRITE_INDEX: usize = 0;
+
 impl SmallAsteroid {
     pub fn bundle(
         tick: u64,
@@ -36,28 +71,36 @@ impl SmallAsteroid {
         velocity: Vec2,
         size: f32,
     ) -> impl Bundle {
+        let radius = assets.radius(SPRITE_INDEX) * size;
         (
             Self,
-            Sprite::from_atlas_image(
-                assets.texture.clone(),
-                TextureAtlas {
-                    layout: assets.layout.clone(),
-                    index: 0,
-                },
-            ),
+            Self::sprite(assets),
             Transform::from_scale(Vec3::new(size, size, size))
                 .with_translation(position.to3()),
             PhysicsBundle::from_state(
                 tick,
                 PhysicsState {
-                    pos: position,
-                    vel: velocity,
-                    mass: 10. * size,
+                    pos: RealVec2::from_vec2(position),
+                    vel: RealVec2::from_vec2(velocity),
+                    mass: Real::from_f32(MASS_PER_SIZE * size),
+                    health: HEALTH_PER_SIZE * size,
                     alive: true,
                     ..default()
                 },
-                Vec2::new(28. * size, 28. * size),
-            ),
+                Vec2::splat(radius * 2.),
+            )
+            .with_collider(Collider::circle(radius)),
+            ROCK,
+        )
+    }
+
+    fn sprite(assets: &AsteroidAssets) -> Sprite {
+        Sprite::from_atlas_image(
+            assets.texture.clone(),
+            TextureAtlas {
+                layout: assets.layout.clone(),
+                index: SPRITE_INDEX,
+            },
         )
     }
 
@@ -79,7 +122,37 @@ impl Plugin for AsteroidPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<AsteroidSpriteLayout>();
         app.register_type::<Asteroid>();
+        app.register_type::<Fragment>();
         app.add_systems(Startup, setup);
+        app.add_systems(Update, add_fragment_sprites);
+    }
+}
+
+/// Draw fragments of asteroids as small asteroids
+///
+/// The physics layer spawns the fragment bodies, see `sync_fragments`, this
+/// only adds the sprite, scaled to the fragment's collider
+fn add_fragment_sprites(
+    mut commands: Commands,
+    assets: Option<Res<AsteroidAssets>>,
+    mut fragments: Query<
+        (Entity, &Fragment, &Collider, &mut Transform),
+        Added<Fragment>,
+    >,
+    asteroids: Query<(), With<Asteroid>>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+    for (entity, fragment, collider, mut transform) in fragments.iter_mut() {
+        if !asteroids.contains(fragment.parent) {
+            continue;
+        }
+        let size = collider.bounding_radius() / assets.radius(SPRITE_INDEX);
+        transform.scale = Vec3::splat(size);
+        commands
+            .entity(entity)
+            .insert((SmallAsteroid, SmallAsteroid::sprite(&assets)));
     }
 }
 
@@ -118,6 +191,6 @@ fn setup(
     commands.insert_resource(AsteroidAssets {
         texture,
         layout: texture_atlas_layout,
-        _rects: rects,
+        rects,
     });
 }
@@ -4,6 +4,7 @@ use crate::prelude::*;
 
 pub mod asteroid;
 pub mod frigate;
+pub mod ship;
 
 pub struct CraftsPlugin;
 
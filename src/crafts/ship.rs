@@ -0,0 +1,92 @@
+//! The player's ship
+
+use crate::{
+    physics::{PhysicsState, Real},
+    prelude::*,
+};
+
+/// Mass of a ship with an empty tank, in kilograms
+pub const DRY_MASS: f32 = 0.6;
+/// Propellant a ship starts with, in kilograms
+pub const PROPELLANT: f32 = 0.4;
+/// Effective exhaust velocity of a ship's engine, in meters/second
+///
+/// A full tank lasts 16 seconds at full thrust, for about 1km/s of delta-v
+pub const EXHAUST_VELOCITY: f32 = 2000.;
+
+/// Physics state of a ship at rest at `pos` with a full tank
+pub fn ship_state(pos: Vec2) -> PhysicsState {
+    PhysicsState {
+        pos: RealVec2::from_vec2(pos),
+        vel: RealVec2::ZERO,
+        ang_vel: Real::ZERO,
+        rotation: Real::ZERO,
+        mass: Real::from_f32(DRY_MASS + PROPELLANT),
+        current_thrust: 0.,
+        max_thrust: 50.,
+        propellant: Real::from_f32(PROPELLANT),
+        exhaust_velocity: EXHAUST_VELOCITY,
+        moment_of_inertia: 1.,
+        max_torque: 2.,
+        target_rotation: None,
+        health: 100.,
+        alive: true,
+        elastic_beams: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assertables::assert_abs_diff_le_x;
+
+    use super::*;
+    use crate::physics::{
+        collisions::SpatialIndex,
+        timeline::compute_future_states,
+        ControlInput,
+        Integrator,
+        SimulationConfig,
+    };
+
+    #[test]
+    fn test_thrust_cuts_out_when_tank_empties() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                ticks_per_second: 10,
+                prediction_ticks: 300,
+                integrator: Integrator::Rk4,
+                ..default()
+            })
+            .add_systems(Update, compute_future_states);
+        let ship = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                ship_state(Vec2::ZERO),
+                Vec2::splat(2.),
+                0,
+                [(1, ControlInput::SetThrust(0.7))],
+            ))
+            .id();
+        app.update();
+
+        // 35N at 2000m/s burns 0.0175kg/s, so the tank lasts 22.86s and runs
+        // dry part way through the 229th tick
+        let timeline = app.world().get::<Timeline>(ship).unwrap();
+        let burning = timeline.state(228).unwrap();
+        assert_eq!(burning.current_thrust, 0.7);
+        assert!(burning.propellant.to_f32() > 0.);
+        let empty = timeline.state(229).unwrap();
+        assert_eq!(empty.current_thrust, 0.);
+        assert_eq!(empty.propellant.to_f32(), 0.);
+        assert!(empty.is_out_of_propellant());
+        assert_abs_diff_le_x!(empty.mass.to_f32(), DRY_MASS, 1e-4);
+
+        // Coasts from there on
+        let later = timeline.state(260).unwrap();
+        assert_eq!(later.vel, empty.vel);
+        let delta_v = ship_state(Vec2::ZERO).delta_v();
+        assert_abs_diff_le_x!(empty.vel.to_vec2().x, delta_v, 0.5);
+    }
+}
@@ -5,18 +5,29 @@ pub mod client;
 pub mod crafts;
 pub mod physics;
 pub mod prelude;
+pub mod replay;
+pub mod snapshot;
 pub mod subsystems;
+pub mod time_control;
 pub mod utils;
 
 use std::borrow::Cow;
 
 use bevy::{ecs::world::Command, gizmos::config};
 use client::ClientPlugin;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     client::{InputHandlerPlugin, TrajectoryPlugin},
-    physics::{PhysicsSimulationPlugin, SimulationConfig},
+    physics::{
+        PhysicsSimulationPlugin,
+        SimulationConfig,
+        SimulationSet,
+        SimulationUpdate,
+    },
     prelude::*,
+    replay::RecorderPlugin,
+    time_control::{update_time_controller, TimeController},
 };
 
 pub struct ParallaxProtocolArenaPlugin {
@@ -37,20 +48,31 @@ impl Default for ParallaxProtocolArenaPlugin {
 
 impl Plugin for ParallaxProtocolArenaPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(self.config.clone()).insert_resource(
-            Time::<Fixed>::from_hz(
-                self.config.ticks_per_second as f64
-                    * self.config.time_dilation as f64,
-            ),
+        let mut time = TimeController::new(self.config.time_dilation);
+        if self.config.paused {
+            time.pause();
+        }
+        app.insert_resource(self.config.clone())
+            .insert_resource(time)
+            .add_systems(PreUpdate, update_time_controller);
+        app.add_plugins((
+            Shape2dPlugin::default(),
+            self.physics.clone(),
+            RecorderPlugin,
+        ))
+        .add_systems(
+            SimulationUpdate,
+            health_despawn.in_set(SimulationSet::Cleanup),
         );
-        app.add_plugins((Shape2dPlugin::default(), self.physics.clone()));
         if let Some(client) = &self.client {
             app.add_plugins(client.clone());
         }
     }
 }
 
-#[derive(Component, Reflect, Clone, Debug)]
+#[derive(
+    Component, Reflect, Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
 pub struct Health(pub f64);
 
 pub fn send_event<E: Event>(e: E) -> impl Command {
@@ -59,7 +81,12 @@ pub fn send_event<E: Event>(e: E) -> impl Command {
     }
 }
 
-pub fn health_despawn(mut commands: Commands, query: Query<(Entity, &Health)>) {
+/// Despawn entities out of health, except for physics bodies which are
+/// despawned once they can't be rewound to, see `PhysicsState::alive`
+pub fn health_despawn(
+    mut commands: Commands,
+    query: Query<(Entity, &Health), Without<Timeline>>,
+) {
     for (e, h) in query.iter() {
         if h.0 <= 0.0001 {
             debug!("Despawning entity {e}");
@@ -30,14 +30,16 @@ use bevy_rand::{
 use collisions::{Collider, SpatialIndex};
 use parallax_protocol_arena::{
     client::{ClientPlugin, GraphicsEnabled},
-    crafts::{asteroid::AsteroidAssets, Faction},
-    health_despawn,
+    crafts::{asteroid::AsteroidAssets, ship::ship_state, Faction},
     physics::*,
     prelude::*,
+    replay::{finish_recording, start_recording},
+    snapshot::save_snapshot,
     subsystems::{
         plasma_cannon::{PlasmaCannon, PlasmaCannonPlugin},
         unguided_missile::{UnguidedMissile, UnguidedMissilePlugin},
     },
+    time_control::TimeController,
     ParallaxProtocolArenaPlugin,
     Selected,
 };
@@ -78,8 +80,14 @@ struct StartPopupUI;
 #[derive(Resource)]
 struct StartPopupTimer(Timer);
 
-#[derive(Resource, Default)]
-struct SlowMotionTimer(Option<Timer>);
+/// Seed of the global RNG
+const SEED: u64 = 123;
+
+/// Where the last match is recorded to
+const RECORDING_PATH: &str = "recordings/last_match.json";
+
+/// Where F5 saves the arena to, loadable by the headless runner
+const SNAPSHOT_PATH: &str = "snapshots/arena.json";
 
 fn main() {
     App::new()
@@ -96,7 +104,7 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
             bevy_pancam::PanCamPlugin,
-            EntropyPlugin::<WyRand>::with_seed(123u64.to_ne_bytes()),
+            EntropyPlugin::<WyRand>::with_seed(SEED.to_ne_bytes()),
             FrameTimeDiagnosticsPlugin::default(),
         ))
         .add_plugins((
@@ -107,6 +115,9 @@ fn main() {
                         ticks_per_second: tps,
                         time_dilation: 1.,
                         prediction_ticks: tps * 30,
+                        integrator: Integrator::VelocityVerlet,
+                        // Rewind up to 5 seconds to retry a maneuver
+                        history_ticks: tps * 5,
                         ..default()
                     }
                 })(),
@@ -123,6 +134,7 @@ fn main() {
             Update,
             (
                 exit_system,
+                save_snapshot_on_key,
                 fps_ui.run_if(on_real_timer(Duration::from_millis(200))),
                 update_time_display,
                 handle_game_over,
@@ -133,18 +145,14 @@ fn main() {
         )
         .add_systems(
             FixedUpdate,
-            (
-                health_despawn,
-                (check_victory, check_ship_death)
-                    .run_if(in_state(GameState::Playing)),
-            ),
+            (check_victory, check_ship_death)
+                .run_if(in_state(GameState::Playing)),
         )
         .add_systems(OnEnter(GameState::Loading), setup_start_popup)
         .add_systems(OnEnter(GameState::Playing), (setup_game, reset_camera))
         .add_systems(OnEnter(GameState::DeathScreen), setup_death_screen)
         .add_systems(OnEnter(GameState::Reset), cleanup_all_state)
         .init_resource::<BestTime>()
-        .init_resource::<SlowMotionTimer>()
         .run();
 }
 
@@ -157,6 +165,15 @@ pub fn exit_system(
     }
 }
 
+fn save_snapshot_on_key(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if keys.just_pressed(KeyCode::F5) {
+        commands.queue(save_snapshot(SNAPSHOT_PATH));
+    }
+}
+
 fn startup(mut commands: Commands) {
     commands.spawn((
         Camera2d,
@@ -197,6 +214,12 @@ fn setup_game(
     eprintln!("Setting up game");
     commands.insert_resource(GraphicsEnabled);
     commands.insert_resource(PhysicsEnabled);
+    // Asteroids drifting out of the race course are done with, and the
+    // finish line at x = 10000 is inside
+    commands.insert_resource(ArenaBounds::new(
+        Rect::new(-4000., -4000., 11000., 4000.),
+        BoundaryMode::Destroy,
+    ));
 
     let current_tick = sim_config.current_tick;
     let ship_e = commands
@@ -220,6 +243,9 @@ fn setup_game(
         sim_config,
         asteroid_assets,
     );
+
+    // Queued after the spawns so they are part of the recorded scenario
+    commands.queue(start_recording(SEED));
 }
 
 pub fn ship_bundle(
@@ -246,17 +272,7 @@ pub fn ship_bundle(
         PlasmaCannon::default(),
         UnguidedMissile::default(),
         PhysicsBundle::new_with_events(
-            PhysicsState {
-                pos,
-                vel: Vec2::ZERO,
-                ang_vel: 0.,
-                rotation: 0.,
-                mass: 1.,
-                current_thrust: 0.,
-                max_thrust: 50.,
-                alive: true,
-                elastic_beam: None,
-            },
+            ship_state(pos),
             Vec2::new(px, px),
             tick,
             [
@@ -342,7 +358,7 @@ fn check_victory(
         return;
     };
 
-    if physics.pos.x >= 10000.0 {
+    if physics.pos.to_vec2().x >= 10000.0 {
         game_over.send(GameOver { victory: true });
     }
 }
@@ -371,6 +387,7 @@ fn check_ship_death(
 }
 
 fn handle_game_over(
+    mut commands: Commands,
     mut game_over: EventReader<GameOver>,
     mut next_state: ResMut<NextState<GameState>>,
     sim_config: Res<SimulationConfig>,
@@ -388,6 +405,7 @@ fn handle_game_over(
                     .unwrap_or(current_time),
             );
         }
+        commands.queue(finish_recording(RECORDING_PATH));
         next_state.set(GameState::DeathScreen);
     }
 }
@@ -439,6 +457,10 @@ fn generate_asteroid_field_with_marker(
     let mut rng = GlobalEntropy::<WyRand>::default();
 
     let tick = sim_config.current_tick;
+    // Far from the ship, asteroids only need a rough prediction
+    let tps = sim_config.ticks_per_second;
+    let policy = PredictionPolicy::horizon(sim_config.prediction_ticks)
+        .with_coarse_step(tps, tps * 2, 500.);
 
     for _ in 0..1000 {
         commands.spawn((
@@ -457,6 +479,7 @@ fn generate_asteroid_field_with_marker(
                     .max(0.1)
                     .min(20.),
             ),
+            policy,
             GameEntity,
         ));
     }
@@ -584,35 +607,15 @@ fn handle_start_popup(
 }
 
 fn handle_slow_motion(
-    mut slow_motion: ResMut<SlowMotionTimer>,
-    time: Res<Time>,
-    mut fixed_time: ResMut<Time<Fixed>>,
     keys: Res<ButtonInput<KeyCode>>,
-    mut sim_config: ResMut<SimulationConfig>,
+    mut time: ResMut<TimeController>,
 ) {
-    // Start slow motion when space is pressed
+    // Slow down for 3 seconds when space is pressed
     if keys.just_pressed(KeyCode::Space) {
-        slow_motion.0 = Some(Timer::from_seconds(3.0, TimerMode::Once));
-        sim_config.time_dilation = 0.125;
-        // Update the fixed timestep
-        fixed_time.set_timestep_hz(
-            sim_config.ticks_per_second as f64
-                * sim_config.time_dilation as f64,
+        time.slow_motion(
+            0.125,
+            Duration::from_secs(3),
+            Duration::from_millis(250),
         );
     }
-
-    // Handle timer if it exists
-    if let Some(timer) = &mut slow_motion.0 {
-        timer.tick(time.delta());
-
-        if timer.finished() {
-            sim_config.time_dilation = 1.0;
-            // Update the fixed timestep back to normal
-            fixed_time.set_timestep_hz(
-                sim_config.ticks_per_second as f64
-                    * sim_config.time_dilation as f64,
-            );
-            slow_motion.0 = None;
-        }
-    }
 }
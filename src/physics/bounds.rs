@@ -0,0 +1,216 @@
+//! Edges of the arena
+//!
+//! With an `ArenaBounds` resource, bodies crossing the edge of the arena are
+//! wrapped around to the opposite edge, reflected back in or destroyed. The
+//! bounds are applied as timelines are computed, so predicted trajectories
+//! include them.
+//!
+//! Crossings are handled once a body has been integrated over a tick, so a
+//! body can be outside the bounds for part of a tick. Bodies in the middle of a
+//! coarse prediction step are only brought back in at the end of the step,
+//! until the step is refined. Gravity doesn't reach across a wrapped edge.
+//!
+//! Adding, changing or removing the bounds recomputes every prediction.
+//!
+//! In `Wrap` mode the broad phase doesn't look across the seam either, so
+//! bodies touching across opposite edges aren't tested against each other.
+//! They only collide once one of them has wrapped over to the other's side.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Real, *};
+use crate::prelude::*;
+
+/// What happens to a body crossing the arena bounds
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize,
+)]
+pub enum BoundaryMode {
+    /// Come back in from the opposite edge, like on a torus
+    #[default]
+    Wrap,
+    /// Bounce off the edge without losing speed
+    Reflect,
+    /// Destroyed on crossing the edge
+    Destroy,
+}
+
+/// Rectangle bodies are kept in, see `BoundaryMode`
+///
+/// Space is unbounded without this resource
+#[derive(
+    Resource, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize,
+)]
+pub struct ArenaBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub mode: BoundaryMode,
+}
+
+impl ArenaBounds {
+    pub fn new(rect: Rect, mode: BoundaryMode) -> Self {
+        Self {
+            min: rect.min,
+            max: rect.max,
+            mode,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::from_corners(self.min, self.max)
+    }
+
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    /// Whether `pos` is outside the bounds, compared on `Real` so that
+    /// fixed-point builds don't round it first
+    fn crossed(&self, pos: RealVec2) -> bool {
+        let (min, max) = self.real_corners();
+        (0..2).any(|axis| pos[axis] < min[axis] || pos[axis] > max[axis])
+    }
+
+    fn real_corners(&self) -> (RealVec2, RealVec2) {
+        (RealVec2::from_vec2(self.min), RealVec2::from_vec2(self.max))
+    }
+
+    /// Bring `state` back within the bounds if it crossed them
+    /// Returns whether it did
+    pub fn apply(&self, state: &mut PhysicsState) -> bool {
+        if !state.alive || !self.crossed(state.pos) {
+            return false;
+        }
+        let (lo, hi) = self.real_corners();
+        match self.mode {
+            BoundaryMode::Wrap => {
+                for axis in 0..2 {
+                    let (min, size) = (lo[axis], hi[axis] - lo[axis]);
+                    state.pos[axis] =
+                        min + (state.pos[axis] - min).rem_euclid(size);
+                }
+            }
+            BoundaryMode::Reflect => {
+                let two = Real::from_f32(2.);
+                for axis in 0..2 {
+                    let (min, max) = (lo[axis], hi[axis]);
+                    if state.pos[axis] < min {
+                        state.pos[axis] =
+                            (two * min - state.pos[axis]).min(max);
+                        state.vel[axis] = state.vel[axis].abs();
+                    } else if state.pos[axis] > max {
+                        state.pos[axis] =
+                            (two * max - state.pos[axis]).max(min);
+                        state.vel[axis] = -state.vel[axis].abs();
+                    }
+                }
+            }
+            BoundaryMode::Destroy => state.alive = false,
+        }
+        true
+    }
+
+    /// Bring the state at `tick` back within the bounds, storing it if it was
+    /// coasted
+    pub fn apply_to_timeline(&self, tick: u64, timeline: &mut Timeline) {
+        let crossed = timeline
+            .state(tick)
+            .is_some_and(|state| state.alive && self.crossed(state.pos));
+        if crossed {
+            self.apply(timeline.state_mut(tick).unwrap());
+        }
+    }
+
+    /// Where the collider of a body that moved from `prev_pos` to `pos` over a
+    /// tick is swept from
+    ///
+    /// Wrapped bodies are swept from beyond the edge they came in through,
+    /// rather than across the whole arena
+    pub fn sweep_start(&self, prev_pos: Vec2, pos: Vec2) -> Vec2 {
+        match self.mode {
+            BoundaryMode::Wrap => {
+                let size = self.max - self.min;
+                prev_pos + size * ((pos - prev_pos) / size).round()
+            }
+            BoundaryMode::Reflect | BoundaryMode::Destroy => prev_pos,
+        }
+    }
+}
+
+/// Where a body that moved from `prev_pos` to `pos` is swept from, given the
+/// bounds if there are any
+pub fn sweep_start(
+    bounds: Option<&ArenaBounds>,
+    prev_pos: Vec2,
+    pos: Vec2,
+) -> Vec2 {
+    bounds.map_or(prev_pos, |bounds| bounds.sweep_start(prev_pos, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(mode: BoundaryMode) -> ArenaBounds {
+        ArenaBounds::new(
+            Rect::from_corners(Vec2::splat(-10.), Vec2::splat(10.)),
+            mode,
+        )
+    }
+
+    fn state(pos: Vec2, vel: Vec2) -> PhysicsState {
+        PhysicsState {
+            pos: RealVec2::from_vec2(pos),
+            vel: RealVec2::from_vec2(vel),
+            alive: true,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn test_inside_is_untouched() {
+        for mode in [
+            BoundaryMode::Wrap,
+            BoundaryMode::Reflect,
+            BoundaryMode::Destroy,
+        ] {
+            let mut inside = state(Vec2::new(10., -3.), Vec2::X);
+            assert!(!bounds(mode).apply(&mut inside));
+            assert_eq!(inside, state(Vec2::new(10., -3.), Vec2::X));
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let bounds = bounds(BoundaryMode::Wrap);
+        let mut crossed = state(Vec2::new(12., -11.), Vec2::new(5., -1.));
+        assert!(bounds.apply(&mut crossed));
+        assert_eq!(crossed.pos.to_vec2(), Vec2::new(-8., 9.));
+        assert_eq!(crossed.vel.to_vec2(), Vec2::new(5., -1.));
+
+        // Swept from just beyond the edge it came in through
+        assert_eq!(
+            bounds.sweep_start(Vec2::new(9., -9.), crossed.pos.to_vec2()),
+            Vec2::new(-11., 11.)
+        );
+        assert_eq!(
+            bounds.sweep_start(Vec2::new(1., 2.), Vec2::new(3., 4.)),
+            Vec2::new(1., 2.)
+        );
+    }
+
+    #[test]
+    fn test_reflect() {
+        let mut crossed = state(Vec2::new(12., 0.), Vec2::new(5., -1.));
+        assert!(bounds(BoundaryMode::Reflect).apply(&mut crossed));
+        assert_eq!(crossed.pos.to_vec2(), Vec2::new(8., 0.));
+        assert_eq!(crossed.vel.to_vec2(), Vec2::new(-5., -1.));
+    }
+
+    #[test]
+    fn test_destroy() {
+        let mut crossed = state(Vec2::new(0., -12.), Vec2::NEG_Y);
+        assert!(bounds(BoundaryMode::Destroy).apply(&mut crossed));
+        assert!(!crossed.alive);
+    }
+}
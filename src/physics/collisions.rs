@@ -1,53 +1,468 @@
-use std::ops::RangeInclusive;
+use std::{ops::RangeInclusive, sync::Arc};
 
-use bevy::color::palettes::css;
+use anyhow::{bail, ensure};
+use bevy::{
+    color::palettes::css,
+    ecs::entity::{EntityMapper, MapEntities},
+};
 use rtree_rs::RTree;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    physics::{PhysicsState, Timeline},
+    physics::{
+        bounds::sweep_start,
+        ArenaBounds,
+        PhysicsState,
+        Planar,
+        Real,
+        RealVec2,
+        Scalar,
+        Timeline,
+    },
     prelude::*,
-    utils::intersect_ray_aabb,
+    utils::{entity_bits, intersect_ray_aabb, segment_aabb_entry},
 };
 
-#[derive(Component, Debug, Clone, Deref, Copy)]
-pub struct Collider(pub BRect);
+/// Collision shape, centred on the entity and rotating with
+/// `PhysicsState::rotation`
+///
+/// The spatial index only stores bounding AABBs (broad phase), candidate pairs
+/// are then checked against the actual shapes with [`Collider::overlaps`]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Collider {
+    Circle {
+        radius: f32,
+    },
+    /// Rectangle given by its half width and half height
+    Box {
+        half_size: Vec2,
+    },
+    /// Convex polygon, vertices in local space wound counter-clockwise
+    /// around the origin, see `Collider::polygon`
+    Polygon {
+        vertices: Arc<[Vec2]>,
+    },
+}
 
 impl Collider {
     pub fn from_dim(dim: Vec2) -> Self {
-        Self(BRect::from_corners(-dim / 2., dim / 2.))
+        Self::Box {
+            half_size: dim / 2.,
+        }
     }
 
     pub fn from_wh(w: f32, h: f32) -> Self {
         Self::from_dim(Vec2::new(w, h))
     }
+
+    pub fn circle(radius: f32) -> Self {
+        Self::Circle { radius }
+    }
+
+    /// Convex polygon around the origin, from vertices in local space wound
+    /// either way
+    ///
+    /// Fails for fewer than 3 vertices, repeated vertices, concave or
+    /// self-intersecting outlines, and outlines the origin isn't inside of
+    pub fn polygon(
+        vertices: impl IntoIterator<Item = Vec2>,
+    ) -> anyhow::Result<Self> {
+        let mut vertices = vertices.into_iter().collect::<Vec<_>>();
+        ensure!(
+            vertices.len() >= 3,
+            "Polygon collider needs 3+ vertices, got {}",
+            vertices.len()
+        );
+        // Twice the signed area, positive when wound counter-clockwise
+        let area = edges(&vertices).map(|(a, b)| a.perp_dot(b)).sum::<f32>();
+        if area < 0. {
+            vertices.reverse();
+        }
+        for (a, b) in edges(&vertices) {
+            ensure!(a != b, "Polygon collider has a repeated vertex {a}");
+            // Convex outlines wound counter-clockwise have every vertex on
+            // the left of every edge
+            let left_of = |v: Vec2| (b - a).perp_dot(v - a);
+            if vertices.iter().any(|v| left_of(*v) < 0.) {
+                bail!("Polygon collider isn't convex at edge {a} to {b}");
+            }
+            if left_of(Vec2::ZERO) <= 0. {
+                bail!("Polygon collider doesn't contain the origin");
+            }
+        }
+        Ok(Self::Polygon {
+            vertices: vertices.into(),
+        })
+    }
+
+    /// World space vertices at `pos` and `rotation`, empty for circles
+    fn world_vertices(&self, pos: Vec2, rotation: f32) -> Vec<Vec2> {
+        let rot = rotation_vec(rotation);
+        let local: Vec<Vec2> = match self {
+            Collider::Circle { .. } => return Vec::new(),
+            Collider::Box { half_size } => {
+                [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)]
+                    .into_iter()
+                    .map(|(x, y)| *half_size * Vec2::new(x, y))
+                    .collect()
+            }
+            Collider::Polygon { vertices } => vertices.to_vec(),
+        };
+        local.into_iter().map(|v| pos + rot.rotate(v)).collect()
+    }
+
+    /// World space AABB of the collider at `pos` and `rotation`
+    pub fn aabb(&self, pos: Vec2, rotation: f32) -> BRect {
+        match self {
+            Collider::Circle { radius } => {
+                BRect::from_center_half_size(pos, Vec2::splat(*radius))
+            }
+            _ => {
+                let vertices = self.world_vertices(pos, rotation);
+                vertices.iter().fold(
+                    BRect::from_corners(vertices[0], vertices[0]),
+                    |r, v| r.union_point(*v),
+                )
+            }
+        }
+    }
+
+    /// World space AABB covering the collider as it moves from `from` to `to`
+    pub fn swept(&self, from: Vec2, to: Vec2, rotation: f32) -> BRect {
+        self.aabb(from, rotation).union(self.aabb(to, rotation))
+    }
+
+    /// Shape containing every point within `distance` of this one
+    pub fn grown(&self, distance: f32) -> Collider {
+        if distance <= 0. {
+            return self.clone();
+        }
+        match self {
+            Collider::Circle { radius } => Collider::circle(radius + distance),
+            Collider::Box { half_size } => Collider::Box {
+                half_size: *half_size + distance,
+            },
+            Collider::Polygon { vertices } => {
+                // Scaling about the centre moves every edge out by at least
+                // `distance`
+                let inner_radius = self.inner_radius();
+                if inner_radius <= 0. {
+                    return Collider::circle(self.bounding_radius() + distance);
+                }
+                let scale = 1. + distance / inner_radius;
+                Collider::Polygon {
+                    vertices: vertices.iter().map(|v| *v * scale).collect(),
+                }
+            }
+        }
+    }
+
+    /// Radius of the smallest circle around the centre containing the shape
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Collider::Circle { radius } => *radius,
+            Collider::Box { half_size } => half_size.length(),
+            Collider::Polygon { vertices } => {
+                vertices.iter().map(|v| v.length()).fold(0., f32::max)
+            }
+        }
+    }
+
+    /// Radius of the largest circle around the centre that fits inside the
+    /// shape. Moving less than this can't skip over the shape
+    fn inner_radius(&self) -> f32 {
+        match self {
+            Collider::Circle { radius } => *radius,
+            Collider::Box { half_size } => half_size.min_element(),
+            Collider::Polygon { vertices } => edges(vertices)
+                .map(|(a, b)| (b - a).perp().normalize_or_zero().dot(a).abs())
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    /// Narrow phase test using the separating axis theorem
+    pub fn overlaps(
+        &self,
+        pos: Vec2,
+        rotation: f32,
+        other: &Collider,
+        other_pos: Vec2,
+        other_rotation: f32,
+    ) -> bool {
+        match (self, other) {
+            (
+                Collider::Circle { radius },
+                Collider::Circle {
+                    radius: other_radius,
+                },
+            ) => {
+                let reach = radius + other_radius;
+                pos.distance_squared(other_pos) <= reach * reach
+            }
+            (Collider::Circle { radius }, _) => circle_polygon_overlap(
+                pos,
+                *radius,
+                &other.world_vertices(other_pos, other_rotation),
+            ),
+            (_, Collider::Circle { radius }) => circle_polygon_overlap(
+                other_pos,
+                *radius,
+                &self.world_vertices(pos, rotation),
+            ),
+            _ => polygons_overlap(
+                &self.world_vertices(pos, rotation),
+                &other.world_vertices(other_pos, other_rotation),
+            ),
+        }
+    }
+
+    /// Unit normal of the contact with `other`, pointing from this shape
+    /// towards it, or zero if the centres coincide
+    ///
+    /// Uses the separating axis along which the shapes overlap the least
+    pub fn contact_normal(
+        &self,
+        pos: Vec2,
+        rotation: f32,
+        other: &Collider,
+        other_pos: Vec2,
+        other_rotation: f32,
+    ) -> Vec2 {
+        let towards = other_pos - pos;
+        let axis = match (self, other) {
+            (Collider::Circle { .. }, Collider::Circle { .. }) => towards,
+            (Collider::Circle { radius }, _) => circle_polygon_axis(
+                pos,
+                *radius,
+                &other.world_vertices(other_pos, other_rotation),
+            ),
+            (_, Collider::Circle { radius }) => circle_polygon_axis(
+                other_pos,
+                *radius,
+                &self.world_vertices(pos, rotation),
+            ),
+            _ => polygons_axis(
+                &self.world_vertices(pos, rotation),
+                &other.world_vertices(other_pos, other_rotation),
+            ),
+        };
+        let normal = axis.normalize_or_zero();
+        if normal.dot(towards) < 0. {
+            -normal
+        } else {
+            normal
+        }
+    }
+}
+
+/// Surface and structural properties used to resolve collisions
+///
+/// Entities without one collide as `Material::default()`, which doesn't
+/// bounce at all
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize,
+)]
+pub struct Material {
+    /// Coefficient of restitution in [0, 1]
+    /// 0 = no bounce along the contact normal, 1 = perfectly elastic
+    pub restitution: f32,
+    /// Coulomb friction coefficient, limits the impulse along the contact
+    /// surface to this fraction of the normal impulse
+    pub friction: f32,
+    /// Specific impact energy (J/kg) the body can absorb
+    /// Sets the `CollisionOutcome` thresholds and the damage taken: an impact
+    /// at the body's toughness takes 1 health
+    pub toughness: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            restitution: 0.,
+            friction: 0.5,
+            toughness: 100.,
+        }
+    }
+}
+
+/// Unit vector pointing along `rotation`
+///
+/// Goes through `RealVec2` so fixed-point builds avoid platform `sin`/`cos`
+fn rotation_vec(rotation: f32) -> Vec2 {
+    RealVec2::from_angle(Real::from_f32(rotation)).to_vec2()
+}
+
+/// Consecutive vertex pairs around a closed polygon
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    vertices
+        .iter()
+        .copied()
+        .zip(vertices.iter().copied().cycle().skip(1))
+}
+
+/// (min, max) of `vertices` projected onto `axis`
+fn project(vertices: &[Vec2], axis: Vec2) -> (f32, f32) {
+    vertices
+        .iter()
+        .map(|v| v.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), d| {
+            (lo.min(d), hi.max(d))
+        })
+}
+
+/// Unit normals of a polygon's edges
+fn edge_normals(vertices: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    edges(vertices).map(|(start, end)| (end - start).perp().normalize_or_zero())
+}
+
+/// Depth two polygons overlap by along `axis`, negative when separated
+fn polygons_depth(a: &[Vec2], b: &[Vec2], axis: Vec2) -> f32 {
+    let (a_min, a_max) = project(a, axis);
+    let (b_min, b_max) = project(b, axis);
+    a_max.min(b_max) - a_min.max(b_min)
+}
+
+/// Separating axes to test between a circle and a polygon
+///
+/// Edge normals alone miss circles sitting just off a corner, the axis
+/// towards the closest vertex covers that case
+fn circle_polygon_axes(
+    center: Vec2,
+    polygon: &[Vec2],
+) -> impl Iterator<Item = Vec2> + '_ {
+    let closest = polygon
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            a.distance_squared(center)
+                .total_cmp(&b.distance_squared(center))
+        })
+        .unwrap_or(center);
+    edge_normals(polygon)
+        .chain(std::iter::once((closest - center).normalize_or_zero()))
+        .filter(|axis| *axis != Vec2::ZERO)
+}
+
+/// Depth a circle and a polygon overlap by along `axis`, negative when
+/// separated
+fn circle_polygon_depth(
+    center: Vec2,
+    radius: f32,
+    polygon: &[Vec2],
+    axis: Vec2,
+) -> f32 {
+    let (min, max) = project(polygon, axis);
+    let c = center.dot(axis);
+    (c + radius).min(max) - (c - radius).max(min)
+}
+
+/// Axis with the smallest overlap depth, or zero if there are no axes
+fn shallowest_axis(
+    axes: impl Iterator<Item = Vec2>,
+    depth: impl Fn(Vec2) -> f32,
+) -> Vec2 {
+    axes.map(|axis| (depth(axis), axis))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map_or(Vec2::ZERO, |(_, axis)| axis)
+}
+
+fn polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    edge_normals(a)
+        .chain(edge_normals(b))
+        .all(|axis| polygons_depth(a, b, axis) >= 0.)
+}
+
+fn polygons_axis(a: &[Vec2], b: &[Vec2]) -> Vec2 {
+    shallowest_axis(edge_normals(a).chain(edge_normals(b)), |axis| {
+        polygons_depth(a, b, axis)
+    })
+}
+
+fn circle_polygon_overlap(center: Vec2, radius: f32, polygon: &[Vec2]) -> bool {
+    circle_polygon_axes(center, polygon)
+        .all(|axis| circle_polygon_depth(center, radius, polygon, axis) >= 0.)
+}
+
+fn circle_polygon_axis(center: Vec2, radius: f32, polygon: &[Vec2]) -> Vec2 {
+    shallowest_axis(circle_polygon_axes(center, polygon), |axis| {
+        circle_polygon_depth(center, radius, polygon, axis)
+    })
 }
 
 pub fn viz_colliders(
     mut gizmos: Gizmos,
     colliders: Query<(&PhysicsState, &Collider)>,
 ) {
+    let color = bevy::color::Color::srgb(0.4, 0.4, 0.4);
     for (phys, collider) in colliders.iter() {
-        // let world_aabb = collider.0.transalate(phys.pos);
-        gizmos.rect_2d(
-            Isometry2d::new(phys.pos, Rot2::radians(0.)),
-            collider.0.size(),
-            bevy::color::Color::srgb(0.4, 0.4, 0.4),
-        );
+        let (pos, rotation) = (phys.pos.to_vec2(), phys.rotation.to_f32());
+        match collider {
+            Collider::Circle { radius } => {
+                gizmos.circle_2d(
+                    Isometry2d::from_translation(pos),
+                    *radius,
+                    color,
+                );
+            }
+            Collider::Box { half_size } => {
+                gizmos.rect_2d(
+                    Isometry2d::new(pos, Rot2::radians(rotation)),
+                    *half_size * 2.,
+                    color,
+                );
+            }
+            Collider::Polygon { .. } => {
+                let vertices = collider.world_vertices(pos, rotation);
+                gizmos.linestrip_2d(
+                    vertices.iter().chain(vertices.first()).copied(),
+                    color,
+                );
+            }
+        }
     }
 }
 
-#[derive(Clone, PartialEq, Debug, Reflect)]
+#[derive(Clone, PartialEq, Debug, Reflect, Serialize, Deserialize)]
 pub struct Collision {
+    #[serde(with = "entity_bits")]
     pub other: Entity,
+    /// Fraction of the tick in [0, 1] at which the colliders first touched
+    pub time_of_impact: f32,
+    /// Health this entity lost in the impact
+    pub damage: f32,
+}
+
+impl MapEntities for Collision {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.other = entity_mapper.map_entity(self.other);
+    }
 }
 
 #[derive(Clone, PartialEq, Debug, Reflect)]
 pub enum EntityCollisionResult {
-    Destroyed,
-    Survives { post_pos: Vec2, post_vel: Vec2 },
+    /// `post_vel` is the velocity the wreck carries away, which any
+    /// fragments inherit
+    Destroyed { post_vel: Vec2, damage: f32 },
+    Survives {
+        post_pos: Vec2,
+        post_vel: Vec2,
+        damage: f32,
+    },
 }
 
 impl EntityCollisionResult {
+    pub fn damage(&self) -> f32 {
+        match self {
+            Self::Destroyed { damage, .. } | Self::Survives { damage, .. } => {
+                *damage
+            }
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        matches!(self, Self::Destroyed { .. })
+    }
+
     pub fn pos_equiv(&self, other: &Self) -> bool {
         match (self, other) {
             (
@@ -65,102 +480,651 @@ impl EntityCollisionResult {
 #[derive(Clone, PartialEq, Debug)]
 pub struct SpatialItem {
     pub entity: Entity,
+    /// Position at the start of the tick
+    /// The collider is swept from here to `pos`
+    pub prev_pos: Vec2,
     pub pos: Vec2,
     pub vel: Vec2,
+    /// Rotation of the collider, held fixed over the tick
+    pub rotation: f32,
     pub mass: f32,
+    pub health: f32,
 }
 
 impl SpatialItem {
+    /// Item that stays at `state.pos` for the whole tick
     pub fn from_state(entity: Entity, state: &PhysicsState) -> SpatialItem {
+        SpatialItem::swept(entity, state.pos.to_vec2(), state)
+    }
+
+    /// Item moving from `prev_pos` to `state.pos` over the tick
+    pub fn swept(
+        entity: Entity,
+        prev_pos: Vec2,
+        state: &PhysicsState,
+    ) -> SpatialItem {
         SpatialItem {
             entity,
-            pos: state.pos,
-            vel: state.vel,
-            mass: state.mass,
+            prev_pos,
+            pos: state.pos.to_vec2(),
+            vel: state.vel.to_vec2(),
+            rotation: state.rotation.to_f32(),
+            mass: state.mass.to_f32(),
+            health: state.health,
+        }
+    }
+
+    /// World space AABB of `collider` swept over the tick
+    pub fn swept_aabb(&self, collider: &Collider) -> BRect {
+        collider.swept(self.prev_pos, self.pos, self.rotation)
+    }
+
+    /// Position a fraction `t` of the way through the tick
+    pub fn pos_at(&self, t: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, t)
+    }
+}
+
+/// Upper bound on the number of shape tests when marching along a sweep
+const MAX_SWEEP_STEPS: usize = 256;
+
+/// Fraction of the tick at which two colliders, each moving in a straight
+/// line from their start to end position, first touch
+///
+/// Works in the frame of `b`. The bounding boxes are tested first: the centre
+/// of `a` traces a segment against `b`'s AABB grown by `a`'s (Minkowski sum),
+/// which gives the earliest possible contact. From there the segment is
+/// marched in steps no longer than the inner radii of both shapes together,
+/// so that neither can be skipped over, and the first overlapping step is
+/// refined by bisection
+///
+/// The march is capped at `MAX_SWEEP_STEPS` steps, so when the relative
+/// travel is more than that many times the inner radii the steps are longer.
+/// Thin shapes can then be passed through once the bounding boxes overlap
+pub fn time_of_impact(
+    (a_from, a_to, a_rot, a_col): (Vec2, Vec2, f32, &Collider),
+    (b_from, b_to, b_rot, b_col): (Vec2, Vec2, f32, &Collider),
+) -> Option<f32> {
+    let origin = a_from - b_from;
+    let direction = (a_to - a_from) - (b_to - b_from);
+    let a_box = a_col.aabb(Vec2::ZERO, a_rot);
+    let b_box = b_col.aabb(Vec2::ZERO, b_rot);
+    let entry = segment_aabb_entry(
+        b_box.min - a_box.max,
+        b_box.max - a_box.min,
+        origin,
+        direction,
+    )?;
+
+    let overlaps_at = |t: f32| {
+        a_col.overlaps(origin + direction * t, a_rot, b_col, Vec2::ZERO, b_rot)
+    };
+    if overlaps_at(entry) {
+        return Some(entry);
+    }
+
+    let travel = direction.length();
+    if travel == 0. {
+        return None;
+    }
+    let step = ((a_col.inner_radius() + b_col.inner_radius()) / travel)
+        .max(1. / MAX_SWEEP_STEPS as f32);
+
+    let mut prev = entry;
+    while prev < 1. {
+        let t = (prev + step).min(1.);
+        if overlaps_at(t) {
+            let (mut lo, mut hi) = (prev, t);
+            for _ in 0..16 {
+                let mid = (lo + hi) / 2.;
+                if overlaps_at(mid) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            return Some(hi);
         }
+        prev = t;
     }
+    None
+}
+
+/// Where two bodies first touched during a tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Fraction of the tick in [0, 1] at which the colliders first touched
+    pub time_of_impact: f32,
+    /// Unit normal pointing from the first body towards the second
+    pub normal: Vec2,
 }
 
+/// First contact between `a` and `b` as they sweep through the tick
+pub fn find_contact(
+    (a, a_col): (&SpatialItem, &Collider),
+    (b, b_col): (&SpatialItem, &Collider),
+) -> Option<Contact> {
+    let time_of_impact = time_of_impact(
+        (a.prev_pos, a.pos, a.rotation, a_col),
+        (b.prev_pos, b.pos, b.rotation, b_col),
+    )?;
+    let normal = a_col.contact_normal(
+        a.pos_at(time_of_impact),
+        a.rotation,
+        b_col,
+        b.pos_at(time_of_impact),
+        b.rotation,
+    );
+    // Centres on top of each other, push apart along the approach instead
+    let normal = if normal == Vec2::ZERO {
+        (a.vel - b.vel).normalize_or(Vec2::X)
+    } else {
+        normal
+    };
+    Some(Contact {
+        time_of_impact,
+        normal,
+    })
+}
+
+/// Consecutive ticks sharing one segment of the spatial index
+///
+/// Each entity gets one rtree entry per segment, covering its swept area over
+/// all of the segment's ticks, instead of one entry per tick
+pub const SEGMENT_TICKS: u64 = 8;
+
+/// Broad phase index of every entity's swept collider over the predicted
+/// ticks
+///
+/// Ticks are grouped into segments keyed by their first tick. A query at a
+/// tick searches its segment's rtree, then checks the candidates' items at
+/// that tick, so the tree and collider of an entity are only stored once per
+/// segment
 #[derive(Resource, Default)]
-// pub struct SpatialIndex(pub EntityHashMap<BTreeMap<u64, BoundingBox>>);
-pub struct SpatialIndex(pub BTreeMap<u64, SpatialIndexPerTick>);
+pub struct SpatialIndex(pub BTreeMap<u64, SpatialIndexSegment>);
+
+pub struct SpatialIndexSegment {
+    entries: EntityHashMap<SegmentEntry>,
+    rtree: RTree<2, SpatialKey, Entity>,
+}
+
+/// An entity's collider and path over one segment
+///
+/// Only the items at the first and last indexed ticks are stored. Positions
+/// in between are interpolated in a straight line from the end of the first
+/// tick to the start of the last, and tested with the collider grown by
+/// `slack` so that no contact is missed. Candidates are checked against the
+/// actual states when collisions are resolved
+///
+/// Ticks are indexed in order, so writing a tick drops the ones after it in
+/// the segment, which are about to be recomputed. A tick that doesn't carry on
+/// from the last one, like a wrap around the arena, starts a new path and
+/// keeps the one before it in `earlier`
+struct SegmentEntry {
+    collider: Collider,
+    /// Rect stored in the rtree, covering the path and where it's heading
+    /// for the rest of the segment
+    rect: BRect,
+    first_offset: usize,
+    last_offset: usize,
+    first: SpatialItem,
+    last: SpatialItem,
+    /// Upper bound on how far indexed positions are from interpolated ones
+    slack: f32,
+    /// Whether the rotation changed over the path, in which case the entity
+    /// is tested with its bounding circle
+    turns: bool,
+    /// Path over the ticks before `first_offset`, if the entity jumped
+    earlier: Option<Box<SegmentEntry>>,
+}
+
+impl SegmentEntry {
+    fn new(
+        offset: usize,
+        collider: &Collider,
+        item: SpatialItem,
+    ) -> SegmentEntry {
+        let mut entry = SegmentEntry {
+            collider: collider.clone(),
+            rect: item.swept_aabb(collider),
+            first_offset: offset,
+            last_offset: offset,
+            first: item.clone(),
+            last: item,
+            slack: 0.,
+            turns: false,
+            earlier: None,
+        };
+        entry.rect = entry.reach();
+        entry
+    }
+
+    /// Interpolated position `boundary` ticks after the start of the first
+    /// tick, on a path whose last tick is `span` ticks after the first and
+    /// starts at `to`
+    fn interpolate(&self, to: Vec2, span: usize, boundary: usize) -> Vec2 {
+        if span < 2 {
+            return self.first.pos;
+        }
+        let t = (boundary - 1) as f32 / (span - 1) as f32;
+        self.first.pos + (to - self.first.pos) * t
+    }
+
+    /// Item at `offset` and the collider to test it with, `None` outside of
+    /// the indexed ticks
+    fn at(&self, offset: usize) -> Option<(Collider, SpatialItem)> {
+        if offset < self.first_offset {
+            return self.earlier.as_ref()?.at(offset);
+        }
+        if offset > self.last_offset {
+            return None;
+        }
+        let index = offset - self.first_offset;
+        let span = self.last_offset - self.first_offset;
+        let collider = if self.turns {
+            Collider::circle(self.collider.bounding_radius() + self.slack)
+        } else {
+            self.collider.grown(self.slack)
+        };
+        if index == 0 {
+            return Some((collider, self.first.clone()));
+        }
+        if index == span {
+            return Some((collider, self.last.clone()));
+        }
+
+        let t = index as f32 / span as f32;
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let (first, last) = (&self.first, &self.last);
+        let item = SpatialItem {
+            entity: first.entity,
+            prev_pos: self.interpolate(last.prev_pos, span, index),
+            pos: self.interpolate(last.prev_pos, span, index + 1),
+            vel: first.vel.lerp(last.vel, t),
+            rotation: lerp(first.rotation, last.rotation),
+            mass: lerp(first.mass, last.mass),
+            health: lerp(first.health, last.health),
+        };
+        Some((collider, item))
+    }
+
+    /// Area covered by the indexed ticks
+    fn cover(&self) -> BRect {
+        let collider = &self.collider;
+        let (first, last) = (&self.first, &self.last);
+        let between = if self.turns {
+            let radius = collider.bounding_radius();
+            BRect::from_corners(first.pos, last.prev_pos).inflate(radius)
+        } else {
+            collider.swept(first.pos, last.prev_pos, first.rotation)
+        };
+        let cover = first
+            .swept_aabb(collider)
+            .union(last.swept_aabb(collider))
+            .union(between.inflate(self.slack));
+        match &self.earlier {
+            Some(earlier) => cover.union(earlier.cover()),
+            None => cover,
+        }
+    }
+
+    /// First indexed offset, including the earlier paths
+    fn start(&self) -> usize {
+        self.earlier
+            .as_ref()
+            .map_or(self.first_offset, |earlier| earlier.start())
+    }
+
+    /// Area covered by the indexed ticks, and by the rest of the segment if
+    /// the last tick's motion carries on, with some leeway
+    ///
+    /// Entities moving steadily stay within it for the whole segment, so
+    /// their rtree entry is only computed once
+    fn reach(&self) -> BRect {
+        let last = &self.last;
+        let step = last.pos - last.prev_pos;
+        let remaining = SEGMENT_TICKS as usize - 1 - self.last_offset;
+        let end = last.pos + step * remaining as f32;
+        let ahead = self.collider.swept(last.pos, end, last.rotation);
+        self.cover().union(ahead.inflate(step.length() / 2.))
+    }
+
+    /// Extend the path with `item` at `offset`, past the last indexed tick
+    fn push(&mut self, offset: usize, item: SpatialItem) {
+        if item.prev_pos != self.last.pos {
+            let path = SegmentEntry::new(offset, &self.collider, item);
+            let earlier = std::mem::replace(self, path);
+            self.earlier = Some(Box::new(earlier));
+            return;
+        }
+        let old_span = self.last_offset - self.first_offset;
+        let span = offset - self.first_offset;
+        // Ticks in between move onto the new line. The old line and the new
+        // one meet at the first tick, so they're furthest apart where the old
+        // one ended
+        let shift = if old_span == 0 {
+            0.
+        } else {
+            let moved = self.interpolate(item.prev_pos, span, old_span);
+            self.last.prev_pos.distance(moved)
+        };
+        let end = self.interpolate(item.prev_pos, span, old_span + 1);
+        self.slack = (self.slack + shift).max(self.last.pos.distance(end));
+        self.turns |= item.rotation != self.first.rotation;
+        self.last = item;
+        self.last_offset = offset;
+    }
+
+    /// Drop the ticks from `offset` on
+    /// Returns whether any are left
+    fn truncate(&mut self, offset: usize) -> bool {
+        if offset <= self.first_offset {
+            // Back onto the earlier path, keeping the rect it's stored with
+            let Some(earlier) = self.earlier.take() else {
+                return false;
+            };
+            let rect = self.rect;
+            *self = *earlier;
+            self.rect = rect;
+            return self.truncate(offset);
+        }
+        if offset <= self.last_offset {
+            // On the current line, which doesn't change
+            let (_, last) = self.at(offset - 1).unwrap();
+            self.last = last;
+            self.last_offset = offset - 1;
+        }
+        true
+    }
 
-pub struct SpatialIndexPerTick {
-    e_map: EntityHashMap<(RRect, SpatialItem)>,
-    rtree: RTree<2, f32, Entity>,
+    /// Drop the ticks before `offset`
+    /// Returns whether any are left
+    fn drop_before(&mut self, offset: usize) -> bool {
+        if offset > self.last_offset {
+            return false;
+        }
+        if offset <= self.first_offset {
+            if let Some(earlier) = &mut self.earlier {
+                if !earlier.drop_before(offset) {
+                    self.earlier = None;
+                }
+            }
+        } else {
+            self.earlier = None;
+            let (_, first) = self.at(offset).unwrap();
+            self.first = first;
+            self.first_offset = offset;
+        }
+        true
+    }
 }
 
-impl Default for SpatialIndexPerTick {
+impl Default for SpatialIndexSegment {
     fn default() -> Self {
         Self {
-            e_map: default(),
+            entries: default(),
             rtree: RTree::new(),
         }
     }
 }
 
-impl SpatialIndexPerTick {
-    fn remove(&mut self, entity: &Entity) {
-        let Some((rect, item)) = self.e_map.remove(entity) else {
+impl SpatialIndexSegment {
+    /// Items at `offset` of entities whose segment rect overlaps `rect`, with
+    /// the colliders to test them with
+    fn candidates(
+        &self,
+        rect: BRect,
+        offset: usize,
+    ) -> impl Iterator<Item = (Collider, SpatialItem)> + '_ {
+        self.rtree
+            .search(rect.to_rtree())
+            .filter_map(move |e| self.entries.get(e.data)?.at(offset))
+    }
+
+    fn insert(
+        &mut self,
+        offset: usize,
+        collider: &Collider,
+        item: SpatialItem,
+    ) {
+        let entity = item.entity;
+        let Some(entry) = self.entries.get_mut(&entity) else {
+            let entry = SegmentEntry::new(offset, collider, item);
+            self.rtree.insert(entry.rect.to_rtree(), entity);
+            self.entries.insert(entity, entry);
             return;
         };
-        self.rtree.remove(rect, entity);
+
+        // Colliders are fixed for the lifetime of an entity
+        let old_rect = entry.rect;
+        if entry.truncate(offset) {
+            entry.push(offset, item);
+        } else {
+            *entry = SegmentEntry::new(offset, collider, item);
+        }
+        // Only refit once the path leaves the rect
+        if entry.rect.union(entry.cover()) != entry.rect {
+            entry.rect = entry.reach();
+        }
+        if entry.rect != old_rect {
+            self.rtree.remove(old_rect.to_rtree(), &entity);
+            self.rtree.insert(entry.rect.to_rtree(), entity);
+        }
     }
 
-    pub fn collides(
-        &self,
-        entity: Entity,
-        pos: Vec2,
-        collider: &Collider,
-    ) -> Option<(RRect, SpatialItem)> {
-        // info!("Checking collisions...");
-        let rect = collider.transalate(pos).to_rtree();
-        self.rtree
-            .search(rect)
-            .filter(|e| e.data != &entity)
-            .next()
-            .and_then(|e| self.e_map.get(e.data).cloned())
+    /// Drop `entity` from `offset` on
+    fn remove(&mut self, offset: usize, entity: Entity) {
+        let Some(entry) = self.entries.get_mut(&entity) else {
+            return;
+        };
+        // Left as is otherwise, the ticks are usually indexed again
+        if !entry.truncate(offset) {
+            self.rtree.remove(entry.rect.to_rtree(), &entity);
+            self.entries.remove(&entity);
+        }
     }
 
-    pub fn insert(&mut self, collider: &Collider, item: SpatialItem) {
-        self.remove(&item.entity);
+    /// Drop every entity's ticks before `offset`, shrinking their rects
+    fn drop_before(&mut self, offset: usize) {
+        let mut emptied = Vec::new();
+        for (&entity, entry) in &mut self.entries {
+            if offset <= entry.start() {
+                continue;
+            }
+            self.rtree.remove(entry.rect.to_rtree(), &entity);
+            if entry.drop_before(offset) {
+                entry.rect = entry.reach();
+                self.rtree.insert(entry.rect.to_rtree(), entity);
+            } else {
+                emptied.push(entity);
+            }
+        }
+        for entity in emptied {
+            self.entries.remove(&entity);
+        }
+    }
 
-        let rect = collider.0.transalate(item.pos).to_rtree();
-        self.rtree.insert(rect, item.entity);
-        self.e_map.insert(item.entity, (rect, item));
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 }
 
 impl SpatialIndex {
+    /// First tick of the segment containing `tick`, and `tick`'s offset into
+    /// it
+    fn segment_of(tick: u64) -> (u64, usize) {
+        let offset = tick % SEGMENT_TICKS;
+        (tick - offset, offset as usize)
+    }
+
+    /// Items at `tick` whose segment rect overlaps `rect`, with the
+    /// colliders to test them with
+    fn candidates(
+        &self,
+        tick: u64,
+        rect: BRect,
+    ) -> impl Iterator<Item = (Collider, SpatialItem)> + '_ {
+        let (start, offset) = Self::segment_of(tick);
+        self.0
+            .get(&start)
+            .into_iter()
+            .flat_map(move |segment| segment.candidates(rect, offset))
+    }
+
     pub fn collides(
         &self,
         entity: Entity,
         tick: u64,
         pos: Vec2,
+        rotation: f32,
         collider: &Collider,
     ) -> Option<(RRect, SpatialItem)> {
-        self.0
-            .get(&tick)
-            .and_then(|index| index.collides(entity, pos, collider))
+        self.candidates(tick, collider.aabb(pos, rotation))
+            .filter(|(_, other)| other.entity != entity)
+            .find(|(other_col, other)| {
+                collider.overlaps(
+                    pos,
+                    rotation,
+                    other_col,
+                    other.pos,
+                    other.rotation,
+                )
+            })
+            .map(|(other_col, other)| {
+                (other.swept_aabb(&other_col).to_rtree(), other)
+            })
+    }
+
+    /// Earliest entity hit by `collider` moving from `from` to `to`, along
+    /// with the time of impact as a fraction of the tick
+    pub fn sweep(
+        &self,
+        entity: Entity,
+        tick: u64,
+        from: Vec2,
+        to: Vec2,
+        rotation: f32,
+        collider: &Collider,
+    ) -> Option<(f32, SpatialItem)> {
+        self.sweep_all(entity, tick, from, to, rotation, collider)
+            .into_iter()
+            .next()
+    }
+
+    /// Every entity hit by `collider` moving from `from` to `to`, along with
+    /// the time of impact of each
+    /// Sorted by time of impact, then entity, so ties come out the same way
+    /// every time
+    pub fn sweep_all(
+        &self,
+        entity: Entity,
+        tick: u64,
+        from: Vec2,
+        to: Vec2,
+        rotation: f32,
+        collider: &Collider,
+    ) -> Vec<(f32, SpatialItem)> {
+        let rect = collider.swept(from, to, rotation);
+        let mut hits = self
+            .candidates(tick, rect)
+            .filter(|(_, other)| other.entity != entity)
+            .filter_map(|(other_col, other)| {
+                let toi = time_of_impact(
+                    (from, to, rotation, collider),
+                    (other.prev_pos, other.pos, other.rotation, &other_col),
+                )?;
+                Some((toi, other))
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|(a, a_item), (b, b_item)| {
+            a.total_cmp(b).then(a_item.entity.cmp(&b_item.entity))
+        });
+        hits
     }
 
+    /// Index `item` at `tick`, dropping the entity's later ticks in the same
+    /// segment, see `SegmentEntry`
     pub fn insert(
         &mut self,
         tick: u64,
         collider: &Collider,
         item: SpatialItem,
     ) {
-        let index = self.0.entry(tick).or_insert_with(default);
-        index.insert(collider, item);
+        let (start, offset) = Self::segment_of(tick);
+        let segment = self.0.entry(start).or_insert_with(default);
+        segment.insert(offset, collider, item);
+    }
+
+    /// Entities whose collider overlaps `rect` at `tick`
+    pub fn entities_within(
+        &self,
+        tick: u64,
+        rect: BRect,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        self.candidates(tick, rect)
+            .filter(move |(collider, item)| {
+                let aabb = item.swept_aabb(collider);
+                aabb.min.cmple(rect.max).all() && rect.min.cmple(aabb.max).all()
+            })
+            .map(|(_, item)| item.entity)
+    }
+
+    /// Index every live state of `timeline`, swept from the state before it
+    /// the same way computing the timeline does
+    pub fn insert_timeline(
+        &mut self,
+        entity: Entity,
+        collider: &Collider,
+        timeline: &Timeline,
+        bounds: Option<&ArenaBounds>,
+    ) {
+        for (tick, state) in timeline.states() {
+            if !state.alive {
+                continue;
+            }
+            let prev_pos = tick
+                .checked_sub(1)
+                .and_then(|prev| timeline.state(prev))
+                .map_or(state.pos, |prev| prev.pos);
+            let prev_pos =
+                sweep_start(bounds, prev_pos.to_vec2(), state.pos.to_vec2());
+            self.insert(
+                tick,
+                collider,
+                SpatialItem::swept(entity, prev_pos, &state),
+            );
+        }
+    }
+
+    /// Earliest tick with anything indexed
+    pub fn first_tick(&self) -> Option<u64> {
+        let (start, segment) = self.0.first_key_value()?;
+        let offset = segment.entries.values().map(SegmentEntry::start).min()?;
+        Some(start + offset as u64)
     }
 
+    /// Drop the index for every tick before `tick`
+    pub fn prune_before(&mut self, tick: u64) {
+        let (start, offset) = Self::segment_of(tick);
+        self.0 = self.0.split_off(&start);
+        let Some(segment) = self.0.get_mut(&start) else {
+            return;
+        };
+        segment.drop_before(offset);
+        if segment.is_empty() {
+            self.0.remove(&start);
+        }
+    }
+
+    /// Drop `entity` at `tick`, and at its later ticks in the same segment
     pub fn remove(&mut self, tick: u64, entity: &Entity) {
-        let Some(index) = self.0.get_mut(&tick) else {
+        let (start, offset) = Self::segment_of(tick);
+        let Some(segment) = self.0.get_mut(&start) else {
             return;
         };
-        index.remove(entity);
-        if index.e_map.is_empty() {
-            self.0.remove(&tick);
+        segment.remove(offset, *entity);
+        if segment.is_empty() {
+            self.0.remove(&start);
         }
     }
 
@@ -180,22 +1144,19 @@ impl SpatialIndex {
 
         for tick in updated {
             // eprintln!("Inserting into spatial index {tick}");
-            let state = timeline.future_states.get(&tick).unwrap();
+            let state = timeline.state(tick).unwrap();
+            let prev_pos =
+                timeline.state(tick - 1).map_or(state.pos, |prev| prev.pos);
             self.insert(
                 tick,
                 collider,
-                SpatialItem {
-                    entity: e,
-                    pos: state.pos,
-                    vel: state.vel,
-                    mass: state.mass,
-                },
+                SpatialItem::swept(e, prev_pos.to_vec2(), &state),
             );
         }
     }
 }
 
-impl std::fmt::Debug for SpatialIndexPerTick {
+impl std::fmt::Debug for SpatialIndexSegment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{{")?;
         for item in self.rtree.iter() {
@@ -223,19 +1184,24 @@ pub fn calculate_impact_energy(
     m2: f32,
     rel_velocity: Vec2,
 ) -> (f32, f32) {
+    let r = Real::from_f32;
+    let rel_velocity = RealVec2::from_vec2(rel_velocity);
     // Calculate v² in (m/s)²
     let v_squared = rel_velocity.length_squared();
 
     // Calculate mass ratio μ = m2/m1
-    let mu = m2 / m1;
+    let mu = r(m2) / r(m1);
 
     // Q = ½μv²
-    let q1 = 0.5 * mu * v_squared;
-    let q2 = 0.5 * (1.0 / mu) * v_squared;
-    (q1, q2)
+    let half = r(0.5);
+    let q1 = half * mu * v_squared;
+    let q2 = half * (Real::ONE / mu) * v_squared;
+    (q1.to_f32(), q2.to_f32())
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize,
+)]
 pub enum CollisionOutcome {
     SurfaceEffects,
     Cratering,
@@ -245,96 +1211,241 @@ pub enum CollisionOutcome {
 }
 
 impl CollisionOutcome {
-    pub fn is_destoyed(q: f32) -> bool {
-        if q < 100. {
-            false
-        } else {
-            true
+    /// Whether an impact of specific energy `q` breaks a body of the given
+    /// `toughness` (J/kg)
+    pub fn is_destoyed(q: f32, toughness: f32) -> bool {
+        q >= toughness
+    }
+
+    /// Number of fragments a body destroyed with this outcome breaks into
+    pub fn fragment_count(&self) -> usize {
+        match self {
+            CollisionOutcome::Fracturing => 2,
+            CollisionOutcome::MajorRestructuring => 3,
+            CollisionOutcome::Disruption => 4,
+            _ => 0,
         }
     }
 
-    pub fn from_q(q: f32) -> Self {
-        match q {
-            q if q < 10.0 => CollisionOutcome::SurfaceEffects,
-            q if q < 100.0 => CollisionOutcome::Cratering,
-            q if q < 1000.0 => CollisionOutcome::Fracturing,
-            q if q < 10000.0 => CollisionOutcome::MajorRestructuring,
+    /// Outcome of an impact of specific energy `q` on a body of the given
+    /// `toughness`, each outcome starting at 10x the energy of the last
+    pub fn from_q(q: f32, toughness: f32) -> Self {
+        match q / toughness {
+            q if q < 0.1 => CollisionOutcome::SurfaceEffects,
+            q if q < 1.0 => CollisionOutcome::Cratering,
+            q if q < 10.0 => CollisionOutcome::Fracturing,
+            q if q < 100.0 => CollisionOutcome::MajorRestructuring,
             _ => CollisionOutcome::Disruption,
         }
     }
 }
 
+/// Health lost by a body of the given `toughness` taking an impact with
+/// specific energy `q` (J/kg)
+/// A body with 1 health is destroyed right at the `is_destoyed` threshold
+pub fn calculate_damage(q: f32, toughness: f32) -> f32 {
+    q / toughness
+}
+
+/// Whether `a` and `b` are moving towards each other
+/// Bodies left touching after an impact share a velocity, so they stop
+/// colliding instead of being hit again every tick
+pub fn is_closing(a: &SpatialItem, b: &SpatialItem) -> bool {
+    (b.vel - a.vel).dot(b.prev_pos - a.prev_pos) < 0.
+}
+
+/// Impulse (N*s) applied to `b` by the contact, `a` receives the opposite
+///
+/// The normal impulse stops the approach along `contact.normal` and bounces
+/// back by the combined restitution. Friction then opposes sliding along the
+/// surface, up to the combined friction coefficient times the normal impulse
+pub fn calculate_contact_impulse(
+    (a, a_mat): (&SpatialItem, &Material),
+    (b, b_mat): (&SpatialItem, &Material),
+    contact: &Contact,
+) -> Vec2 {
+    let r = Real::from_f32;
+    let dot = |u: RealVec2, v: RealVec2| u.x * v.x + u.y * v.y;
+    let normal = RealVec2::from_vec2(contact.normal);
+    let rel_vel = RealVec2::from_vec2(b.vel - a.vel);
+    let approach = dot(rel_vel, normal);
+    if approach >= Real::ZERO {
+        return Vec2::ZERO;
+    }
+
+    let inv_mass_sum = Real::ONE / r(a.mass) + Real::ONE / r(b.mass);
+    let restitution = r((a_mat.restitution + b_mat.restitution) / 2.);
+    let friction = r(a_mat.friction * b_mat.friction).sqrt();
+    let normal_impulse = -(Real::ONE + restitution) * approach / inv_mass_sum;
+
+    let sliding = rel_vel - normal * approach;
+    let sliding_speed = sliding.length();
+    let friction_impulse = if sliding_speed > Real::ZERO {
+        let stop = sliding_speed / inv_mass_sum;
+        let limit = friction * normal_impulse;
+        let magnitude = if stop < limit { stop } else { limit };
+        sliding * (magnitude / sliding_speed)
+    } else {
+        RealVec2::ZERO
+    };
+    (normal * normal_impulse - friction_impulse).to_vec2()
+}
+
+/// Damage dealt to each body and whether it survives
+///
+/// Each body takes damage proportional to its own specific impact energy
+/// relative to its toughness, so the lighter body takes the brunt of it.
+/// Bodies are only destroyed once their health runs out. Velocities change by
+/// the contact impulse, and survivors move on from where they touched for the
+/// rest of the tick
 pub fn calculate_collision_result(
-    a: &SpatialItem,
-    b: &SpatialItem,
+    (a, a_mat): (&SpatialItem, &Material),
+    (b, b_mat): (&SpatialItem, &Material),
+    contact: &Contact,
+    seconds_per_tick: f32,
 ) -> (EntityCollisionResult, EntityCollisionResult) {
     let (q, q_other) = calculate_impact_energy(a.mass, b.mass, b.vel - a.vel);
-    let post_vel = calculate_inelastic_collision(a.mass, a.vel, b.mass, b.vel);
-    if q > q_other {
-        (
-            EntityCollisionResult::Destroyed,
-            EntityCollisionResult::Survives {
-                post_pos: b.pos,
-                post_vel,
-            },
-        )
-    } else {
-        (
+    let impulse = calculate_contact_impulse((a, a_mat), (b, b_mat), contact);
+    let remaining = (1. - contact.time_of_impact) * seconds_per_tick;
+    let result = |item: &SpatialItem, mat: &Material, q: f32, impulse: Vec2| {
+        let damage = calculate_damage(q, mat.toughness);
+        let post_vel = item.vel + impulse / item.mass;
+        if item.health - damage <= 0. {
+            EntityCollisionResult::Destroyed { post_vel, damage }
+        } else {
             EntityCollisionResult::Survives {
-                post_pos: a.pos,
+                post_pos: item.pos_at(contact.time_of_impact)
+                    + post_vel * remaining,
                 post_vel,
-            },
-            EntityCollisionResult::Destroyed,
-        )
+                damage,
+            }
+        }
+    };
+    (
+        result(a, a_mat, q, -impulse),
+        result(b, b_mat, q_other, impulse),
+    )
+}
+
+/// Fraction of a fragmented body's specific impact energy that sends its
+/// fragments flying apart
+const FRAGMENT_ENERGY_FRACTION: f32 = 0.1;
+
+/// Health each fragment starts with
+const FRAGMENT_HEALTH: f32 = 1.;
+
+/// Debris left behind by a body broken up in a collision
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fragmentation {
+    pub outcome: CollisionOutcome,
+    /// States of the fragments at the collision tick
+    pub fragments: Vec<PhysicsState>,
+    /// Collision radius of each fragment, so that together they cover the
+    /// area of the body
+    pub radius: f32,
+}
+
+impl MapEntities for Fragmentation {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for fragment in &mut self.fragments {
+            fragment.map_entities(entity_mapper);
+        }
     }
 }
 
-fn calculate_inelastic_collision(
-    mass_a: f32,
-    vel_a: Vec2,
-    mass_b: f32,
-    vel_b: Vec2,
-) -> Vec2 {
-    // Calculate momentum conservation: p1 + p2 = (m1 + m2)v_final
-    let total_momentum = (vel_a * mass_a) + (vel_b * mass_b);
+/// Fragments of `destroyed` after being hit by `other`, or None if the impact
+/// wasn't violent enough to break it up
+///
+/// The fragments split the mass and area evenly and start spaced around a
+/// ring of `radius`, the destroyed body's, flying outwards from `post_vel`,
+/// the wreck's velocity after the impact. The spread cancels out, so mass and
+/// momentum are conserved
+pub fn calculate_fragmentation(
+    (destroyed, material): (&SpatialItem, &Material),
+    other: &SpatialItem,
+    post_vel: Vec2,
+    radius: f32,
+) -> Option<Fragmentation> {
+    let (q, _) = calculate_impact_energy(
+        destroyed.mass,
+        other.mass,
+        other.vel - destroyed.vel,
+    );
+    let outcome = CollisionOutcome::from_q(q, material.toughness);
+    let count = outcome.fragment_count();
+    if count == 0 {
+        return None;
+    }
 
-    // Final velocity = total momentum / total mass
-    total_momentum / (mass_a + mass_b)
+    let spread = (2. * FRAGMENT_ENERGY_FRACTION * q).sqrt();
+    // Offset the ring by half a step so no fragment starts straight towards
+    // the other body
+    let away = (destroyed.pos - other.pos).normalize_or(Vec2::X);
+    let fragments = (0..count)
+        .map(|i| {
+            let angle = PI * (2 * i + 1) as f32 / count as f32;
+            let dir = rotation_vec(angle).rotate(away);
+            PhysicsState {
+                pos: RealVec2::from_vec2(destroyed.pos + dir * radius),
+                vel: RealVec2::from_vec2(post_vel + dir * spread),
+                mass: Real::from_f32(destroyed.mass / count as f32),
+                health: FRAGMENT_HEALTH,
+                alive: true,
+                ..default()
+            }
+        })
+        .collect();
+    Some(Fragmentation {
+        outcome,
+        fragments,
+        radius: radius / (count as f32).sqrt(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+    use assertables::{assert_abs_diff_le_x, assert_approx_eq};
     use bevy::prelude::*;
 
-    use super::*;
+    use super::{Material, *};
 
     #[test]
     fn test_spatial_index() {
         let collider = Collider::from_dim(Vec2::splat(2.));
-        let mut spatial_index = SpatialIndexPerTick::default();
+        let mut spatial_index = SpatialIndex::default();
         let e0 = Entity::from_raw(0);
         let e1 = Entity::from_raw(1);
         let pos = Vec2::new(30., 0.);
         spatial_index.insert(
+            5,
             &collider,
             SpatialItem {
                 entity: e0,
+                prev_pos: pos,
                 pos,
                 vel: Vec2::new(0., 0.),
+                rotation: 0.,
                 mass: 1.,
+                health: 1.,
             },
         );
         spatial_index.insert(
+            5,
             &collider,
             SpatialItem {
                 entity: e1,
+                prev_pos: pos,
                 pos,
                 vel: Vec2::new(0., 0.),
+                rotation: 0.,
                 mass: 1.,
+                health: 1.,
             },
         );
 
-        let res = spatial_index.collides(e0, pos, &collider);
+        let res = spatial_index.collides(e0, 5, pos, 0., &collider);
         assert!(res.is_some());
         let (rect, item) = res.unwrap();
         assert_eq!(
@@ -347,6 +1458,449 @@ mod tests {
         assert_eq!(item.entity, e1);
     }
 
+    #[test]
+    fn test_sweep_catches_tunneling() {
+        let small = Collider::from_dim(Vec2::splat(1.));
+        let big = Collider::from_dim(Vec2::splat(4.));
+        let mut spatial_index = SpatialIndex::default();
+        let asteroid = Entity::from_raw(0);
+        let bullet = Entity::from_raw(1);
+        let item = |entity, prev_pos, pos| SpatialItem {
+            entity,
+            prev_pos,
+            pos,
+            vel: Vec2::ZERO,
+            rotation: 0.,
+            mass: 1.,
+            health: 1.,
+        };
+        spatial_index.insert(
+            5,
+            &big,
+            item(asteroid, Vec2::X * 50., Vec2::X * 50.),
+        );
+
+        // Neither end of the tick overlaps, but the path does
+        let (from, to) = (Vec2::ZERO, Vec2::X * 100.);
+        assert!(spatial_index.collides(bullet, 5, to, 0., &small).is_none());
+        let (toi, hit) = spatial_index
+            .sweep(bullet, 5, from, to, 0., &small)
+            .unwrap();
+        assert_eq!(hit.entity, asteroid);
+        assert_approx_eq!(toi, 0.475);
+
+        // Missing to the side
+        let offset = Vec2::Y * 3.;
+        assert!(spatial_index
+            .sweep(bullet, 5, from + offset, to + offset, 0., &small)
+            .is_none());
+    }
+
+    #[test]
+    fn test_spatial_index_segments() {
+        let collider = Collider::from_dim(Vec2::splat(2.));
+        let mut spatial_index = SpatialIndex::default();
+        let mover = Entity::from_raw(0);
+        let probe = Entity::from_raw(1);
+        let item = |tick: u64| {
+            let pos = Vec2::X * tick as f32 * 10.;
+            SpatialItem {
+                entity: mover,
+                prev_pos: pos - Vec2::X * 10.,
+                pos,
+                vel: Vec2::X * 10.,
+                rotation: 0.,
+                mass: 1.,
+                health: 1.,
+            }
+        };
+        for tick in 3..20 {
+            spatial_index.insert(tick, &collider, item(tick));
+        }
+        // One rtree entry per segment rather than per tick
+        assert_eq!(spatial_index.0.len(), 3);
+        assert!(spatial_index.0.values().all(|segment| segment
+            .rtree
+            .iter()
+            .count()
+            == 1));
+
+        // The segment covers the whole path, but only the tick's own item
+        // is hit
+        let hit = |spatial_index: &SpatialIndex, tick: u64, x: f32| {
+            spatial_index
+                .collides(probe, tick, Vec2::X * x, 0., &collider)
+                .map(|(_, item)| item.pos.x)
+        };
+        assert_eq!(hit(&spatial_index, 10, 100.), Some(100.));
+        assert_eq!(hit(&spatial_index, 10, 130.), None);
+        assert_eq!(hit(&spatial_index, 13, 130.), Some(130.));
+
+        spatial_index.prune_before(10);
+        assert_eq!(spatial_index.first_tick(), Some(10));
+        assert_eq!(spatial_index.0.len(), 2);
+
+        // Removing every tick of a segment drops it, pruning shrank the rest
+        for tick in 16..20 {
+            spatial_index.remove(tick, &mover);
+        }
+        assert_eq!(spatial_index.0.len(), 1);
+        assert_eq!(hit(&spatial_index, 15, 150.), Some(150.));
+        let segment = spatial_index.0.values().next().unwrap();
+        let rect = segment.rtree.iter().next().unwrap().rect.to_bevy();
+        assert_eq!(rect.min.x, 89.);
+    }
+
+    #[test]
+    fn test_segment_interpolates_between_ends() {
+        let collider = Collider::from_dim(Vec2::splat(2.));
+        let probe = Entity::from_raw(1);
+        let item = |prev: f32, pos: f32| SpatialItem {
+            entity: Entity::from_raw(0),
+            prev_pos: Vec2::X * prev,
+            pos: Vec2::X * pos,
+            vel: Vec2::X * (pos - prev),
+            rotation: 0.,
+            mass: 1.,
+            health: 1.,
+        };
+
+        // Steady movers are covered for the whole segment from the start, so
+        // the rtree entry is never refit
+        let mut steady = SpatialIndex::default();
+        let mut rects = Vec::new();
+        for tick in 8..16 {
+            let pos = tick as f32 * 10.;
+            steady.insert(tick, &collider, item(pos - 10., pos));
+            let segment = &steady.0[&8];
+            rects.push(segment.rtree.iter().next().unwrap().rect.to_bevy());
+        }
+        assert!(rects.iter().all(|rect| *rect == rects[0]));
+
+        // Accelerating, the ticks in between are off the straight line from
+        // 1m to 49m but still hit where they were indexed
+        let mut index = SpatialIndex::default();
+        for tick in 8..16 {
+            let t = (tick - 8) as f32;
+            index.insert(tick, &collider, item(t * t, (t + 1.) * (t + 1.)));
+        }
+        let hit = |index: &SpatialIndex, tick: u64, x: f32| {
+            index
+                .collides(probe, tick, Vec2::X * x, 0., &collider)
+                .map(|(_, item)| item.pos.x)
+        };
+        // Ends at 16m, interpolated to 25m
+        assert_eq!(hit(&index, 11, 16.), Some(25.));
+        assert_eq!(hit(&index, 11, 60.), None);
+        // The first and last ticks are stored as is
+        assert_eq!(hit(&index, 8, 1.), Some(1.));
+        assert_eq!(hit(&index, 15, 64.), Some(64.));
+
+        // Writing a tick drops the ones after it
+        index.remove(12, &Entity::from_raw(0));
+        assert_eq!(hit(&index, 12, 25.), None);
+        assert!(hit(&index, 11, 16.).is_some());
+    }
+
+    #[test]
+    fn test_time_of_impact_both_moving() {
+        let col = Collider::from_dim(Vec2::splat(2.));
+        // Head on at 10 m/s each, starting 20m apart, touch after 18m
+        let toi = time_of_impact(
+            (Vec2::ZERO, Vec2::X * 10., 0., &col),
+            (Vec2::X * 20., Vec2::X * 10., 0., &col),
+        );
+        assert_eq!(toi, Some(0.9));
+
+        // Same direction and speed never close the gap
+        let toi = time_of_impact(
+            (Vec2::ZERO, Vec2::X * 10., 0., &col),
+            (Vec2::X * 20., Vec2::X * 30., 0., &col),
+        );
+        assert_eq!(toi, None);
+    }
+
+    #[test]
+    fn test_rotated_box_overlap() {
+        // Missile-like 2x0.5 box next to a unit box 1.4m away
+        let missile = Collider::from_wh(2., 0.5);
+        let target = Collider::from_dim(Vec2::splat(1.));
+        let target_pos = Vec2::new(0., 1.4);
+
+        // Lying flat it can't reach
+        assert!(!missile.overlaps(Vec2::ZERO, 0., &target, target_pos, 0.));
+        // Turned to point at the target it does
+        assert!(missile.overlaps(
+            Vec2::ZERO,
+            FRAC_PI_2,
+            &target,
+            target_pos,
+            0.
+        ));
+        // Its rotated AABB is tall and thin
+        let aabb = missile.aabb(Vec2::ZERO, FRAC_PI_2);
+        assert_approx_eq!(aabb.width(), 0.5);
+        assert_approx_eq!(aabb.height(), 2.);
+    }
+
+    #[test]
+    fn test_circle_overlaps() {
+        let circle = Collider::circle(1.);
+        let unit_box = Collider::from_dim(Vec2::splat(2.));
+
+        // The AABBs of a circle and box overlap diagonally, the shapes don't
+        let pos = Vec2::splat(1.9);
+        assert!(!circle
+            .aabb(pos, 0.)
+            .intersect(unit_box.aabb(Vec2::ZERO, 0.))
+            .is_empty());
+        assert!(!circle.overlaps(pos, 0., &unit_box, Vec2::ZERO, 0.));
+        assert!(!unit_box.overlaps(Vec2::ZERO, 0., &circle, pos, 0.));
+        assert!(circle.overlaps(
+            Vec2::new(1.9, 0.),
+            0.,
+            &unit_box,
+            Vec2::ZERO,
+            0.
+        ));
+
+        assert!(circle.overlaps(Vec2::ZERO, 0., &circle, Vec2::X * 2., 0.));
+        assert!(!circle.overlaps(Vec2::ZERO, 0., &circle, Vec2::X * 2.1, 0.));
+    }
+
+    #[test]
+    fn test_polygon_overlap() {
+        let triangle = Collider::polygon([
+            Vec2::new(-1., -1.),
+            Vec2::new(1., -1.),
+            Vec2::new(0., 1.),
+        ])
+        .unwrap();
+        let unit_box = Collider::from_dim(Vec2::splat(1.));
+
+        // Beside the sloped edge, inside the triangle's AABB
+        let pos = Vec2::new(1., 0.6);
+        assert!(!triangle.overlaps(Vec2::ZERO, 0., &unit_box, pos, 0.));
+        // Flipped upside down the wide base is now on top
+        assert!(triangle.overlaps(Vec2::ZERO, PI, &unit_box, pos, 0.));
+    }
+
+    #[test]
+    fn test_polygon_validation() {
+        let clockwise = [Vec2::new(0., 1.), Vec2::new(1., -1.), -Vec2::ONE];
+        let Collider::Polygon { vertices } =
+            Collider::polygon(clockwise).unwrap()
+        else {
+            unreachable!();
+        };
+        assert_eq!(&*vertices, &[-Vec2::ONE, Vec2::new(1., -1.), Vec2::Y]);
+
+        assert!(Collider::polygon([Vec2::X, Vec2::Y]).is_err());
+        assert!(
+            Collider::polygon([Vec2::X, Vec2::Y, Vec2::Y, -Vec2::ONE]).is_err()
+        );
+        // Arrowhead with a notch at the back
+        let concave = [
+            Vec2::new(2., 0.),
+            Vec2::new(-1., 1.),
+            Vec2::new(-0.5, 0.),
+            Vec2::new(-1., -1.),
+        ];
+        assert!(Collider::polygon(concave).is_err());
+        // Bowtie crossing itself
+        let bowtie = [-Vec2::ONE, Vec2::ONE, Vec2::new(1., -1.), Vec2::NEG_X];
+        assert!(Collider::polygon(bowtie).is_err());
+        let off_centre = [Vec2::X, Vec2::new(2., 0.), Vec2::new(2., 1.)];
+        assert!(Collider::polygon(off_centre).is_err());
+    }
+
+    #[test]
+    fn test_time_of_impact_step_cap() {
+        // Thin stick across the diagonal, whose AABB is reached well before
+        // the stick is
+        let stick = Collider::from_wh(40., 0.02);
+        let ball = Collider::circle(0.1);
+        let toi = |from: f32, to: f32| {
+            time_of_impact(
+                (Vec2::new(from, 10.), Vec2::new(to, 10.), 0., &ball),
+                (Vec2::ZERO, Vec2::ZERO, FRAC_PI_4, &stick),
+            )
+        };
+
+        // Capped steps of 0.23m are still shorter than the 0.31m the ball
+        // overlaps the stick for
+        let hit = toi(-30., 30.).unwrap();
+        assert_abs_diff_le_x!(-30. + 60. * hit, 10. - 0.11 * SQRT_2, 1e-3);
+        // Steps of 7.8m pass through it
+        assert_eq!(toi(-1000., 1000.), None);
+    }
+
+    #[test]
+    fn test_time_of_impact_rotated() {
+        let missile = Collider::from_wh(2., 0.5);
+        let circle = Collider::circle(1.);
+        let (from, to) = (Vec2::new(-10., 0.), Vec2::new(10., 0.));
+        let still = (Vec2::new(0., 1.8), Vec2::new(0., 1.8), 0., &circle);
+
+        // Flying sideways its 2m length reaches the circle
+        let toi =
+            time_of_impact((from, to, FRAC_PI_2, &missile), still).unwrap();
+        assert!(toi > 0.45 && toi < 0.5, "{toi}");
+        // Pointing along its path it passes underneath
+        assert_eq!(time_of_impact((from, to, 0., &missile), still), None);
+    }
+
+    #[test]
+    fn test_contact_normal() {
+        let square = Collider::from_dim(Vec2::splat(2.));
+        let circle = Collider::circle(1.);
+
+        // Shallowest along x even though the centres are offset in y
+        let pos = Vec2::new(1.9, 0.5);
+        let normal = square.contact_normal(Vec2::ZERO, 0., &square, pos, 0.);
+        assert_eq!(normal, Vec2::X);
+        let normal = square.contact_normal(pos, 0., &square, Vec2::ZERO, 0.);
+        assert_eq!(normal, -Vec2::X);
+
+        // Circle just touching the bottom face
+        let pos = Vec2::new(0.5, 1.9);
+        let normal = circle.contact_normal(Vec2::ZERO, 0., &square, pos, 0.);
+        assert_eq!(normal, Vec2::Y);
+    }
+
+    #[test]
+    fn test_contact_impulse() {
+        let item = |x: f32, vel: Vec2| SpatialItem {
+            entity: Entity::from_raw(0),
+            prev_pos: Vec2::new(x, 0.),
+            pos: Vec2::new(x, 0.),
+            vel,
+            rotation: 0.,
+            mass: 1.,
+            health: 1.,
+        };
+        let a = item(0., Vec2::new(10., 5.));
+        let b = item(2., Vec2::ZERO);
+        let contact = Contact {
+            time_of_impact: 0.5,
+            normal: Vec2::X,
+        };
+        let impulse = |material: Material| {
+            calculate_contact_impulse(
+                (&a, &material),
+                (&b, &material),
+                &contact,
+            )
+        };
+
+        // Equal masses swap normal velocities, without friction the sliding
+        // velocity is kept
+        let bouncy = Material {
+            restitution: 1.,
+            friction: 0.,
+            ..default()
+        };
+        assert_eq!(impulse(bouncy), Vec2::new(10., 0.));
+
+        // Enough friction to stop sliding makes the bodies stick together
+        assert_eq!(impulse(Material::default()), Vec2::new(5., 2.5));
+
+        // Less friction only slows the sliding down
+        let slippery = Material {
+            friction: 0.2,
+            ..default()
+        };
+        let slip = impulse(slippery);
+        assert_approx_eq!(slip.x, 5.);
+        assert_approx_eq!(slip.y, 1.);
+
+        // Separating bodies are left alone
+        let b = item(2., Vec2::new(20., 0.));
+        let material = Material::default();
+        let impulse = calculate_contact_impulse(
+            (&a, &material),
+            (&b, &material),
+            &contact,
+        );
+        assert_eq!(impulse, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_outcome_scales_with_toughness() {
+        assert_eq!(
+            CollisionOutcome::from_q(500., 100.),
+            CollisionOutcome::Fracturing
+        );
+        assert_eq!(
+            CollisionOutcome::from_q(500., 1000.),
+            CollisionOutcome::Cratering
+        );
+        assert!(CollisionOutcome::is_destoyed(500., 100.));
+        assert!(!CollisionOutcome::is_destoyed(500., 1000.));
+        assert_approx_eq!(calculate_damage(500., 1000.), 0.5);
+    }
+
+    #[test]
+    fn test_fragmentation_conserves_mass_and_momentum() {
+        let item = |entity, pos, vel, mass| SpatialItem {
+            entity: Entity::from_raw(entity),
+            prev_pos: pos,
+            pos,
+            vel,
+            rotation: 0.,
+            mass,
+            health: 1.,
+        };
+        let rock = Material::default();
+        let asteroid = item(0, Vec2::ZERO, Vec2::new(0., 5.), 10.);
+        let missile = item(1, Vec2::new(3., 0.), Vec2::new(-200., 0.), 1.);
+        let fragment = |missile: &SpatialItem| {
+            let momentum =
+                asteroid.vel * asteroid.mass + missile.vel * missile.mass;
+            let post_vel = momentum / (asteroid.mass + missile.mass);
+            calculate_fragmentation((&asteroid, &rock), missile, post_vel, 4.)
+        };
+
+        // Q = 1/2 * (1 / 10) * 200^2 ~= 2000 J/kg
+        let debris = fragment(&missile).unwrap();
+        assert_eq!(debris.outcome, CollisionOutcome::MajorRestructuring);
+        assert_eq!(debris.fragments.len(), 3);
+
+        let missile = item(1, Vec2::new(3., 0.), Vec2::new(-100., 0.), 1.);
+        let debris = fragment(&missile).unwrap();
+        assert_eq!(debris.outcome, CollisionOutcome::Fracturing);
+        assert_eq!(debris.fragments.len(), 2);
+
+        // The survivor sticks to the wreck
+        let momentum =
+            asteroid.vel * asteroid.mass + missile.vel * missile.mass;
+        let survivor_vel = momentum / (asteroid.mass + missile.mass);
+        let total_mass: f32 =
+            debris.fragments.iter().map(|f| f.mass.to_f32()).sum();
+        let debris_momentum: Vec2 = debris
+            .fragments
+            .iter()
+            .map(|f| f.vel.to_vec2() * f.mass.to_f32())
+            .sum();
+        assert_approx_eq!(total_mass, asteroid.mass);
+        let after = debris_momentum + survivor_vel * missile.mass;
+        assert_abs_diff_le_x!(after.x, momentum.x, 1e-3);
+        assert_abs_diff_le_x!(after.y, momentum.y, 1e-3);
+
+        for fragment in &debris.fragments {
+            assert_approx_eq!(
+                fragment.pos.to_vec2().distance(asteroid.pos),
+                4.
+            );
+            // Neither fragment starts on the missile's side
+            assert!(fragment.pos.to_vec2().x < 1e-3);
+        }
+
+        // A gentle bump does nothing
+        let nudge = item(1, Vec2::new(3., 0.), Vec2::new(-1., 5.), 1.);
+        assert!(fragment(&nudge).is_none());
+    }
+
     #[test]
     fn test_slow_equal_mass() {
         let v = Vec2::new(50.0, 0.0); // 50 m/s
@@ -0,0 +1,620 @@
+//! Scalar types used by the physics core
+//!
+//! The simulation math (integration, beam forces, collision response) is
+//! written against `Real` and `RealVec2`. By default these are `f32` and
+//! `Vec2`. With the `fixed-point` cargo feature they switch to `I32F32`, a
+//! 64 bit fixed-point number with 32 fractional bits, so that every tick is
+//! computed with integer arithmetic only and produces the same bits regardless
+//! of compiler, optimization level or target. This is what lockstep play needs,
+//! where peers exchange inputs and each compute the timeline themselves.
+//!
+//! `PhysicsState` stores its kinematics (position, velocity, rotation, angular
+//! velocity, mass and propellant) as `Real` too, so they carry full precision
+//! from one tick to the next and only get converted to `f32` for rendering
+//! and input. Tuning parameters like thrust and torque limits stay `f32` and
+//! are converted exactly when a step reads them.
+//!
+//! Limitations: collision detection (`SpatialItem`, time of impact, contact
+//! normals) and `GravityField::acceleration` are still evaluated in `f32` on
+//! values converted from `Real`. They use only `+ - * /` and `sqrt`, which
+//! IEEE 754 requires to be correctly rounded, so they cost precision but not
+//! determinism. Going into `Real` is exact for magnitudes from 2^-9 up to
+//! 2^31, smaller magnitudes are truncated to 32 fractional bits.
+//!
+//! All `I32F32` operations saturate instead of overflowing or panicking so
+//! that debug and release builds agree even at the edges of the range.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Scalar used by the physics core
+#[cfg(not(feature = "fixed-point"))]
+pub type Real = f32;
+/// Scalar used by the physics core
+#[cfg(feature = "fixed-point")]
+pub type Real = I32F32;
+
+/// 2D vector used by the physics core
+#[cfg(not(feature = "fixed-point"))]
+pub type RealVec2 = Vec2;
+/// 2D vector used by the physics core
+#[cfg(feature = "fixed-point")]
+pub type RealVec2 = FixVec2;
+
+/// Arithmetic needed to advance the simulation
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + fmt::Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f32(value: f32) -> Self;
+    fn to_f32(self) -> f32;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    /// Remainder of dividing by `rhs`, always positive for positive `rhs`
+    fn rem_euclid(self, rhs: Self) -> Self;
+    /// Sine and cosine of an angle in radians
+    fn sin_cos(self) -> (Self, Self);
+}
+
+/// Quantity that can be advanced by an `Integrator` with scalar `S`
+pub trait Vector<S>:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<S, Output = Self>
+    + Div<S, Output = Self>
+    + Neg<Output = Self>
+{
+}
+
+/// 2D vector over a `Scalar`, convertible to and from bevy's `Vec2`
+pub trait Planar<S: Scalar>: Vector<S> {
+    fn from_vec2(vec: Vec2) -> Self;
+    fn to_vec2(self) -> Vec2;
+    /// Unit vector pointing at `angle` radians from +X
+    fn from_angle(angle: S) -> Self;
+    fn length(self) -> S;
+    fn length_squared(self) -> S;
+}
+
+impl Scalar for f32 {
+    const ONE: Self = 1.;
+    const ZERO: Self = 0.;
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn rem_euclid(self, rhs: Self) -> Self {
+        f32::rem_euclid(self, rhs)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        f32::sin_cos(self)
+    }
+}
+
+impl Vector<f32> for f32 {}
+
+impl Vector<f32> for Vec2 {}
+
+impl Planar<f32> for Vec2 {
+    fn from_vec2(vec: Vec2) -> Self {
+        vec
+    }
+
+    fn to_vec2(self) -> Vec2 {
+        self
+    }
+
+    fn from_angle(angle: f32) -> Self {
+        Vec2::from_angle(angle)
+    }
+
+    fn length(self) -> f32 {
+        Vec2::length(self)
+    }
+
+    fn length_squared(self) -> f32 {
+        Vec2::length_squared(self)
+    }
+}
+
+const FRAC_BITS: u32 = 32;
+const SCALE: f64 = (1u64 << FRAC_BITS) as f64;
+
+/// Signed fixed-point number with 32 integer and 32 fractional bits
+///
+/// Range is roughly +-2.1e9 with a resolution of 2.3e-10. Serialized as its
+/// raw bits so saved states load back exactly
+#[derive(
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub struct I32F32(i64);
+
+impl I32F32 {
+    pub const FRAC_PI_2: Self = Self(6_746_518_852);
+    pub const MAX: Self = Self(i64::MAX);
+    pub const MIN: Self = Self(i64::MIN);
+    pub const ONE: Self = Self(1 << FRAC_BITS);
+    pub const PI: Self = Self(13_493_037_705);
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_bits(bits: i64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> i64 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Self {
+        Self((value as i64) << FRAC_BITS)
+    }
+
+    /// Nearest representable value, saturating at the ends of the range
+    /// NaN maps to zero
+    pub fn from_f32(value: f32) -> Self {
+        // f32 -> f64 and scaling by a power of two are exact, the final cast
+        // truncates and saturates
+        Self((value as f64 * SCALE) as i64)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        (self.0 as f64 / SCALE) as f32
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        // sqrt(v / 2^32) * 2^32 = sqrt(v * 2^32)
+        Self(isqrt((self.0 as u128) << FRAC_BITS) as i64)
+    }
+
+    /// Exact, since both are multiples of the same resolution. Zero for a
+    /// zero `rhs`
+    pub fn rem_euclid(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            return Self::ZERO;
+        }
+        Self(self.0.wrapping_rem_euclid(rhs.0))
+    }
+
+    /// Sine and cosine computed with a Taylor series after reducing the
+    /// angle to [-pi/4, pi/4]
+    pub fn sin_cos(self) -> (Self, Self) {
+        // Nearest multiple of pi/2
+        let quotient =
+            ((self.0 as i128) << FRAC_BITS) / Self::FRAC_PI_2.0 as i128;
+        let quadrant = (quotient + (1 << (FRAC_BITS - 1))) >> FRAC_BITS;
+        let r = Self(saturate(
+            self.0 as i128 - quadrant * Self::FRAC_PI_2.0 as i128,
+        ));
+        let r2 = r * r;
+
+        // Horner form of the series up to r^11 and r^10
+        let one = Self::ONE;
+        let n = Self::from_int;
+        let sin = r
+            * (one
+                - r2 / n(6)
+                    * (one
+                        - r2 / n(20)
+                            * (one
+                                - r2 / n(42)
+                                    * (one
+                                        - r2 / n(72) * (one - r2 / n(110))))));
+        let cos = one
+            - r2 / n(2)
+                * (one
+                    - r2 / n(12)
+                        * (one
+                            - r2 / n(30)
+                                * (one - r2 / n(56) * (one - r2 / n(90)))));
+
+        match quadrant.rem_euclid(4) {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        }
+    }
+}
+
+/// Integer square root, rounded down
+fn isqrt(value: u128) -> u128 {
+    let mut rem = value;
+    let mut root = 0u128;
+    let mut bit = 1u128 << 126;
+    while bit > rem {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+fn saturate(value: i128) -> i64 {
+    value.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+impl fmt::Debug for I32F32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 as f64 / SCALE)
+    }
+}
+
+impl fmt::Display for I32F32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 as f64 / SCALE)
+    }
+}
+
+impl Add for I32F32 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl AddAssign for I32F32 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for I32F32 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl SubAssign for I32F32 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for I32F32 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(saturate((self.0 as i128 * rhs.0 as i128) >> FRAC_BITS))
+    }
+}
+
+impl Div for I32F32 {
+    type Output = Self;
+
+    /// Division by zero saturates towards the sign of the numerator
+    fn div(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            return match self.0.cmp(&0) {
+                Ordering::Less => Self::MIN,
+                Ordering::Equal => Self::ZERO,
+                Ordering::Greater => Self::MAX,
+            };
+        }
+        Self(saturate(((self.0 as i128) << FRAC_BITS) / rhs.0 as i128))
+    }
+}
+
+impl Neg for I32F32 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl Scalar for I32F32 {
+    const ONE: Self = I32F32::ONE;
+    const ZERO: Self = I32F32::ZERO;
+
+    fn from_f32(value: f32) -> Self {
+        I32F32::from_f32(value)
+    }
+
+    fn to_f32(self) -> f32 {
+        I32F32::to_f32(self)
+    }
+
+    fn abs(self) -> Self {
+        I32F32::abs(self)
+    }
+
+    fn sqrt(self) -> Self {
+        I32F32::sqrt(self)
+    }
+
+    fn rem_euclid(self, rhs: Self) -> Self {
+        I32F32::rem_euclid(self, rhs)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        I32F32::sin_cos(self)
+    }
+}
+
+impl Vector<I32F32> for I32F32 {}
+
+/// 2D vector of `I32F32`
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub struct FixVec2 {
+    pub x: I32F32,
+    pub y: I32F32,
+}
+
+impl FixVec2 {
+    pub const ZERO: Self = Self::new(I32F32::ZERO, I32F32::ZERO);
+
+    pub const fn new(x: I32F32, y: I32F32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn length_squared(self) -> I32F32 {
+        self.x * self.x + self.y * self.y
+    }
+
+    /// Computed from the raw bits so that large vectors don't saturate the
+    /// intermediate square
+    pub fn length(self) -> I32F32 {
+        let (x, y) = (self.x.0 as i128, self.y.0 as i128);
+        I32F32(isqrt((x * x + y * y) as u128) as i64)
+    }
+}
+
+impl Add for FixVec2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for FixVec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for FixVec2 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for FixVec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<I32F32> for FixVec2 {
+    type Output = Self;
+
+    fn mul(self, rhs: I32F32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Div<I32F32> for FixVec2 {
+    type Output = Self;
+
+    fn div(self, rhs: I32F32) -> Self {
+        Self::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl Neg for FixVec2 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+impl Index<usize> for FixVec2 {
+    type Output = I32F32;
+
+    fn index(&self, axis: usize) -> &I32F32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            _ => panic!("index out of bounds"),
+        }
+    }
+}
+
+impl IndexMut<usize> for FixVec2 {
+    fn index_mut(&mut self, axis: usize) -> &mut I32F32 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            _ => panic!("index out of bounds"),
+        }
+    }
+}
+
+impl Vector<I32F32> for FixVec2 {}
+
+impl Planar<I32F32> for FixVec2 {
+    fn from_vec2(vec: Vec2) -> Self {
+        Self::new(I32F32::from_f32(vec.x), I32F32::from_f32(vec.y))
+    }
+
+    fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.x.to_f32(), self.y.to_f32())
+    }
+
+    fn from_angle(angle: I32F32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    fn length(self) -> I32F32 {
+        FixVec2::length(self)
+    }
+
+    fn length_squared(self) -> I32F32 {
+        FixVec2::length_squared(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assertables::assert_abs_diff_le_x;
+
+    use super::*;
+
+    fn fx(value: f32) -> I32F32 {
+        I32F32::from_f32(value)
+    }
+
+    #[test]
+    fn test_conversion_round_trips() {
+        for value in [0., 1., -1., 0.5, 1234.5, -0.0078125] {
+            assert_eq!(fx(value).to_f32(), value);
+        }
+        assert_eq!(fx(f32::NAN), I32F32::ZERO);
+        assert_eq!(fx(f32::INFINITY), I32F32::MAX);
+        assert_eq!(fx(f32::NEG_INFINITY), I32F32::MIN);
+    }
+
+    #[test]
+    fn test_state_conversion_round_trips() {
+        // Every f32 from 2^-9 up to 2^31 has at most 32 fractional bits, so
+        // tuning parameters and collision results go into a step unchanged
+        let mut bits = 2f32.powi(-9).to_bits();
+        while bits < 2f32.powi(31).to_bits() {
+            let value = f32::from_bits(bits);
+            assert_eq!(fx(value).to_f32(), value);
+            assert_eq!(fx(-value).to_f32(), -value);
+            bits += 9973;
+        }
+
+        // Smaller magnitudes are truncated
+        let lsb = 2f32.powi(-32);
+        assert_eq!(fx(lsb * 1.5).to_f32(), lsb);
+        assert_eq!(fx(lsb * 0.5), I32F32::ZERO);
+
+        // Converting a result to f32 rounds it once, converting it again
+        // after another round trip doesn't drift further
+        let stored = (fx(1.) / fx(3.)).to_f32();
+        assert_eq!(stored, 1. / 3.);
+        assert_eq!(fx(stored).to_f32(), stored);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(fx(1.5) + fx(2.25), fx(3.75));
+        assert_eq!(fx(1.5) - fx(2.25), fx(-0.75));
+        assert_eq!(fx(1.5) * fx(-2.), fx(-3.));
+        assert_eq!(fx(3.) / fx(4.), fx(0.75));
+        assert_eq!(-fx(2.), fx(-2.));
+    }
+
+    #[test]
+    fn test_saturates() {
+        assert_eq!(I32F32::MAX + I32F32::ONE, I32F32::MAX);
+        assert_eq!(I32F32::MIN - I32F32::ONE, I32F32::MIN);
+        assert_eq!(fx(1e6) * fx(1e6), I32F32::MAX);
+        assert_eq!(fx(1.) / I32F32::ZERO, I32F32::MAX);
+        assert_eq!(fx(-1.) / I32F32::ZERO, I32F32::MIN);
+        assert_eq!(-I32F32::MIN, I32F32::MAX);
+    }
+
+    #[test]
+    fn test_rem_euclid() {
+        assert_eq!(fx(7.5).rem_euclid(fx(2.)), fx(1.5));
+        assert_eq!(fx(-0.5).rem_euclid(fx(2.)), fx(1.5));
+        assert_eq!(fx(1.).rem_euclid(I32F32::ZERO), I32F32::ZERO);
+        assert_eq!(I32F32::MIN.rem_euclid(-I32F32::from_bits(1)), I32F32::ZERO);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(fx(4.).sqrt(), fx(2.));
+        assert_eq!(fx(0.25).sqrt(), fx(0.5));
+        assert_eq!(fx(-4.).sqrt(), I32F32::ZERO);
+        assert_abs_diff_le_x!(fx(2.).sqrt().to_f32(), 2f32.sqrt(), 1e-6);
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        let mut angle = -10.;
+        while angle < 10. {
+            let (sin, cos) = fx(angle).sin_cos();
+            assert_abs_diff_le_x!(sin.to_f32(), angle.sin(), 1e-6);
+            assert_abs_diff_le_x!(cos.to_f32(), angle.cos(), 1e-6);
+            angle += 0.37;
+        }
+        assert_eq!(I32F32::ZERO.sin_cos(), (I32F32::ZERO, I32F32::ONE));
+    }
+
+    #[test]
+    fn test_vec_length() {
+        let v = FixVec2::from_vec2(Vec2::new(3., 4.));
+        assert_eq!(v.length(), fx(5.));
+        // Squaring would saturate, the length doesn't
+        let far = FixVec2::from_vec2(Vec2::new(3e5, 4e5));
+        assert_eq!(far.length(), fx(5e5));
+        assert_eq!(far.length_squared(), I32F32::MAX);
+    }
+}
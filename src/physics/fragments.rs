@@ -0,0 +1,229 @@
+//! Debris of bodies broken up in collisions
+//!
+//! A `Breakable` body destroyed by a violent enough impact breaks up, and the
+//! collision records the fragments in its timeline, see `Fragmentation`.
+//! `sync_fragments` spawns them as bodies of their own as soon as the
+//! collision is predicted, with timelines starting at the collision tick, so
+//! predictions show the debris field and headless simulations and replays
+//! collide with it like any other body. Clients add their own visuals.
+
+use serde::{Deserialize, Serialize};
+
+use super::{collisions::Material, *};
+use crate::prelude::*;
+
+/// Marks a body that breaks up into fragments when destroyed in a collision
+///
+/// Fragments are breakable too
+#[derive(
+    Component,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+pub struct Breakable;
+
+/// Piece of a body broken up in a predicted collision
+///
+/// Fragments are spawned as soon as the collision is predicted, with a
+/// timeline starting at the collision tick, and stay hidden until then, see
+/// `sync_physics_state_transform`. If the prediction changes they are
+/// despawned again
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize,
+)]
+pub struct Fragment {
+    #[serde(with = "entity_bits")]
+    pub parent: Entity,
+    /// Tick of the collision that created this fragment
+    pub tick: u64,
+}
+
+impl MapEntities for Fragment {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.parent = entity_mapper.map_entity(self.parent);
+    }
+}
+
+/// Bundle for a fragment of `parent`, made of `material`, appearing at `tick`
+/// in `state`
+pub fn fragment_bundle(
+    tick: u64,
+    parent: Entity,
+    state: &PhysicsState,
+    radius: f32,
+    material: Material,
+) -> impl Bundle {
+    (
+        Transform::from_translation(state.pos.to_vec2().to3()),
+        PhysicsBundle::from_state(
+            tick,
+            state.clone(),
+            Vec2::splat(radius * 2.),
+        )
+        .with_collider(Collider::circle(radius)),
+        material,
+        Breakable,
+        Fragment { parent, tick },
+        Visibility::Hidden,
+    )
+}
+
+/// Spawn fragments for breakable bodies predicted to break up
+///
+/// Fragments from the recomputed part of a parent's timeline are replaced by
+/// the ones from the new prediction
+pub fn sync_fragments(
+    mut commands: Commands,
+    parents: Query<(Entity, &Timeline, Option<&Material>), With<Breakable>>,
+    fragments: Query<(Entity, &Fragment)>,
+) {
+    for (parent, timeline, material) in parents.iter() {
+        let Some(updated) = timeline.last_updated_range.clone() else {
+            continue;
+        };
+
+        for (entity, fragment) in fragments.iter() {
+            if fragment.parent == parent && updated.contains(&fragment.tick) {
+                commands.queue(despawn_fragment(entity));
+            }
+        }
+
+        let material = material.copied().unwrap_or_default();
+        for (tick, debris) in timeline.fragmentations.range(updated) {
+            for state in &debris.fragments {
+                commands.spawn(fragment_bundle(
+                    *tick,
+                    parent,
+                    state,
+                    debris.radius,
+                    material,
+                ));
+            }
+        }
+    }
+}
+
+/// Despawn a fragment that is no longer predicted, along with its own
+/// fragments
+fn despawn_fragment(entity: Entity) -> impl Command {
+    move |world: &mut World| despawn_fragment_tree(world, entity)
+}
+
+fn despawn_fragment_tree(world: &mut World, entity: Entity) {
+    let Some(timeline) = world.get::<Timeline>(entity).cloned() else {
+        return;
+    };
+
+    // Anything that collided with it has to be recomputed
+    for (tick, collision) in timeline
+        .sim_events
+        .iter()
+        .flat_map(|(tick, events)| events.iter().map(move |e| (tick, e)))
+    {
+        if let Some(mut other) = world.get_mut::<Timeline>(collision.other) {
+            other.last_computed_tick = other.last_computed_tick.min(tick - 1);
+        }
+    }
+
+    let mut spatial_index = world.resource_mut::<SpatialIndex>();
+    for (tick, _) in timeline.states() {
+        spatial_index.remove(tick, &entity);
+    }
+
+    let children = world
+        .query::<(Entity, &Fragment)>()
+        .iter(world)
+        .filter(|(_, fragment)| fragment.parent == entity)
+        .map(|(child, _)| child)
+        .collect::<Vec<_>>();
+    world.despawn(entity);
+    for child in children {
+        despawn_fragment_tree(world, child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        test_utils::{PhysicsStateBuilderExt, TestStateBuilder},
+        PhysicsEnabled,
+        PhysicsSimulationPlugin,
+    };
+
+    #[test]
+    fn test_breakable_body_spawns_fragments_without_a_client() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::ParallaxProtocolArenaPlugin {
+                config: default(),
+                physics: PhysicsSimulationPlugin {
+                    should_keep_alive: false,
+                    is_test: true,
+                },
+                client: None,
+            })
+            .insert_resource(PhysicsEnabled);
+
+        // Hits the asteroid at tick 5
+        let ship_st = TestStateBuilder::new().vel(600., 0.).mass(100.).b();
+        app.world_mut().spawn(PhysicsBundle::new_with_events(
+            ship_st,
+            Vec2::ONE,
+            0,
+            [],
+        ));
+        let asteroid_st = TestStateBuilder::new().pos(50., 0.).mass(10.).b();
+        let asteroid = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(
+                    asteroid_st,
+                    Vec2::splat(4.),
+                    0,
+                    [],
+                ),
+                Breakable,
+            ))
+            .id();
+        app.update();
+        app.update();
+
+        let debris = app
+            .world()
+            .get::<Timeline>(asteroid)
+            .unwrap()
+            .fragmentations
+            .get(&5)
+            .cloned()
+            .unwrap();
+        let mut fragments = app.world_mut().query::<(
+            &Fragment,
+            &Collider,
+            &Material,
+            &Visibility,
+            Has<Breakable>,
+        )>();
+        let fragments = fragments.iter(app.world()).collect::<Vec<_>>();
+        assert_eq!(fragments.len(), debris.fragments.len());
+        for (fragment, collider, material, visibility, breakable) in fragments {
+            assert_eq!(
+                *fragment,
+                Fragment {
+                    parent: asteroid,
+                    tick: 5
+                }
+            );
+            assert_eq!(*collider, Collider::circle(debris.radius));
+            assert_eq!(*material, Material::default());
+            assert_eq!(*visibility, Visibility::Hidden);
+            assert!(breakable);
+        }
+    }
+}
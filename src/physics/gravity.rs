@@ -0,0 +1,222 @@
+//! Point-mass gravity wells
+//!
+//! Entities with a `GravitySource` attract every other simulated entity. The
+//! acceleration from all sources is summed into the force model integrated by
+//! `compute_future_states`, so predicted trajectories include slingshots
+//! around massive bodies.
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::prelude::*;
+
+/// Newtonian gravitational constant (m^3 kg^-1 s^-2)
+pub const GRAVITATIONAL_CONSTANT: f32 = 6.674e-11;
+
+/// Marks an entity as a point-mass source of gravity
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize,
+)]
+pub struct GravitySource {
+    /// Attracting mass in kilograms
+    /// Independent of `PhysicsState::mass` so that the well can be far more
+    /// massive than its collision response suggests
+    pub mass: f32,
+
+    /// Softening radius in meters
+    /// Keeps the force finite as the distance to the source approaches zero
+    pub softening_radius: f32,
+
+    /// Distance in meters beyond which the source exerts no force
+    pub cutoff: Option<f32>,
+}
+
+impl GravitySource {
+    /// Acceleration at `pos` due to this source located at `source_pos`
+    /// Uses Plummer softening: a = G * M * d / (|d|^2 + eps^2)^(3/2)
+    pub fn acceleration(&self, source_pos: Vec2, pos: Vec2) -> Vec2 {
+        let offset = source_pos - pos;
+        let dist_sq = offset.length_squared();
+        if !self.in_range(dist_sq) {
+            return Vec2::ZERO;
+        }
+
+        let softened = dist_sq + self.softening_radius * self.softening_radius;
+        if softened <= 0.0 {
+            return Vec2::ZERO;
+        }
+        offset
+            * (GRAVITATIONAL_CONSTANT * self.mass
+                / (softened * softened.sqrt()))
+    }
+
+    fn in_range(&self, dist_sq: f32) -> bool {
+        self.cutoff.is_none_or(|cutoff| dist_sq <= cutoff * cutoff)
+    }
+}
+
+/// A gravity source's state at the start of a tick
+#[derive(Clone, Debug)]
+struct GravityWell {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    source: GravitySource,
+}
+
+/// Snapshot of every gravity source at the start of a tick
+#[derive(Clone, Debug, Default)]
+pub struct GravityField {
+    wells: Vec<GravityWell>,
+}
+
+impl GravityField {
+    /// Build the field from each source's state at `tick`
+    /// Sources without a live state at `tick` are skipped
+    pub fn at_tick<'a>(
+        tick: u64,
+        sources: impl IntoIterator<Item = (Entity, &'a GravitySource, &'a Timeline)>,
+    ) -> Self {
+        let mut field = GravityField::default();
+        for (entity, source, timeline) in sources {
+            if let Some(state) = timeline.state(tick) {
+                field.push(entity, &state, *source);
+            }
+        }
+        field
+    }
+
+    pub fn push(
+        &mut self,
+        entity: Entity,
+        state: &PhysicsState,
+        source: GravitySource,
+    ) {
+        if !state.alive {
+            return;
+        }
+        self.wells.push(GravityWell {
+            entity,
+            pos: state.pos.to_vec2(),
+            vel: state.vel.to_vec2(),
+            source,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wells.is_empty()
+    }
+
+    /// Whether any source can pull on `entity`, starting at `pos` and moving
+    /// at `vel`, during a tick of `seconds`
+    ///
+    /// Conservative: sources without a cutoff always reach
+    pub fn reaches(
+        &self,
+        entity: Entity,
+        pos: Vec2,
+        vel: Vec2,
+        seconds: f32,
+    ) -> bool {
+        self.wells
+            .iter()
+            .filter(|well| well.entity != entity)
+            .any(|well| {
+                let Some(cutoff) = well.source.cutoff else {
+                    return true;
+                };
+                let closing = (vel - well.vel).length() * seconds;
+                well.pos.distance(pos) <= cutoff + closing
+            })
+    }
+
+    /// Acceleration felt by `entity` at `pos`, `t` seconds into the tick
+    ///
+    /// Sources are extrapolated linearly through the tick and never attract
+    /// themselves
+    pub fn acceleration(&self, entity: Entity, t: f32, pos: Vec2) -> Vec2 {
+        self.wells.iter().filter(|well| well.entity != entity).fold(
+            Vec2::ZERO,
+            |acc, well| {
+                acc + well.source.acceleration(well.pos + well.vel * t, pos)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assertables::assert_approx_eq;
+
+    use super::*;
+
+    fn source(mass: f32) -> GravitySource {
+        GravitySource {
+            mass,
+            softening_radius: 0.,
+            cutoff: None,
+        }
+    }
+
+    #[test]
+    fn test_inverse_square() {
+        let well = source(1e12);
+        let near = well.acceleration(Vec2::ZERO, Vec2::new(10., 0.));
+        let far = well.acceleration(Vec2::ZERO, Vec2::new(20., 0.));
+
+        // Points back towards the source
+        assert!(near.x < 0.);
+        assert_approx_eq!(near.y, 0.);
+        assert_approx_eq!(near.x, -GRAVITATIONAL_CONSTANT * 1e12 / 100.);
+        assert_approx_eq!(near.x / far.x, 4.);
+    }
+
+    #[test]
+    fn test_softening_and_cutoff() {
+        let well = GravitySource {
+            softening_radius: 1.,
+            cutoff: Some(50.),
+            ..source(1e12)
+        };
+
+        // Softening keeps the force finite at the centre
+        assert_eq!(well.acceleration(Vec2::ZERO, Vec2::ZERO), Vec2::ZERO);
+        let close = well.acceleration(Vec2::ZERO, Vec2::new(0.01, 0.));
+        assert!(close.is_finite());
+
+        assert_ne!(
+            well.acceleration(Vec2::ZERO, Vec2::new(50., 0.)),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            well.acceleration(Vec2::ZERO, Vec2::new(51., 0.)),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_field_sums_sources_and_skips_self() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+        let state = |x: f32| PhysicsState {
+            pos: RealVec2::from_vec2(Vec2::new(x, 0.)),
+            alive: true,
+            ..default()
+        };
+
+        let mut field = GravityField::default();
+        field.push(a, &state(-10.), source(1e12));
+        field.push(b, &state(10.), source(1e12));
+
+        // Equal and opposite pulls cancel in the middle
+        let mid = field.acceleration(Entity::from_raw(3), 0., Vec2::ZERO);
+        assert_approx_eq!(mid.x, 0.);
+
+        // A is only pulled by B
+        let on_a = field.acceleration(a, 0., Vec2::new(-10., 0.));
+        assert_eq!(
+            on_a,
+            source(1e12).acceleration(Vec2::new(10., 0.), Vec2::new(-10., 0.))
+        );
+    }
+}
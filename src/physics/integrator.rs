@@ -0,0 +1,206 @@
+//! Numerical integration schemes used to advance physics state by one tick
+//!
+//! All schemes share the same interface: given a position, velocity and an
+//! acceleration function they return the position and velocity `dt` seconds
+//! later. The acceleration function receives the time offset into the step
+//! so that time-varying forces (e.g. thrust while rotating) can be sampled at
+//! the right point.
+//!
+//! Steps are generic over the scalar type so the same schemes run on `f32` and
+//! on the fixed-point `Real` used by the `fixed-point` feature.
+
+use serde::{Deserialize, Serialize};
+
+use super::fixed::{Scalar, Vector};
+use crate::prelude::*;
+
+/// Numerical scheme used to advance position and velocity each tick
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+pub enum Integrator {
+    /// First order explicit Euler
+    /// Position advances with the velocity from the start of the tick.
+    /// Cheapest option, but gains energy in oscillating systems
+    #[default]
+    ExplicitEuler,
+
+    /// First order semi-implicit (symplectic) Euler
+    /// Velocity is updated first and the new velocity advances position
+    SemiImplicitEuler,
+
+    /// Second order velocity Verlet
+    /// Exact for constant acceleration, keeps spring energy bounded
+    VelocityVerlet,
+
+    /// Classic fourth order Runge-Kutta
+    /// Most accurate, costs four acceleration evaluations per tick
+    Rk4,
+}
+
+impl Integrator {
+    /// Advance `pos` and `vel` by `dt` seconds
+    ///
+    /// `accel(t, pos, vel)` returns the acceleration `t` seconds into the step
+    pub fn step<S: Scalar, V: Vector<S>>(
+        self,
+        pos: V,
+        vel: V,
+        dt: S,
+        accel: impl Fn(S, V, V) -> V,
+    ) -> (V, V) {
+        let half = S::from_f32(0.5) * dt;
+        let two = S::from_f32(2.);
+        match self {
+            Integrator::ExplicitEuler => {
+                let a = accel(S::ZERO, pos, vel);
+                (pos + vel * dt, vel + a * dt)
+            }
+            Integrator::SemiImplicitEuler => {
+                let vel = vel + accel(S::ZERO, pos, vel) * dt;
+                (pos + vel * dt, vel)
+            }
+            Integrator::VelocityVerlet => {
+                let a0 = accel(S::ZERO, pos, vel);
+                let new_pos = pos + vel * dt + a0 * (half * dt);
+                // Velocity dependent forces see a first order prediction of
+                // the end-of-step velocity
+                let a1 = accel(dt, new_pos, vel + a0 * dt);
+                (new_pos, vel + (a0 + a1) * half)
+            }
+            Integrator::Rk4 => {
+                let k1_x = vel;
+                let k1_v = accel(S::ZERO, pos, vel);
+
+                let k2_x = vel + k1_v * half;
+                let k2_v = accel(half, pos + k1_x * half, k2_x);
+
+                let k3_x = vel + k2_v * half;
+                let k3_v = accel(half, pos + k2_x * half, k3_x);
+
+                let k4_x = vel + k3_v * dt;
+                let k4_v = accel(dt, pos + k3_x * dt, k4_x);
+
+                let sixth = dt / S::from_f32(6.);
+                (
+                    pos + (k1_x + k2_x * two + k3_x * two + k4_x) * sixth,
+                    vel + (k1_v + k2_v * two + k3_v * two + k4_v) * sixth,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assertables::assert_abs_diff_le_x;
+
+    use super::*;
+    use crate::physics::fixed::I32F32;
+
+    const ALL: [Integrator; 4] = [
+        Integrator::ExplicitEuler,
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+    ];
+
+    /// Integrate `ticks` steps of a 1D system, returning the final (pos, vel)
+    fn run(
+        integrator: Integrator,
+        (mut pos, mut vel): (f32, f32),
+        dt: f32,
+        ticks: usize,
+        accel: impl Fn(f32, f32, f32) -> f32,
+    ) -> (f32, f32) {
+        for _ in 0..ticks {
+            (pos, vel) = integrator.step(pos, vel, dt, &accel);
+        }
+        (pos, vel)
+    }
+
+    #[test]
+    fn test_constant_acceleration() {
+        // x(t) = x0 + v0 t + a t^2 / 2
+        let (x0, v0, a, dt, ticks) = (5., -2., 3., 1. / 60., 600);
+        let t = dt * ticks as f32;
+        let expected_pos = x0 + v0 * t + 0.5 * a * t * t;
+        let expected_vel = v0 + a * t;
+
+        for integrator in ALL {
+            let (pos, vel) = run(integrator, (x0, v0), dt, ticks, |_, _, _| a);
+            assert_abs_diff_le_x!(vel, expected_vel, 1e-2);
+            match integrator {
+                // First order schemes are off by a t dt / 2
+                Integrator::ExplicitEuler | Integrator::SemiImplicitEuler => {
+                    assert_abs_diff_le_x!(pos, expected_pos, a * t * dt);
+                }
+                Integrator::VelocityVerlet | Integrator::Rk4 => {
+                    assert_abs_diff_le_x!(pos, expected_pos, 5e-2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_spring_oscillation() {
+        // x'' = -w^2 x with x(0) = 1, v(0) = 0 => x(t) = cos(w t)
+        let (w, dt) = (2., 1. / 60.);
+        let spring = |_, x: f32, _| -w * w * x;
+        // Five full periods
+        let ticks = (5. * 2. * PI / w / dt) as usize;
+        let t = ticks as f32 * dt;
+        let expected = ((w * t).cos(), -w * (w * t).sin());
+
+        let error = |integrator| {
+            let (pos, vel) = run(integrator, (1., 0.), dt, ticks, spring);
+            (pos - expected.0).abs() + (vel - expected.1).abs() / w
+        };
+
+        assert!(error(Integrator::Rk4) < 1e-3);
+        assert!(error(Integrator::VelocityVerlet) < 1e-2);
+        assert!(error(Integrator::SemiImplicitEuler) < 5e-2);
+        // Explicit euler gains energy every tick
+        assert!(error(Integrator::ExplicitEuler) > 0.1);
+    }
+
+    #[test]
+    fn test_spring_energy_stays_bounded() {
+        let (w, dt) = (2., 1. / 10.);
+        let spring = |_, x: f32, _| -w * w * x;
+        let energy = |(x, v): (f32, f32)| 0.5 * v * v + 0.5 * w * w * x * x;
+        let initial = energy((1., 0.));
+
+        for integrator in [Integrator::VelocityVerlet, Integrator::Rk4] {
+            let end = run(integrator, (1., 0.), dt, 300, spring);
+            assert_abs_diff_le_x!(energy(end), initial, 0.05 * initial);
+        }
+
+        let end = run(Integrator::ExplicitEuler, (1., 0.), dt, 300, spring);
+        assert!(energy(end) > 10. * initial);
+    }
+
+    #[test]
+    fn test_fixed_point_constant_acceleration() {
+        let fx = I32F32::from_f32;
+        let (dt, a) = (fx(0.25), fx(2.));
+        let (mut pos, mut vel) = (I32F32::ZERO, I32F32::ZERO);
+        for _ in 0..8 {
+            (pos, vel) =
+                Integrator::VelocityVerlet.step(pos, vel, dt, |_, _, _| a);
+        }
+        // Exact for constant acceleration, and every intermediate is exactly
+        // representable
+        assert_eq!(vel, fx(4.));
+        assert_eq!(pos, fx(4.));
+    }
+}
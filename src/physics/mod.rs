@@ -18,16 +18,27 @@
 //! - `Timeline`: Manages scheduled control inputs and computed future states
 //!   for an entity
 //! - `SimulationConfig`: Controls global simulation parameters and time flow
+//! - `PredictionPolicy`: Optional per-entity prediction horizon and resolution
 //!
 //! # How It Works
 //!
 //! 1. Control inputs (thrust, rotation changes, etc.) are scheduled at specific
 //!    simulation ticks
 //! 2. The system computes future states by integrating physics from the current
-//!    state
+//!    state. Entities drifting with no input or force acting on them are
+//!    extrapolated in closed form instead, see `Coast`
 //! 3. When new inputs are added, affected future states are invalidated and
-//!    recomputed
+//!    recomputed. Within each tick, islands of entities coupled by beams or
+//!    collisions are computed in parallel
 //! 4. Entity transforms are synchronized with the current simulation tick
+//! 5. States from the last `SimulationConfig::history_ticks` ticks are kept, so
+//!    the simulation can be taken back to any of them with `rewind`. Destroyed
+//!    bodies are hidden rather than despawned until their destruction leaves
+//!    this window
+//!
+//! Each tick runs the `SimulationUpdate` schedule, whose `SimulationSet`s
+//! order input handling, gameplay, prediction, syncing and cleanup. Systems
+//! that depend on the tick run there and read it from `SimTick`.
 //!
 //! # Coordinate System
 //!
@@ -40,47 +51,75 @@
 //! # Physics Model
 //!
 //! The simulation uses a simplified 2D physics model with these properties:
-//! - No gravity or orbital mechanics
-//! - Constant mass (no fuel consumption)
+//! - Point-mass gravity from entities with a `GravitySource`
+//! - Thrust burns propellant following the rocket equation; crafts with no
+//!   exhaust velocity have unlimited propellant
 //! - Instant thrust response
-//! - Perfect rigid body collisions
+//! - Rotation slews at a bounded angular acceleration for crafts with a
+//!   `max_torque`, and is instant otherwise
+//! - Perfect rigid body collisions between circle, oriented box and convex
+//!   polygon colliders
+//! - Optional arena bounds that wrap, reflect or destroy bodies crossing them,
+//!   see `ArenaBounds`
 //!
 //! # Limitations
 //!
-//! - No continuous collision detection (may miss collisions at high velocities)
-//! - Limited accuracy from simple Euler integration
+//! - Continuous collision detection sweeps colliders in a straight line between
+//!   tick positions, ignoring curvature and rotation within a tick
+//! - Integration accuracy depends on `SimulationConfig::integrator`; the
+//!   default explicit Euler drifts over long prediction horizons
+//! - Only the `fixed-point` feature guarantees bit-identical timelines across
+//!   builds and targets, see `fixed`
 //! - No support for non-rigid body deformation
 
+pub mod bounds;
 pub mod collisions;
+pub mod fixed;
+pub mod fragments;
+pub mod gravity;
+pub mod integrator;
 #[cfg(test)]
 mod test_utils;
 pub mod timeline;
 
 use std::{
     ops::{RangeBounds, RangeInclusive},
-    sync::Arc,
     time::Duration,
 };
 
 use bevy::{
-    ecs::schedule::ScheduleLabel,
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        schedule::{ExecutorKind, ScheduleLabel},
+        system::RunSystemOnce,
+    },
     time::common_conditions::on_timer,
     utils::warn,
 };
+pub use bounds::{ArenaBounds, BoundaryMode};
 use collisions::{
     calculate_collision_result,
+    calculate_fragmentation,
     calculate_impact_energy,
+    find_contact,
+    is_closing,
     viz_colliders,
     Collider,
     Collision,
     EntityCollisionResult,
+    Fragmentation,
     SpatialIndex,
     SpatialItem,
 };
+pub use fixed::{Planar, Real, RealVec2, Scalar};
+pub use fragments::{Breakable, Fragment};
+pub use gravity::{GravityField, GravitySource};
+pub use integrator::Integrator;
+use serde::{Deserialize, Serialize};
 use timeline::compute_future_states;
-pub use timeline::Timeline;
+pub use timeline::{Coast, Timeline};
 
-use crate::prelude::*;
+use crate::{prelude::*, utils::entity_bits, Health};
 
 #[derive(Bundle)]
 pub struct PhysicsBundle {
@@ -95,7 +134,7 @@ impl PhysicsBundle {
         state: PhysicsState,
         dim: Vec2,
     ) -> PhysicsBundle {
-        let collider = Collider(BRect::from_corners(-dim / 2., dim / 2.));
+        let collider = Collider::from_dim(dim);
         let mut timeline = Timeline::default();
         timeline.future_states.insert(tick, state.clone());
         timeline.last_computed_tick = tick;
@@ -106,6 +145,12 @@ impl PhysicsBundle {
         }
     }
 
+    /// Replace the default box collider with another shape
+    pub fn with_collider(mut self, collider: Collider) -> PhysicsBundle {
+        self.collider = collider;
+        self
+    }
+
     pub fn new_with_events(
         state: PhysicsState,
         dim: Vec2,
@@ -113,7 +158,14 @@ impl PhysicsBundle {
         events: impl IntoIterator<Item = (u64, ControlInput)>,
     ) -> PhysicsBundle {
         let mut bundle = PhysicsBundle::from_state(state_tick, state, dim);
-        bundle.timeline.input_events.extend(events);
+        for (tick, event) in events {
+            bundle
+                .timeline
+                .input_events
+                .entry(tick)
+                .or_default()
+                .push(event);
+        }
         bundle
     }
 
@@ -129,15 +181,21 @@ impl PhysicsBundle {
         PhysicsBundle::from_state(
             tick,
             PhysicsState {
-                pos,
-                vel,
-                rotation,
-                ang_vel: 0.,
-                mass,
+                pos: RealVec2::from_vec2(pos),
+                vel: RealVec2::from_vec2(vel),
+                rotation: Real::from_f32(rotation),
+                ang_vel: Real::ZERO,
+                mass: Real::from_f32(mass),
                 current_thrust: 0.,
                 max_thrust,
+                propellant: Real::ZERO,
+                exhaust_velocity: 0.,
+                moment_of_inertia: 0.,
+                max_torque: 0.,
+                target_rotation: None,
+                health: 1.,
                 alive: true,
-                elastic_beam: None,
+                elastic_beams: Vec::new(),
             },
             dim,
         )
@@ -146,27 +204,29 @@ impl PhysicsBundle {
 
 /// Represents the complete physical state of a simulated entity at a point in
 /// time
-#[derive(Component, Clone, Debug, Default, PartialEq)]
+#[derive(
+    Component, Clone, Debug, Default, PartialEq, Serialize, Deserialize,
+)]
 #[require(Transform, Timeline)]
 pub struct PhysicsState {
     /// Position in world space (meters)
     /// Origin at center, +X right, +Y up
-    pub pos: Vec2,
+    pub pos: RealVec2,
 
     /// Velocity vector (meters/second)
-    pub vel: Vec2,
+    pub vel: RealVec2,
 
     /// Orientation angle in radians
     /// 0 = facing +X axis, increases clockwise
-    pub rotation: f32,
+    pub rotation: Real,
 
     /// Angular velocity in radians/second
     /// Positive = clockwise rotation
-    pub ang_vel: f32,
+    pub ang_vel: Real,
 
-    /// Mass of entity in kilograms
+    /// Mass of entity in kilograms, including remaining propellant
     /// Used for collision momentum calculations
-    pub mass: f32,
+    pub mass: Real,
 
     /// Current thrust level normalized to [-1.0, 1.0]
     /// Negative = reverse thrust
@@ -176,12 +236,50 @@ pub struct PhysicsState {
     /// Actual thrust force = current_thrust * max_thrust
     pub max_thrust: f32,
 
+    /// Remaining propellant in kilograms
+    /// Included in `mass` and burned while thrusting
+    pub propellant: Real,
+
+    /// Effective exhaust velocity in meters/second (Isp * g0)
+    /// Propellant burn rate = thrust force / exhaust_velocity
+    /// 0 = thrust is free and propellant is never consumed
+    pub exhaust_velocity: f32,
+
+    /// Moment of inertia about the rotation axis in kg*m^2
+    /// Angular acceleration = max_torque / moment_of_inertia
+    pub moment_of_inertia: f32,
+
+    /// Maximum torque the attitude thrusters can apply in Newton-meters
+    /// 0 = rotation is unlimited and `RotateTo` snaps instantly
+    pub max_torque: f32,
+
+    /// Angle in radians the craft is slewing towards, if any
+    /// Cleared once the craft comes to rest on it
+    pub target_rotation: Option<f32>,
+
+    /// Remaining structural health
+    /// Collisions deal damage proportional to specific impact energy and the
+    /// entity is destroyed once this reaches 0
+    /// f32::INFINITY = indestructible
+    pub health: f32,
+
     /// Whether entity still exists or has been destroyed
-    /// False indicates entity should be despawned
+    /// Destroyed entities are despawned once no longer in the history window
     pub alive: bool,
 
-    /// Optional elastic beam connection to another entity
-    pub elastic_beam: Option<Arc<ElasticBeamInfo>>,
+    /// Elastic beams this entity has connected, to other entities or fixed
+    /// anchors
+    /// Each beam is only stored on the entity that connected it, but pulls on
+    /// both ends
+    pub elastic_beams: Vec<ElasticBeamInfo>,
+}
+
+impl MapEntities for PhysicsState {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for beam in &mut self.elastic_beams {
+            beam.anchor.map_entities(entity_mapper);
+        }
+    }
 }
 
 #[derive(Event, Debug, Reflect)]
@@ -194,6 +292,45 @@ pub struct TimelineEventRequest {
     pub input: ControlInput,
 }
 
+/// A timeline input that was added or removed, sent by
+/// `process_timeline_events` once the request has been applied
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct TimelineEventAccepted {
+    pub entity: Entity,
+    /// Simulation tick when the input takes effect
+    pub tick: u64,
+    pub input: ControlInput,
+    /// Whether the input was removed rather than added
+    pub removed: bool,
+}
+
+/// A timeline request that couldn't be applied, sent by
+/// `process_timeline_events`, or by `send_rejected_connects` for beam connects
+/// that turn out to be out of range once the simulation reaches them
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct TimelineEventRejected {
+    pub entity: Entity,
+    /// Simulation tick the input was requested at
+    pub tick: u64,
+    pub input: ControlInput,
+    /// Whether the request was to remove the input rather than add it
+    pub removed: bool,
+    pub reason: RejectionReason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The entity has no `Timeline`
+    NoTimeline,
+    /// Beam parameters out of range, see `BeamParams::is_valid`
+    InvalidBeamParams,
+    /// No matching input at the tick to remove
+    NoMatchingInput,
+    /// Beam connect from further away than `max_length`, so the beam never
+    /// formed
+    OutOfRange,
+}
+
 #[derive(Event, Debug, Reflect)]
 pub struct TimelineEventRemovalRequest {
     /// Entity to apply to
@@ -201,6 +338,8 @@ pub struct TimelineEventRemovalRequest {
     /// Simulation tick when this input takes effect
     pub tick: u64,
     /// The control input to remove
+    /// Only the first matching input at `tick` is removed, other inputs on
+    /// the same tick are kept
     pub input: ControlInput,
 }
 
@@ -209,7 +348,7 @@ pub struct TimelineEventRemovalRequest {
 ///
 /// These inputs represent discrete changes to an entity's movement parameters.
 /// They can be scheduled in advance to create complex movement patterns.
-#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub enum ControlInput {
     /// Set thrust level between -1.0 (full reverse) and 1.0 (full forward)
     SetThrust(f32),
@@ -221,21 +360,91 @@ pub enum ControlInput {
     SetAngVel(f32),
 
     /// Simultaneously set thrust (-1.0 to 1.0) and rotation (radians)
-    /// Useful for immediate course corrections
+    /// Torque-limited crafts slew towards the rotation while thrusting, the
+    /// same as `RotateTo`
     SetThrustAndRotation(f32, f32),
 
-    /// Connect an elastic beam to another entity
-    ElasticBeamConnect(Entity),
+    /// Slew towards an absolute rotation in radians at the rate allowed by
+    /// `max_torque`, coming to rest on it
+    /// Crafts without a torque limit snap to it like `SetRotation`
+    RotateTo(f32),
 
-    /// Disconnect an elastic beam from another entity
-    ElasticBeamDisconnect(Entity),
+    /// Connect an elastic beam to another entity or a fixed point
+    /// Rejected if the anchor is beyond the beam's `max_length` at the start
+    /// of the tick the input is applied. Replaces any existing beam to the
+    /// same anchor
+    ElasticBeamConnect(BeamAnchor, BeamParams),
+
+    /// Disconnect the elastic beam to an anchor
+    ElasticBeamDisconnect(BeamAnchor),
+}
+
+impl MapEntities for ControlInput {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        match self {
+            ControlInput::ElasticBeamConnect(anchor, _)
+            | ControlInput::ElasticBeamDisconnect(anchor) => {
+                anchor.map_entities(entity_mapper)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What the far end of an elastic beam is attached to
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum BeamAnchor {
+    /// Another entity, which feels the opposite force
+    Entity(#[serde(with = "entity_bits")] Entity),
+    /// A point in world space that never moves
+    Fixed(Vec2),
+}
+
+impl MapEntities for BeamAnchor {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let BeamAnchor::Entity(entity) = self {
+            *entity = entity_mapper.map_entity(*entity);
+        }
+    }
+}
+
+/// Tunable properties of an elastic beam, chosen when connecting
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct BeamParams {
+    /// Natural length of the beam when no forces are applied
+    pub neutral_length: f32,
+    /// Spring constant (higher = stiffer beam)
+    pub stiffness: f32,
+    /// Maximum length before beam breaks, also the range it can connect at
+    pub max_length: f32,
+}
+
+impl BeamParams {
+    /// Whether the lengths and stiffness are finite and non-negative, and the
+    /// beam can stretch to its neutral length without breaking
+    pub fn is_valid(&self) -> bool {
+        let values = [self.neutral_length, self.stiffness, self.max_length];
+        values.iter().all(|value| value.is_finite() && *value >= 0.)
+            && self.max_length > 0.
+            && self.neutral_length <= self.max_length
+    }
+}
+
+impl Default for BeamParams {
+    fn default() -> Self {
+        Self {
+            neutral_length: 10.0,
+            stiffness: 0.25,
+            max_length: 100.0,
+        }
+    }
 }
 
 /// Parameters defining an elastic beam connection between entities
-#[derive(Clone, Debug, PartialEq, Reflect)]
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ElasticBeamInfo {
-    /// Entity this beam is connected to
-    pub connected_entity: Entity,
+    /// Far end of the beam
+    pub anchor: BeamAnchor,
     /// Natural length of the beam when no forces are applied
     pub neutral_length: f32,
     /// Spring constant (higher = stiffer beam)
@@ -245,6 +454,15 @@ pub struct ElasticBeamInfo {
 }
 
 impl ElasticBeamInfo {
+    pub fn new(anchor: BeamAnchor, params: BeamParams) -> Self {
+        Self {
+            anchor,
+            neutral_length: params.neutral_length,
+            stiffness: params.stiffness,
+            max_length: params.max_length,
+        }
+    }
+
     /// Calculate potential energy stored in the beam given both connected
     /// positions Uses spring equation: PE = 1/2 * k * x^2
     /// where k is stiffness and x is displacement from neutral length
@@ -258,22 +476,61 @@ impl ElasticBeamInfo {
     /// Returns force vector to be applied to pos_a (opposite force applies to
     /// pos_b)
     pub fn force_on_a(&self, pos_a: Vec2, pos_b: Vec2) -> Vec2 {
+        self.real_force_on_a(
+            RealVec2::from_vec2(pos_a),
+            RealVec2::from_vec2(pos_b),
+        )
+        .to_vec2()
+    }
+
+    /// `force_on_a` computed with the physics core's `Real` scalar
+    fn real_force_on_a(&self, pos_a: RealVec2, pos_b: RealVec2) -> RealVec2 {
         let displacement_vec = pos_b - pos_a;
         let current_length = displacement_vec.length();
 
-        let displacement = current_length - self.neutral_length;
+        let displacement = current_length - Real::from_f32(self.neutral_length);
 
-        if displacement <= 0.0 {
-            return Vec2::ZERO;
+        if displacement <= Real::ZERO {
+            return RealVec2::ZERO;
         }
 
         let direction = displacement_vec / current_length;
 
         // Force points along the beam axis
-        direction * (self.stiffness * displacement)
+        direction * (Real::from_f32(self.stiffness) * displacement)
     }
 }
 
+/// Record of an elastic beam snapping, stored on the timelines of both ends
+///
+/// Sent as an event by `send_beam_breaks` once the simulation reaches the tick
+/// the beam breaks at
+#[derive(Event, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct BeamBroken {
+    /// Entity the beam belonged to
+    #[serde(with = "entity_bits")]
+    pub owner: Entity,
+    pub anchor: BeamAnchor,
+    pub reason: BeamBreakReason,
+}
+
+impl MapEntities for BeamBroken {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.owner = entity_mapper.map_entity(self.owner);
+        self.anchor.map_entities(entity_mapper);
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize,
+)]
+pub enum BeamBreakReason {
+    /// Stretched past `max_length`
+    Overstretched,
+    /// The entity at one end was destroyed or despawned
+    EndpointLost,
+}
+
 #[derive(Clone, Debug, PartialEq, Reflect)]
 pub enum TimelineEvent {
     Control(ControlInput),
@@ -281,7 +538,8 @@ pub enum TimelineEvent {
 }
 
 /// Global simulation parameters and time control
-#[derive(Resource, Clone, Debug)]
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulationConfig {
     /// Current simulation tick
     pub current_tick: u64,
@@ -293,6 +551,29 @@ pub struct SimulationConfig {
     pub paused: bool,
     /// How many ticks in the future to predict
     pub prediction_ticks: u64,
+    /// Numerical scheme used to advance entities each tick
+    pub integrator: Integrator,
+    /// How many past ticks to keep, and so how far back the simulation can
+    /// be rewound
+    /// Off by default, when only the previous tick is kept
+    pub history_ticks: u64,
+    /// Whether islands of entities that can't affect each other within a tick
+    /// are computed in parallel on the compute task pool
+    /// The results are bit-identical either way
+    pub parallel_islands: bool,
+}
+
+impl SimulationConfig {
+    /// Length of one simulation tick in virtual seconds
+    pub fn seconds_per_tick(&self) -> f32 {
+        1.0 / self.ticks_per_second as f32
+    }
+
+    /// Earliest tick whose states are still kept
+    /// At least the previous tick is always kept
+    pub fn oldest_retained_tick(&self) -> u64 {
+        self.current_tick.saturating_sub(self.history_ticks.max(1))
+    }
 }
 
 impl Default for SimulationConfig {
@@ -303,6 +584,61 @@ impl Default for SimulationConfig {
             time_dilation: 1.0,
             paused: false,
             prediction_ticks: 120,
+            integrator: Integrator::default(),
+            history_ticks: 0,
+            parallel_islands: true,
+        }
+    }
+}
+
+/// How far ahead and how finely an entity's future is predicted
+///
+/// Entities without a policy are predicted every tick up to
+/// `SimulationConfig::prediction_ticks`. Past `exact_ticks` from the current
+/// tick, an entity with a `coarse_step` above 1 is integrated that many ticks
+/// at once, with the ticks in between interpolated in a straight line. Coarse
+/// states are recomputed tick by tick once they come within `exact_ticks`, so
+/// the simulation itself is never coarse
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize,
+)]
+pub struct PredictionPolicy {
+    /// How many ticks past the current tick to predict
+    pub horizon_ticks: u64,
+    /// Ticks integrated at once past `exact_ticks`
+    pub coarse_step: u64,
+    /// Ticks past the current tick that are always integrated one at a time
+    /// At least the next tick always is
+    pub exact_ticks: u64,
+    /// Distance in meters from the `Selected` craft within which the entity
+    /// is integrated every tick regardless of `coarse_step`
+    pub focus_radius: f32,
+}
+
+impl PredictionPolicy {
+    /// Predict `horizon_ticks` ahead, every tick
+    pub fn horizon(horizon_ticks: u64) -> Self {
+        Self {
+            horizon_ticks,
+            coarse_step: 1,
+            exact_ticks: horizon_ticks,
+            focus_radius: 0.,
+        }
+    }
+
+    /// Integrate `coarse_step` ticks at once past `exact_ticks`, unless within
+    /// `focus_radius` of the selected craft
+    pub fn with_coarse_step(
+        self,
+        coarse_step: u64,
+        exact_ticks: u64,
+        focus_radius: f32,
+    ) -> Self {
+        Self {
+            coarse_step,
+            exact_ticks,
+            focus_radius,
+            ..self
         }
     }
 }
@@ -314,70 +650,224 @@ pub struct PhysicsSimulationPlugin {
     pub is_test: bool,
 }
 
+/// Runs the `SimulationUpdate` schedule, once per fixed timestep or once per
+/// update in tests
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub struct PhysicsSystemSet;
 
 #[derive(Resource)]
 pub struct PhysicsEnabled;
 
+/// Schedule advancing the simulation by one tick
+///
+/// Systems that depend on the tick belong here rather than in `Update`, so
+/// that they run once per tick whatever the frame rate. It runs single
+/// threaded so that systems without an explicit order still run in the same
+/// order every tick
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SimulationUpdate;
+
+/// Stages of a tick in `SimulationUpdate`, run in order
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SimulationSet {
+    /// Schedule requested control inputs, once `update_simulation_time` has
+    /// advanced the tick
+    Inputs,
+    /// Gameplay at the new tick, like firing weapons
+    Gameplay,
+    /// Compute future states
+    Predict,
+    /// Bring entities up to date with their state at the tick, and send the
+    /// events happening at it
+    Sync,
+    /// Despawn destroyed entities
+    Cleanup,
+}
+
+/// Tick being simulated, mirrored from `SimulationConfig::current_tick` by
+/// `update_simulation_time` at the start of each tick
+///
+/// Read-only, the tick is changed through `SimulationConfig`. Outside of
+/// `SimulationUpdate`, e.g. right after a `rewind`, it can lag behind until
+/// the next tick starts
+#[derive(
+    Resource, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct SimTick(u64);
+
+impl SimTick {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
 impl Plugin for PhysicsSimulationPlugin {
     fn build(&self, app: &mut App) {
         let should_keep_alive = self.should_keep_alive;
-        let systems = (
-            update_simulation_time,
-            compute_future_states,
-            sync_physics_state_transform,
-            despawn_not_alive.run_if(move || !should_keep_alive),
-        )
-            .chain()
-            .in_set(PhysicsSystemSet);
 
         app.add_event::<TimelineEventRequest>()
             .add_event::<TimelineEventRemovalRequest>()
+            .add_event::<TimelineEventAccepted>()
+            .add_event::<TimelineEventRejected>()
+            .add_event::<BeamBroken>()
             .insert_resource(SpatialIndex::default())
-            .add_systems(Update, (viz_colliders, process_timeline_events));
+            .init_resource::<SimTick>()
+            .add_systems(Update, viz_colliders);
+
+        app.init_schedule(SimulationUpdate)
+            .edit_schedule(SimulationUpdate, |schedule| {
+                schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+            })
+            .configure_sets(
+                SimulationUpdate,
+                (
+                    SimulationSet::Inputs,
+                    SimulationSet::Gameplay,
+                    SimulationSet::Predict,
+                    SimulationSet::Sync,
+                    SimulationSet::Cleanup,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                SimulationUpdate,
+                (
+                    // Starts the tick, so that every set sees it
+                    update_simulation_time.before(SimulationSet::Inputs),
+                    process_timeline_events.in_set(SimulationSet::Inputs),
+                    compute_future_states.in_set(SimulationSet::Predict),
+                    (
+                        sync_physics_state_transform,
+                        fragments::sync_fragments
+                            .after(sync_physics_state_transform),
+                        send_beam_breaks,
+                        send_rejected_connects,
+                    )
+                        .in_set(SimulationSet::Sync),
+                    despawn_not_alive
+                        .run_if(move || !should_keep_alive)
+                        .in_set(SimulationSet::Cleanup),
+                ),
+            );
 
+        let run_simulation = run_simulation_update.in_set(PhysicsSystemSet);
         if !self.is_test {
-            app.add_systems(FixedUpdate, systems).configure_sets(
+            app.add_systems(FixedUpdate, run_simulation).configure_sets(
                 FixedUpdate,
                 PhysicsSystemSet.run_if(
                     |enabled: Option<Res<PhysicsEnabled>>| enabled.is_some(),
                 ),
             );
         } else {
-            app.add_systems(Update, systems)
+            app.add_systems(Update, run_simulation)
                 .configure_sets(Update, PhysicsSystemSet);
         }
     }
 }
 
+/// Simulate one tick
+pub fn run_simulation_update(world: &mut World) {
+    world.run_schedule(SimulationUpdate);
+}
+
+/// Despawn destroyed bodies once the simulation can't be rewound to a tick
+/// they're alive at
 fn despawn_not_alive(
     mut commands: Commands,
-    states: Query<(Entity, &PhysicsState)>,
+    states: Query<(Entity, &PhysicsState, &Timeline)>,
 ) {
-    for (entity, state) in states.iter() {
-        if !state.alive {
+    for (entity, state, timeline) in states.iter() {
+        // States are only kept within the history window
+        let rewindable = timeline
+            .first_tick()
+            .and_then(|tick| timeline.state(tick))
+            .is_some_and(|state| state.alive);
+        if !state.alive && !rewindable {
             info!(?entity, "Despawning dead entity");
             commands.entity(entity).despawn();
         }
     }
 }
 
-// Increment current_tick when not paused
-fn update_simulation_time(mut sim_time: ResMut<SimulationConfig>) {
+/// Send `BeamBroken` for the beams breaking at the current tick
+///
+/// Breaks are recorded on the timelines of both ends, and sent once from the
+/// owner's
+pub fn send_beam_breaks(
+    timelines: Query<(Entity, &Timeline)>,
+    tick: Res<SimTick>,
+    mut last_sent: Local<Option<u64>>,
+    mut breaks: EventWriter<BeamBroken>,
+) {
+    // The tick doesn't advance while paused
+    if *last_sent == Some(tick.0) {
+        return;
+    }
+    *last_sent = Some(tick.0);
+    for (entity, timeline) in &timelines {
+        let Some(broken) = timeline.broken_beams.get(&tick.0) else {
+            continue;
+        };
+        for broken in broken.iter().filter(|broken| broken.owner == entity) {
+            info!(?broken, "Beam broke");
+            breaks.send(broken.clone());
+        }
+    }
+}
+
+/// Send `TimelineEventRejected` for the beam connects at the current tick that
+/// are out of range
+pub fn send_rejected_connects(
+    timelines: Query<(Entity, &Timeline)>,
+    tick: Res<SimTick>,
+    mut last_sent: Local<Option<u64>>,
+    mut rejected: EventWriter<TimelineEventRejected>,
+) {
+    // The tick doesn't advance while paused
+    if *last_sent == Some(tick.0) {
+        return;
+    }
+    *last_sent = Some(tick.0);
+    for (entity, timeline) in &timelines {
+        let Some(inputs) = timeline.rejected_connects.get(&tick.0) else {
+            continue;
+        };
+        for input in inputs {
+            warn!(?entity, ?input, "Beam connect out of range");
+            rejected.send(TimelineEventRejected {
+                entity,
+                tick: tick.0,
+                input: *input,
+                removed: false,
+                reason: RejectionReason::OutOfRange,
+            });
+        }
+    }
+}
+
+/// Increment current_tick when not paused, and start the tick
+pub fn update_simulation_time(
+    mut sim_time: ResMut<SimulationConfig>,
+    mut tick: ResMut<SimTick>,
+) {
     if !sim_time.paused {
         sim_time.current_tick += 1;
         info!(tick = sim_time.current_tick, "Updated tick");
     }
+    tick.0 = sim_time.current_tick;
 }
 
 // When receiving events:
 //   1. Update Timeline events
 //   2. Set last_computed_tick to invalidate future states
-fn process_timeline_events(
+//   3. Send a `TimelineEventAccepted` for each applied request, and a
+//      `TimelineEventRejected` for the others
+pub fn process_timeline_events(
     mut timeline_events: EventReader<TimelineEventRequest>,
     mut timeline_removals: EventReader<TimelineEventRemovalRequest>,
     mut timelines: Query<&mut Timeline>,
+    mut accepted: EventWriter<TimelineEventAccepted>,
+    mut rejected: EventWriter<TimelineEventRejected>,
 ) {
     for TimelineEventRequest {
         tick,
@@ -386,12 +876,34 @@ fn process_timeline_events(
     } in timeline_events.read()
     {
         info!(?tick, ?input, ?entity, "Got timeline event request");
+        let mut reject = |reason| {
+            warn!(?reason, "Rejected timeline event request");
+            rejected.send(TimelineEventRejected {
+                entity: *entity,
+                tick: *tick,
+                input: *input,
+                removed: false,
+                reason,
+            });
+        };
         let Ok(mut timeline) = timelines.get_mut(*entity) else {
-            warn!("Timeline component missing for given request");
+            reject(RejectionReason::NoTimeline);
             continue;
         };
+        if let ControlInput::ElasticBeamConnect(_, params) = input {
+            if !params.is_valid() {
+                reject(RejectionReason::InvalidBeamParams);
+                continue;
+            }
+        }
 
         timeline.add_input_event(*tick, *input);
+        accepted.send(TimelineEventAccepted {
+            entity: *entity,
+            tick: *tick,
+            input: *input,
+            removed: false,
+        });
     }
 
     for TimelineEventRemovalRequest {
@@ -401,152 +913,505 @@ fn process_timeline_events(
     } in timeline_removals.read()
     {
         info!(?tick, ?input, ?entity, "Got timeline removal request");
+        let mut reject = |reason| {
+            warn!(?reason, "Rejected timeline removal request");
+            rejected.send(TimelineEventRejected {
+                entity: *entity,
+                tick: *tick,
+                input: *input,
+                removed: true,
+                reason,
+            });
+        };
         let Ok(mut timeline) = timelines.get_mut(*entity) else {
-            warn!("Timeline component missing for given removal");
+            reject(RejectionReason::NoTimeline);
             continue;
         };
 
-        timeline.remove_input_event(*tick, *input);
+        if !timeline.remove_input_event(*tick, *input) {
+            reject(RejectionReason::NoMatchingInput);
+            continue;
+        }
+        accepted.send(TimelineEventAccepted {
+            entity: *entity,
+            tick: *tick,
+            input: *input,
+            removed: true,
+        });
     }
 }
 
 impl PhysicsState {
-    fn integrate(&self, delta_seconds: f32) -> Self {
+    /// Advance this state by one tick with no external forces
+    pub fn integrate(
+        &self,
+        delta_seconds: f32,
+        integrator: Integrator,
+    ) -> Self {
+        self.integrate_with(delta_seconds, integrator, |_, _| Vec2::ZERO)
+    }
+
+    /// Integrate with an additional acceleration field
+    ///
+    /// `external(t, pos)` returns acceleration from the environment (e.g.
+    /// gravity) at `pos`, `t` seconds into the tick
+    pub fn integrate_with(
+        &self,
+        delta_seconds: f32,
+        integrator: Integrator,
+        external: impl Fn(f32, Vec2) -> Vec2,
+    ) -> Self {
         if !self.alive {
             return PhysicsState::default();
         }
 
-        // Calculate thrust force
-        let thrust_direction = Vec2::from_angle(self.rotation);
-        let thrust_force =
-            thrust_direction * (self.current_thrust * self.max_thrust);
+        // The step itself runs on `Real` so that it is bit-exact in
+        // fixed-point builds
+        let r = Real::from_f32;
+        let dt = r(delta_seconds);
+        let rotation = self.rotation;
+        // Attitude thrusters pick the angular velocity for this tick while
+        // slewing
+        let slew = self.slew(dt);
+        let ang_vel = slew.map_or(self.ang_vel, |(ang_vel, _)| ang_vel);
+        let arrived = matches!(slew, Some((_, true)));
+        let (mass, propellant) = (self.mass, self.propellant);
+
+        // Thrust follows the craft's rotation through the tick, beam forces
+        // will be added separately
+        let thrust = r(self.current_thrust) * r(self.max_thrust);
+        let mass_flow = self.mass_flow(thrust);
+        let external = |t: Real, pos: RealVec2| {
+            RealVec2::from_vec2(external(t.to_f32(), pos.to_vec2()))
+        };
+        // Thrust cuts out part way through the tick if the tank runs dry, in
+        // which case the rest of the tick is integrated as a separate coast
+        // so that the cutoff doesn't fall between integrator samples
+        let burn_time = if mass_flow > Real::ZERO {
+            (propellant / mass_flow).clamp(Real::ZERO, dt)
+        } else {
+            dt
+        };
+        let (mut pos, mut vel) = (self.pos, self.vel);
+        if burn_time > Real::ZERO {
+            (pos, vel) = integrator.step(pos, vel, burn_time, |t, pos, _| {
+                let thrust_direction =
+                    RealVec2::from_angle(rotation + ang_vel * t);
+                let mass = mass - mass_flow * t;
+                thrust_direction * thrust / mass + external(t, pos)
+            });
+        }
+        if burn_time < dt {
+            (pos, vel) =
+                integrator.step(pos, vel, dt - burn_time, |t, pos, _| {
+                    external(burn_time + t, pos)
+                });
+        }
 
-        // Start with thrust force, beam forces will be added separately
-        let acceleration = thrust_force / self.mass;
+        // A tank that runs dry is empty, even if dividing and multiplying by
+        // the flow rate rounded away the last bit
+        let burned = if burn_time < dt {
+            propellant
+        } else {
+            (mass_flow * burn_time).min(propellant)
+        };
+        let propellant = propellant - burned;
+        let current_thrust =
+            if mass_flow > Real::ZERO && propellant <= Real::ZERO {
+                0.
+            } else {
+                self.current_thrust
+            };
 
         PhysicsState {
-            pos: self.pos + self.vel * delta_seconds,
-            vel: self.vel + acceleration * delta_seconds,
-            rotation: self.rotation + self.ang_vel * delta_seconds,
-            ang_vel: self.ang_vel,
-            mass: self.mass,
-            current_thrust: self.current_thrust,
+            pos,
+            vel,
+            rotation: rotation + ang_vel * dt,
+            ang_vel: if arrived { Real::ZERO } else { ang_vel },
+            mass: mass - burned,
+            current_thrust,
             max_thrust: self.max_thrust,
+            propellant,
+            exhaust_velocity: self.exhaust_velocity,
+            moment_of_inertia: self.moment_of_inertia,
+            max_torque: self.max_torque,
+            target_rotation: if arrived { None } else { self.target_rotation },
+            health: self.health,
             alive: self.alive,
-            elastic_beam: self.elastic_beam.clone(),
+            elastic_beams: self.elastic_beams.clone(),
         }
     }
 
-    /// Apply elastic beam forces given the other entity's position
-    fn integrate_beam(&mut self, other: &mut PhysicsState, delta_seconds: f32) {
-        if let Some(beam) = &self.elastic_beam {
-            let current_length = (other.pos - self.pos).length();
+    /// Whether the entity keeps drifting in a straight line at constant spin
+    /// unless something else acts on it
+    pub fn is_inert(&self) -> bool {
+        self.alive
+            && self.current_thrust == 0.
+            && self.target_rotation.is_none()
+            && self.elastic_beams.is_empty()
+    }
 
-            if current_length > beam.max_length {
-                eprintln!("Beam too long, disconnecting");
-                self.elastic_beam = None;
-            } else {
-                // Calculate and apply beam force
-                let beam_force = beam.force_on_a(self.pos, other.pos);
-                let beam_acceleration = beam_force / self.mass;
-                self.vel += beam_acceleration * delta_seconds;
-
-                // Apply force to other entity
-                let other_acceleration = beam_force / other.mass;
-                other.vel -= other_acceleration * delta_seconds;
-
-                eprintln!("\n--------------------------------");
-                eprintln!("Current length: {:?}", current_length);
-                eprintln!("Pos: {:?}", self.pos);
-                eprintln!("Other pos: {:?}", other.pos);
-                eprintln!("Beam force: {:?}", beam_force);
-                eprintln!("Beam acceleration: {:?}", beam_acceleration);
-                eprintln!("Self vel: {:?}", self.vel);
-                eprintln!("Other vel: {:?}", other.vel);
-            }
+    /// State after drifting for `ticks` ticks with no force or torque, in
+    /// closed form
+    pub fn coasted(&self, ticks: u64, seconds_per_tick: f32) -> Self {
+        let r = Real::from_f32;
+        let t = r(seconds_per_tick) * r(ticks as f32);
+        PhysicsState {
+            pos: self.pos + self.vel * t,
+            rotation: self.rotation + self.ang_vel * t,
+            ..self.clone()
         }
     }
 
-    fn apply_input_event(&mut self, event: Option<&ControlInput>) {
-        let Some(event) = event else {
-            return;
+    /// Apply the force of one of this entity's elastic beams
+    ///
+    /// `other` is the state of the entity at the far end, or `None` for beams
+    /// to a fixed anchor, which act like an immovable partner.
+    ///
+    /// Both states are expected to have already been integrated for this tick
+    /// without the beam. The separation between the ends is re-integrated
+    /// over the tick from its start-of-tick value (recovered by undoing the
+    /// free drift) with the beam force, and the difference is applied split
+    /// by mass so total momentum is conserved.
+    ///
+    /// Returns false without applying any force if the beam is stretched past
+    /// `max_length` and breaks
+    fn integrate_beam(
+        &mut self,
+        beam: &ElasticBeamInfo,
+        other: Option<&mut PhysicsState>,
+        delta_seconds: f32,
+        integrator: Integrator,
+    ) -> bool {
+        let r = Real::from_f32;
+        let (other_pos, other_vel, other_inv_mass) = match (&other, beam.anchor)
+        {
+            (Some(other), _) => (other.pos, other.vel, Real::ONE / other.mass),
+            (None, BeamAnchor::Fixed(anchor)) => {
+                (RealVec2::from_vec2(anchor), RealVec2::ZERO, Real::ZERO)
+            }
+            // Nothing to pull against
+            (None, BeamAnchor::Entity(_)) => return true,
         };
+        let (pos, vel) = (self.pos, self.vel);
+        let inv_mass = Real::ONE / self.mass;
+        let dt = r(delta_seconds);
+
+        let separation = other_pos - pos;
+        if separation.length() > r(beam.max_length) {
+            return false;
+        }
+
+        // Relative acceleration of other w.r.t. self
+        let inv_mass_sum = inv_mass + other_inv_mass;
+        let rel_vel = other_vel - vel;
+        let start_separation = separation - rel_vel * dt;
+        let (new_separation, new_rel_vel) = integrator.step(
+            start_separation,
+            rel_vel,
+            dt,
+            |_, separation, _| {
+                -beam.real_force_on_a(RealVec2::ZERO, separation) * inv_mass_sum
+            },
+        );
+        let d_separation = new_separation - separation;
+        let d_rel_vel = new_rel_vel - rel_vel;
+
+        // Lighter ends move further, fixed anchors don't move at all
+        let self_share = inv_mass / inv_mass_sum;
+        let other_share = other_inv_mass / inv_mass_sum;
+        self.pos = pos - d_separation * self_share;
+        self.vel = vel - d_rel_vel * self_share;
+        if let Some(other) = other {
+            other.pos = other_pos + d_separation * other_share;
+            other.vel = other_vel + d_rel_vel * other_share;
+        }
+        true
+    }
+
+    /// Apply a tick's control inputs in order, so later inputs override
+    /// earlier ones
+    fn apply_input_events(&mut self, events: &[ControlInput]) {
+        for event in events {
+            self.apply_input_event(event);
+        }
+    }
+
+    fn apply_input_event(&mut self, event: &ControlInput) {
         match event {
             ControlInput::SetThrust(thrust) => {
                 self.current_thrust = *thrust;
             }
             ControlInput::SetRotation(rotation) => {
-                self.rotation = *rotation;
-                self.ang_vel = 0.;
+                self.rotation = Real::from_f32(*rotation);
+                self.ang_vel = Real::ZERO;
+                self.target_rotation = None;
             }
             ControlInput::SetThrustAndRotation(thrust, rotation) => {
                 self.current_thrust = *thrust;
-                self.rotation = *rotation;
-                self.ang_vel = 0.;
+                self.rotate_to(*rotation);
+            }
+            ControlInput::RotateTo(rotation) => {
+                self.rotate_to(*rotation);
             }
             ControlInput::SetAngVel(ang_vel) => {
-                self.ang_vel = *ang_vel;
+                self.ang_vel = Real::from_f32(*ang_vel);
+                self.target_rotation = None;
             }
-            ControlInput::ElasticBeamConnect(connected_entity) => {
-                let beam = ElasticBeamInfo {
-                    connected_entity: *connected_entity,
-                    neutral_length: 10.0,
-                    stiffness: 0.25,
-                    max_length: 100.0,
-                };
-                self.elastic_beam = Some(Arc::new(beam));
+            ControlInput::ElasticBeamConnect(anchor, params) => {
+                self.elastic_beams.retain(|beam| beam.anchor != *anchor);
+                self.elastic_beams
+                    .push(ElasticBeamInfo::new(*anchor, *params));
             }
-            ControlInput::ElasticBeamDisconnect(entity) => {
-                // Only disconnect if connected to specified entity
-                if let Some(beam) = &self.elastic_beam {
-                    if beam.connected_entity == *entity {
-                        self.elastic_beam = None;
-                    }
-                }
+            ControlInput::ElasticBeamDisconnect(anchor) => {
+                self.elastic_beams.retain(|beam| beam.anchor != *anchor);
             }
         }
     }
 
-    fn apply_collision_result(&mut self, result: &EntityCollisionResult) {
+    /// Start slewing towards `rotation`, or snap to it if rotation is
+    /// unlimited
+    fn rotate_to(&mut self, rotation: f32) {
+        if self.max_angular_accel().is_finite() {
+            self.target_rotation = Some(rotation);
+        } else {
+            self.rotation = Real::from_f32(rotation);
+            self.ang_vel = Real::ZERO;
+            self.target_rotation = None;
+        }
+    }
+
+    /// Angular velocity to hold over the next `dt` seconds while slewing
+    /// towards `target_rotation`, and whether the craft comes to rest on the
+    /// target by the end of it. `None` when not slewing
+    ///
+    /// Accelerates at the maximum rate towards the target and brakes in time
+    /// to stop on it (bang-bang control)
+    fn slew(&self, dt: Real) -> Option<(Real, bool)> {
+        let target = self.target_rotation?;
+        let max_accel = self.max_angular_accel();
+        if !max_accel.is_finite() {
+            return None;
+        }
+        let r = Real::from_f32;
+        let error = shortest_rotation(self.rotation, r(target));
+        let ang_vel = self.ang_vel;
+        let max_accel = r(max_accel);
+        let max_dv = max_accel * dt;
+
+        // Close enough to stop on the target within this tick
+        if error.abs() <= max_dv * dt && ang_vel.abs() <= max_dv {
+            return Some((error / dt, true));
+        }
+
+        // Fastest angular velocity from which the craft can still brake in
+        // time: braking from n * max_dv covers max_dv * dt * n(n + 1) / 2
+        let braking_ticks =
+            (r(0.25) + r(2.) * error.abs() / (max_dv * dt)).sqrt() - r(0.5);
+        let stop_speed = max_dv * braking_ticks;
+        let desired = if error < Real::ZERO {
+            -stop_speed
+        } else {
+            stop_speed
+        };
+        Some((ang_vel + (desired - ang_vel).clamp(-max_dv, max_dv), false))
+    }
+
+    /// Maximum angular acceleration in radians/second^2
+    ///
+    /// Infinite for crafts without a torque limit
+    pub fn max_angular_accel(&self) -> f32 {
+        if self.max_torque <= 0. || self.moment_of_inertia <= 0. {
+            return f32::INFINITY;
+        }
+        self.max_torque / self.moment_of_inertia
+    }
+
+    pub fn apply_collision_result(&mut self, result: &EntityCollisionResult) {
         match result {
-            EntityCollisionResult::Destroyed => {
+            EntityCollisionResult::Destroyed { .. } => {
+                self.health = 0.;
                 self.alive = false;
             }
-            EntityCollisionResult::Survives { post_pos, post_vel } => {
-                self.pos = *post_pos;
-                self.vel = *post_vel;
+            EntityCollisionResult::Survives {
+                post_pos,
+                post_vel,
+                damage,
+            } => {
+                self.pos = RealVec2::from_vec2(*post_pos);
+                self.vel = RealVec2::from_vec2(*post_vel);
+                self.health -= damage;
             }
         }
     }
 
-    pub fn dir(&self) -> Vec2 {
-        Vec2::from_angle(self.rotation)
+    /// Propellant consumed per second (kg/s) while producing `thrust` Newtons
+    /// Zero for crafts with free thrust
+    fn mass_flow(&self, thrust: Real) -> Real {
+        if self.exhaust_velocity <= 0. {
+            return Real::ZERO;
+        }
+        thrust.abs() / Real::from_f32(self.exhaust_velocity)
+    }
+
+    /// Whether the craft can no longer produce thrust
+    pub fn is_out_of_propellant(&self) -> bool {
+        self.exhaust_velocity > 0. && self.propellant <= Real::ZERO
+    }
+
+    /// Remaining delta-v in meters/second from the rocket equation
+    /// dv = ve * ln(m0 / m_dry)
+    ///
+    /// Infinite for crafts with free thrust
+    pub fn delta_v(&self) -> f32 {
+        if self.exhaust_velocity <= 0. {
+            return f32::INFINITY;
+        }
+        let dry_mass = self.mass - self.propellant;
+        if dry_mass <= Real::ZERO {
+            return f32::INFINITY;
+        }
+        self.exhaust_velocity * (self.mass / dry_mass).to_f32().ln()
+    }
+
+    /// Unit vector the craft is facing
+    ///
+    /// Computed on `Real` so that projectiles spawned along it are
+    /// deterministic in fixed-point builds
+    pub fn dir(&self) -> RealVec2 {
+        RealVec2::from_angle(self.rotation)
+    }
+
+    /// State of a body of `mass` fired from this craft, starting `offset`
+    /// meters ahead of it and `speed` meters/second faster along its heading
+    pub fn launch(&self, offset: f32, speed: f32, mass: f32) -> PhysicsState {
+        let r = Real::from_f32;
+        PhysicsState {
+            pos: self.pos + self.dir() * r(offset),
+            vel: self.vel + self.dir() * r(speed),
+            rotation: self.rotation,
+            mass: r(mass),
+            health: 1.,
+            alive: true,
+            ..default()
+        }
     }
 
     pub fn quat(&self) -> Quat {
-        Quat::from_rotation_z(self.rotation)
+        Quat::from_rotation_z(self.rotation.to_f32())
     }
 }
 
+/// Signed shortest rotation in radians taking `from` to `to`, in [-PI, PI)
+fn shortest_rotation(from: Real, to: Real) -> Real {
+    let pi = Real::from_f32(PI);
+    (to - from + pi).rem_euclid(Real::from_f32(2. * PI)) - pi
+}
+
+/// Components `sync_physics_state_transform` updates from the timeline
+type SyncedComponents<'a> = (
+    &'a mut Transform,
+    &'a mut PhysicsState,
+    &'a mut Timeline,
+    Option<&'a mut Health>,
+    Option<&'a mut Visibility>,
+);
+
 /// Update tranform and physics state from timeline
-fn sync_physics_state_transform(
-    mut query: Query<(&mut Transform, &mut PhysicsState, &mut Timeline)>,
+///
+/// Entities are only visible from the tick they appear until they're
+/// destroyed
+pub fn sync_physics_state_transform(
+    mut query: Query<SyncedComponents>,
     sim_state: Res<SimulationConfig>,
+    mut spatial_index: ResMut<SpatialIndex>,
 ) {
-    for (mut transform, mut phys_state, mut timeline) in query.iter_mut() {
+    let oldest = sim_state.oldest_retained_tick();
+    for (mut transform, mut phys_state, mut timeline, health, visibility) in
+        query.iter_mut()
+    {
+        // Entities spawned ahead of time, like collision fragments, have no
+        // state until the tick they appear
+        let unborn = timeline
+            .first_tick()
+            .is_some_and(|tick| tick > sim_state.current_tick);
+        if unborn {
+            if let Some(mut visibility) = visibility {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+            continue;
+        }
+
         *phys_state = timeline
-            .future_states
-            .get(&sim_state.current_tick)
+            .state(sim_state.current_tick)
             .expect("current tick not included in timeline")
-            .clone();
+            .into_owned();
+        if let Some(mut visibility) = visibility {
+            visibility.set_if_neq(if phys_state.alive {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+
+        transform.translation = Vec3::from2(phys_state.pos.to_vec2());
+        transform.rotation = phys_state.quat();
+        if let Some(mut health) = health {
+            health.0 = phys_state.health as f64;
+        }
+
+        // Drop everything older than the history window. Inputs and events
+        // at `oldest` only matter for computing its state, which is kept
+        timeline.drop_states_before(oldest);
+        timeline.input_events.retain(|k, _v| *k > oldest);
+        timeline.sim_events.retain(|k, _v| *k > oldest);
+        timeline.broken_beams.retain(|k, _v| *k > oldest);
+        timeline.rejected_connects.retain(|k, _v| *k > oldest);
+        timeline.fragmentations.retain(|k, _v| *k > oldest);
+    }
+    spatial_index.prune_before(oldest);
+}
 
-        transform.translation = Vec3::from2(phys_state.pos);
-        transform.rotation = Quat::from_rotation_z(phys_state.rotation);
+/// Triggered by `rewind` just before going back, while the simulation is
+/// still at `from`
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rewinding {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// Rewind the simulation to `tick`, which has to be within the history
+/// window
+///
+/// Timelines are recomputed from `tick`, which rebuilds the spatial index and
+/// gives back the same predictions as long as the inputs are unchanged.
+/// Bodies destroyed since `tick` are still around, hidden, and come back.
+/// Entities despawned some other way are not brought back
+pub fn rewind(tick: u64) -> impl Command {
+    move |world: &mut World| {
+        let config = world.resource::<SimulationConfig>();
+        if tick > config.current_tick || tick < config.oldest_retained_tick() {
+            warn!(
+                tick,
+                current_tick = config.current_tick,
+                "Can't rewind to a tick outside the history window"
+            );
+            return;
+        }
+        info!(tick, "Rewinding simulation");
+        let from = config.current_tick;
+        world.trigger(Rewinding { from, to: tick });
+        world.resource_mut::<SimulationConfig>().current_tick = tick;
+
+        let mut timelines = world.query::<&mut Timeline>();
+        for mut timeline in timelines.iter_mut(world) {
+            timeline.last_computed_tick = timeline.last_computed_tick.min(tick);
+        }
 
-        if let Some(to_remove) = sim_state.current_tick.checked_sub(2) {
-            timeline.future_states.remove(&to_remove);
-            timeline.input_events.retain(|k, _v| *k > to_remove + 1);
-            timeline.sim_events.retain(|k, _v| *k > to_remove + 1);
+        // Restore physics states and transforms right away, in case the
+        // simulation is paused
+        if let Err(err) = world.run_system_once(sync_physics_state_transform) {
+            warn!(?err, "Failed to sync states after rewind");
         }
     }
 }
@@ -555,10 +1420,11 @@ fn sync_physics_state_transform(
 mod tests {
     use std::{f32::consts::PI, time::Duration};
 
-    use assertables::assert_approx_eq;
+    use assertables::{assert_abs_diff_le_x, assert_approx_eq};
     use bevy::{app::App, time::Time};
 
     use super::{test_utils::*, *};
+    use crate::time_control::TimeController;
 
     fn create_test_app() -> App {
         let mut app = App::new();
@@ -578,15 +1444,21 @@ mod tests {
 
     fn create_test_physics_state() -> PhysicsState {
         PhysicsState {
-            pos: Vec2::ZERO,
-            vel: Vec2::ZERO,
-            rotation: 0.0,
-            ang_vel: 0.0,
-            mass: 1.0,
+            pos: RealVec2::ZERO,
+            vel: RealVec2::ZERO,
+            rotation: Real::ZERO,
+            ang_vel: Real::ZERO,
+            mass: Real::from_f32(1.0),
             current_thrust: 0.0,
             max_thrust: 100.0,
+            propellant: Real::ZERO,
+            exhaust_velocity: 0.0,
+            moment_of_inertia: 0.0,
+            max_torque: 0.0,
+            target_rotation: None,
+            health: 1.0,
             alive: true,
-            elastic_beam: None,
+            elastic_beams: Vec::new(),
         }
     }
 
@@ -596,85 +1468,107 @@ mod tests {
 
         // Case 1: No forces, only existing velocity
         let state = PhysicsState {
-            pos: Vec2::new(10.0, 5.0),
-            vel: Vec2::new(2.0, 1.0),
-            rotation: 0.0,
-            ang_vel: 0.5,
-            mass: 1.0,
+            pos: RealVec2::from_vec2(Vec2::new(10.0, 5.0)),
+            vel: RealVec2::from_vec2(Vec2::new(2.0, 1.0)),
+            rotation: Real::ZERO,
+            ang_vel: Real::from_f32(0.5),
+            mass: Real::from_f32(1.0),
             current_thrust: 0.0,
             max_thrust: 100.0,
+            propellant: Real::ZERO,
+            exhaust_velocity: 0.0,
+            moment_of_inertia: 0.0,
+            max_torque: 0.0,
+            target_rotation: None,
+            health: 1.0,
             alive: true,
-            elastic_beam: None,
+            elastic_beams: Vec::new(),
         };
 
-        let next_state = state.integrate(delta);
+        let next_state = state.integrate(delta, Integrator::ExplicitEuler);
 
         // Position should change based on existing velocity
-        assert_approx_eq!(next_state.pos.x, 10.0 + 2.0 * delta);
-        assert_approx_eq!(next_state.pos.y, 5.0 + 1.0 * delta);
+        assert_approx_eq!(next_state.pos.to_vec2().x, 10.0 + 2.0 * delta);
+        assert_approx_eq!(next_state.pos.to_vec2().y, 5.0 + 1.0 * delta);
         // Velocity should remain unchanged (no forces)
-        assert_approx_eq!(next_state.vel.x, 2.0);
-        assert_approx_eq!(next_state.vel.y, 1.0);
+        assert_approx_eq!(next_state.vel.to_vec2().x, 2.0);
+        assert_approx_eq!(next_state.vel.to_vec2().y, 1.0);
         // Rotation should change based on angular velocity
-        assert_approx_eq!(next_state.rotation, 0.0 + 0.5 * delta);
+        assert_approx_eq!(next_state.rotation.to_f32(), 0.0 + 0.5 * delta);
 
         // Case 2: Full thrust to the right (rotation = 0)
         let state = PhysicsState {
-            pos: Vec2::ZERO,
-            vel: Vec2::ZERO,
-            rotation: 0.0,
-            ang_vel: 0.0,
-            mass: 2.0,           // 2kg mass
-            current_thrust: 1.0, // Full thrust
-            max_thrust: 100.0,   // 100N max thrust
+            pos: RealVec2::ZERO,
+            vel: RealVec2::ZERO,
+            rotation: Real::ZERO,
+            ang_vel: Real::ZERO,
+            mass: Real::from_f32(2.0), // 2kg mass
+            current_thrust: 1.0,       // Full thrust
+            max_thrust: 100.0,         // 100N max thrust
+            propellant: Real::ZERO,
+            exhaust_velocity: 0.0,
+            moment_of_inertia: 0.0,
+            max_torque: 0.0,
+            target_rotation: None,
+            health: 1.0,
             alive: true,
-            elastic_beam: None,
+            elastic_beams: Vec::new(),
         };
 
-        let next_state = state.integrate(delta);
+        let next_state = state.integrate(delta, Integrator::ExplicitEuler);
 
         // Calculate expected values:
         // Force = 100N right
         // Acceleration = 100N / 2kg = 50 m/s²
         // Δv = 50 m/s² * (1/60) s = 0.8333... m/s
         // Position shouldn't change yet since initial velocity was zero
-        assert_approx_eq!(next_state.vel.x, 50.0 * delta);
-        assert_approx_eq!(next_state.vel.y, 0.0);
-        assert_approx_eq!(next_state.pos.x, 0.0); // Fixed: position doesn't change first frame
-        assert_approx_eq!(next_state.pos.y, 0.0);
+        assert_approx_eq!(next_state.vel.to_vec2().x, 50.0 * delta);
+        assert_approx_eq!(next_state.vel.to_vec2().y, 0.0);
+        assert_approx_eq!(next_state.pos.to_vec2().x, 0.0); // Fixed: position doesn't change first frame
+        assert_approx_eq!(next_state.pos.to_vec2().y, 0.0);
 
         // Case 3: Full thrust at 45 degrees
         let state = PhysicsState {
-            pos: Vec2::ZERO,
-            vel: Vec2::ZERO,
-            rotation: PI / 4.0, // 45 degrees
-            ang_vel: 0.0,
-            mass: 2.0,
+            pos: RealVec2::ZERO,
+            vel: RealVec2::ZERO,
+            rotation: Real::from_f32(PI / 4.0), // 45 degrees
+            ang_vel: Real::ZERO,
+            mass: Real::from_f32(2.0),
             current_thrust: 1.0,
             max_thrust: 100.0,
+            propellant: Real::ZERO,
+            exhaust_velocity: 0.0,
+            moment_of_inertia: 0.0,
+            max_torque: 0.0,
+            target_rotation: None,
+            health: 1.0,
             alive: true,
-            elastic_beam: None,
+            elastic_beams: Vec::new(),
         };
 
-        let next_state = state.integrate(delta);
+        let next_state = state.integrate(delta, Integrator::ExplicitEuler);
 
         // At 45 degrees, force is split equally between x and y
         // Each component should be 100N * √2/2 = 70.71... N
         // Acceleration per component = 35.355... m/s²
         let expected_accel = 50.0 / 2.0_f32.sqrt();
-        assert_approx_eq!(next_state.vel.x, expected_accel * delta);
-        assert_approx_eq!(next_state.vel.y, expected_accel * delta);
-        assert_approx_eq!(next_state.pos.x, 0.0); // Fixed: position doesn't change first frame
-        assert_approx_eq!(next_state.pos.y, 0.0);
+        assert_approx_eq!(next_state.vel.to_vec2().x, expected_accel * delta);
+        assert_approx_eq!(next_state.vel.to_vec2().y, expected_accel * delta);
+        assert_approx_eq!(next_state.pos.to_vec2().x, 0.0); // Fixed: position doesn't change first frame
+        assert_approx_eq!(next_state.pos.to_vec2().y, 0.0);
 
         // Let's verify position changek after a second integration step
-        let third_state = next_state.integrate(delta);
+        let third_state =
+            next_state.integrate(delta, Integrator::ExplicitEuler);
         assert_approx_eq!(
-            third_state.pos.x,
+            third_state.pos.to_vec2().x,
             (expected_accel * delta) * delta, /* Using velocity from
                                                * previous state */
         );
-        assert_approx_eq!(third_state.pos.y, (expected_accel * delta) * delta,);
+        assert_approx_eq!(
+            third_state.pos.to_vec2().y,
+            (expected_accel * delta) * delta,
+        );
     }
 
     #[test]
@@ -705,7 +1599,7 @@ mod tests {
             .expect("Timeline component should exist");
 
         // Verify states were computed
-        assert!(!timeline.future_states.is_empty());
+        assert!(timeline.first_tick().is_some());
         assert_eq!(
             timeline.last_computed_tick,
             1 + app.world().resource::<SimulationConfig>().prediction_ticks
@@ -713,31 +1607,404 @@ mod tests {
 
         // Check that thrust was applied at the correct tick
         let state_before = timeline
-            .future_states
-            .get(&29)
+            .state(29)
             .expect("Should have state before thrust application");
         let state_after = timeline
-            .future_states
-            .get(&31)
+            .state(31)
             .expect("Should have state after thrust application");
         assert!(state_after.vel.length() > state_before.vel.length());
     }
 
+    #[test]
+    fn test_history_window_is_kept() {
+        let mut app = create_test_app();
+        app.world_mut()
+            .resource_mut::<SimulationConfig>()
+            .history_ticks = 3;
+        let entity = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                create_test_physics_state(),
+                Vec2::splat(2.),
+                0,
+                [(2, ControlInput::SetThrust(1.0))],
+            ))
+            .id();
+        for _ in 0..6 {
+            app.update();
+        }
+
+        let timeline = app.world().entity(entity).get::<Timeline>().unwrap();
+        assert_eq!(timeline.first_tick(), Some(3));
+        assert!(timeline.input_events.is_empty());
+        let spatial_index = app.world().resource::<SpatialIndex>();
+        assert_eq!(spatial_index.first_tick(), Some(3));
+    }
+
+    #[test]
+    fn test_rewind_restores_states() {
+        let mut app = create_test_app();
+        app.world_mut()
+            .resource_mut::<SimulationConfig>()
+            .history_ticks = 10;
+        let entity = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                create_test_physics_state(),
+                Vec2::splat(2.),
+                0,
+                [(2, ControlInput::SetThrust(1.0))],
+            ))
+            .id();
+        for _ in 0..5 {
+            app.update();
+        }
+        let state_at = |app: &App, tick: u64| {
+            let timeline =
+                app.world().entity(entity).get::<Timeline>().unwrap();
+            timeline.state(tick).unwrap().into_owned()
+        };
+        let expected = state_at(&app, 3);
+
+        // Can't rewind into the future
+        rewind(6).apply(app.world_mut());
+        assert_eq!(app.world().resource::<SimulationConfig>().current_tick, 5);
+
+        rewind(1).apply(app.world_mut());
+        assert_eq!(app.world().resource::<SimulationConfig>().current_tick, 1);
+        let state = app.world().entity(entity).get::<PhysicsState>().unwrap();
+        assert_eq!(state.vel.to_vec2(), Vec2::ZERO);
+        let transform = app.world().entity(entity).get::<Transform>().unwrap();
+        assert_eq!(transform.translation, Vec3::ZERO);
+
+        // Replaying gives back the same states, and reindexes them
+        app.update();
+        app.update();
+        assert_eq!(*app.world().resource::<SimTick>(), SimTick(3));
+        let state = app.world().entity(entity).get::<PhysicsState>().unwrap();
+        assert_eq!(*state, expected);
+        let spatial_index = app.world().resource::<SpatialIndex>();
+        let found = spatial_index
+            .entities_within(
+                3,
+                BRect::from_center_half_size(expected.pos.to_vec2(), Vec2::ONE),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![entity]);
+    }
+
+    #[test]
+    fn test_rewind_brings_back_destroyed_bodies() {
+        let mut app = create_test_app();
+        app.world_mut()
+            .resource_mut::<SimulationConfig>()
+            .history_ticks = 10;
+        // Hits the asteroid at tick 5
+        let bullet_st = TestStateBuilder::new().vel(600., 0.).b();
+        let bullet = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(bullet_st, Vec2::ONE, 0, []),
+                Visibility::default(),
+            ))
+            .id();
+        let asteroid_st = TestStateBuilder::new().pos(50., 0.).mass(100.).b();
+        let asteroid = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                asteroid_st,
+                Vec2::splat(4.),
+                0,
+                [],
+            ))
+            .id();
+        for _ in 0..8 {
+            app.update();
+        }
+        let alive =
+            |app: &App| app.world().get::<PhysicsState>(bullet).unwrap().alive;
+        let visibility =
+            |app: &App| *app.world().get::<Visibility>(bullet).unwrap();
+        let hits = |app: &App| {
+            let timeline = app.world().get::<Timeline>(bullet).unwrap();
+            timeline.sim_events.get(&5).cloned().unwrap_or_default()
+        };
+
+        // Destroyed but kept, hidden, while it can be rewound to
+        assert!(!alive(&app));
+        assert_eq!(visibility(&app), Visibility::Hidden);
+        assert_eq!(hits(&app)[0].other, asteroid);
+
+        rewind(2).apply(app.world_mut());
+        assert!(alive(&app));
+        assert_eq!(visibility(&app), Visibility::Inherited);
+
+        // Replaying destroys it again, against the same asteroid
+        for _ in 2..8 {
+            app.update();
+        }
+        assert!(!alive(&app));
+        assert_eq!(hits(&app)[0].other, asteroid);
+
+        // Despawned once the collision leaves the history window
+        for _ in 0..10 {
+            app.update();
+        }
+        assert!(app.world().get::<PhysicsState>(bullet).is_none());
+    }
+
+    #[test]
+    fn test_simulation_sets_run_in_order_each_tick() {
+        #[derive(Resource, Default)]
+        struct Ran(Vec<(u64, SimulationSet)>);
+
+        let mut app = create_test_app();
+        app.init_resource::<Ran>();
+        // Added in reverse, so that only the sets order them
+        for set in [
+            SimulationSet::Cleanup,
+            SimulationSet::Sync,
+            SimulationSet::Predict,
+            SimulationSet::Gameplay,
+            SimulationSet::Inputs,
+        ] {
+            let record = move |tick: Res<SimTick>, mut ran: ResMut<Ran>| {
+                ran.0.push((tick.0, set));
+            };
+            app.add_systems(SimulationUpdate, record.in_set(set));
+        }
+        app.world_mut().spawn(PhysicsBundle::new_with_events(
+            create_test_physics_state(),
+            Vec2::splat(2.),
+            0,
+            [(2, ControlInput::SetThrust(1.0))],
+        ));
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let mut expected = Vec::new();
+        for tick in 1..=3 {
+            for set in [
+                SimulationSet::Inputs,
+                SimulationSet::Gameplay,
+                SimulationSet::Predict,
+                SimulationSet::Sync,
+                SimulationSet::Cleanup,
+            ] {
+                expected.push((tick, set));
+            }
+        }
+        assert_eq!(app.world().resource::<Ran>().0, expected);
+
+        // Pausing still runs the schedule, at the same tick
+        app.world_mut().resource_mut::<TimeController>().pause();
+        app.update();
+        assert_eq!(*app.world().resource::<SimTick>(), SimTick(3));
+    }
+
+    #[test]
+    fn test_beam_broken_event_sent_at_break_tick() {
+        #[derive(Resource, Default)]
+        struct Received(Vec<(u64, BeamBroken)>);
+
+        let mut app = create_test_app();
+        let receive = |tick: Res<SimTick>,
+                       mut events: EventReader<BeamBroken>,
+                       mut received: ResMut<Received>| {
+            received
+                .0
+                .extend(events.read().map(|e| (tick.0, e.clone())));
+        };
+        app.init_resource::<Received>()
+            .add_systems(Update, receive.after(PhysicsSystemSet));
+
+        // B starts within range but flies off at 10m per tick
+        let mut b_st = create_test_physics_state();
+        b_st.pos = RealVec2::from_vec2(Vec2::new(15., 0.));
+        b_st.vel = RealVec2::from_vec2(Vec2::new(600., 0.));
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, Vec2::ONE, 0, []))
+            .id();
+        let anchor = BeamAnchor::Entity(b);
+        let params = BeamParams {
+            neutral_length: 15.,
+            stiffness: 0.01,
+            max_length: 20.,
+        };
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                create_test_physics_state(),
+                Vec2::ONE,
+                0,
+                [(1, ControlInput::ElasticBeamConnect(anchor, params))],
+            ))
+            .id();
+        app.update();
+        let timeline = app.world().entity(a).get::<Timeline>().unwrap();
+        let break_tick = *timeline.broken_beams.keys().next().unwrap();
+        for _ in 0..break_tick + 2 {
+            app.update();
+        }
+        // Paused ticks don't send it again
+        app.world_mut().resource_mut::<TimeController>().pause();
+        app.update();
+
+        // Sent once, from the owner, when its tick is reached
+        let broken = BeamBroken {
+            owner: a,
+            anchor,
+            reason: BeamBreakReason::Overstretched,
+        };
+        assert_eq!(
+            app.world().resource::<Received>().0,
+            vec![(break_tick, broken)]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_connect_rejected_at_its_tick() {
+        #[derive(Resource, Default)]
+        struct Received(Vec<(u64, TimelineEventRejected)>);
+
+        let mut app = create_test_app();
+        let receive = |tick: Res<SimTick>,
+                       mut events: EventReader<TimelineEventRejected>,
+                       mut received: ResMut<Received>| {
+            received.0.extend(events.read().map(|e| (tick.0, *e)));
+        };
+        app.init_resource::<Received>()
+            .add_systems(Update, receive.after(PhysicsSystemSet));
+
+        let mut b_st = create_test_physics_state();
+        b_st.pos = RealVec2::from_vec2(Vec2::new(50., 0.));
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, Vec2::ONE, 0, []))
+            .id();
+        let connect = ControlInput::ElasticBeamConnect(
+            BeamAnchor::Entity(b),
+            BeamParams {
+                neutral_length: 15.,
+                stiffness: 0.01,
+                max_length: 20.,
+            },
+        );
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                create_test_physics_state(),
+                Vec2::ONE,
+                0,
+                [(3, connect)],
+            ))
+            .id();
+        for _ in 0..5 {
+            app.update();
+        }
+
+        // Sent once the simulation reaches the connect, with no break
+        let expected = TimelineEventRejected {
+            entity: a,
+            tick: 3,
+            input: connect,
+            removed: false,
+            reason: RejectionReason::OutOfRange,
+        };
+        assert_eq!(app.world().resource::<Received>().0, vec![(3, expected)]);
+        assert!(app.world().resource::<Events<BeamBroken>>().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_beam_params_rejected() {
+        #[derive(Resource, Default)]
+        struct Received(Vec<TimelineEventRejected>);
+
+        let mut app = create_test_app();
+        let receive = |mut events: EventReader<TimelineEventRejected>,
+                       mut received: ResMut<Received>| {
+            received.0.extend(events.read().copied());
+        };
+        app.init_resource::<Received>()
+            .add_systems(Update, receive.after(PhysicsSystemSet));
+        let entity = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                create_test_physics_state(),
+                Vec2::ONE,
+                0,
+                [],
+            ))
+            .id();
+
+        let connect = |params| {
+            ControlInput::ElasticBeamConnect(BeamAnchor::Fixed(Vec2::X), params)
+        };
+        let valid = BeamParams::default();
+        let invalid = [
+            BeamParams {
+                stiffness: -1.,
+                ..valid
+            },
+            BeamParams {
+                neutral_length: 200.,
+                ..valid
+            },
+            BeamParams {
+                max_length: 0.,
+                neutral_length: 0.,
+                ..valid
+            },
+            BeamParams {
+                max_length: f32::NAN,
+                ..valid
+            },
+        ];
+        assert!(valid.is_valid());
+        for params in [valid].into_iter().chain(invalid) {
+            app.world_mut().send_event(TimelineEventRequest {
+                entity,
+                tick: 5,
+                input: connect(params),
+            });
+        }
+        app.world_mut().send_event(TimelineEventRemovalRequest {
+            entity,
+            tick: 6,
+            input: connect(valid),
+        });
+        app.update();
+
+        let timeline = app.world().get::<Timeline>(entity).unwrap();
+        assert_eq!(timeline.inputs(5), &[connect(valid)]);
+        let received = &app.world().resource::<Received>().0;
+        let reasons = received
+            .iter()
+            .map(|rejected| (rejected.removed, rejected.reason))
+            .collect::<Vec<_>>();
+        let mut expected =
+            vec![(false, RejectionReason::InvalidBeamParams); invalid.len()];
+        expected.push((true, RejectionReason::NoMatchingInput));
+        assert_eq!(reasons, expected);
+    }
+
     #[test]
     fn test_rotation_affects_thrust_direction() {
         let mut state = create_test_physics_state();
         state.current_thrust = 1.0;
-        state.rotation = std::f32::consts::FRAC_PI_2; // 90 degrees, thrust up
+        state.rotation = Real::from_f32(std::f32::consts::FRAC_PI_2); // 90 degrees, thrust up
 
-        let next_state = state.integrate(1.0 / 60.0);
-        assert!(next_state.vel.x.abs() < f32::EPSILON);
-        assert!(next_state.vel.y > 0.0);
+        let next_state = state.integrate(1.0 / 60.0, Integrator::ExplicitEuler);
+        assert!(next_state.vel.to_vec2().x.abs() < f32::EPSILON);
+        assert!(next_state.vel.to_vec2().y > 0.0);
     }
 
     #[test]
     fn test_elastic_beam_potential_energy() {
         let beam = ElasticBeamInfo {
-            connected_entity: Entity::from_raw(1),
+            anchor: BeamAnchor::Entity(Entity::from_raw(1)),
             neutral_length: 10.0,
             stiffness: 0.25,
             max_length: 100.0,
@@ -765,7 +2032,7 @@ mod tests {
     #[test]
     fn test_elastic_beam_force() {
         let beam = ElasticBeamInfo {
-            connected_entity: Entity::from_raw(1),
+            anchor: BeamAnchor::Entity(Entity::from_raw(1)),
             neutral_length: 10.0,
             stiffness: 0.25,
             max_length: 100.0,
@@ -801,31 +2068,308 @@ mod tests {
 
         // Create beam pulling to the right
         let beam = ElasticBeamInfo {
-            connected_entity: Entity::from_raw(1),
+            anchor: BeamAnchor::Entity(Entity::from_raw(1)),
             neutral_length: 10.0,
             stiffness: 0.25,
             max_length: 100.0,
         };
-        state.elastic_beam = Some(Arc::new(beam));
 
         // Test normal integration
         let mut other = create_test_physics_state();
-        other.pos = Vec2::new(20.0, 0.0);
+        other.pos = RealVec2::from_vec2(Vec2::new(20.0, 0.0));
 
         let delta = 1.0 / 60.0;
-        state.integrate_beam(&mut other, delta);
+        let ee = Integrator::ExplicitEuler;
+        assert!(state.integrate_beam(&beam, Some(&mut other), delta, ee));
 
-        assert!(state.elastic_beam.is_some());
-        assert!(state.vel.x > 0.0);
-        assert_approx_eq!(state.vel.y, 0.0);
+        assert!(state.vel.to_vec2().x > 0.0);
+        assert_approx_eq!(state.vel.to_vec2().y, 0.0);
+        assert_approx_eq!(state.vel.to_vec2().x, -other.vel.to_vec2().x);
 
         // Test beam breaking
         let mut far_state = create_test_physics_state();
-        far_state.pos = Vec2::new(110.0, 0.0);
+        far_state.pos = RealVec2::from_vec2(Vec2::new(110.0, 0.0));
+        let before = state.clone();
+
+        assert!(!state.integrate_beam(&beam, Some(&mut far_state), delta, ee));
+        assert_eq!(state, before);
+        assert_eq!(far_state.vel.to_vec2(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_elastic_beam_fixed_anchor() {
+        let delta = 1.0 / 60.0;
+        let beam = ElasticBeamInfo::new(
+            BeamAnchor::Fixed(Vec2::new(20.0, 0.0)),
+            BeamParams {
+                neutral_length: 10.0,
+                stiffness: 0.25,
+                max_length: 100.0,
+            },
+        );
+        let mut state = create_test_physics_state();
+        state.mass = Real::from_f32(2.0);
+
+        assert!(state.integrate_beam(
+            &beam,
+            None,
+            delta,
+            Integrator::ExplicitEuler
+        ));
+        // The anchor takes none of the motion: a = k x / m
+        assert_approx_eq!(state.vel.to_vec2().x, 0.25 * 10.0 / 2.0 * delta);
+    }
+
+    const INTEGRATORS: [Integrator; 4] = [
+        Integrator::ExplicitEuler,
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+    ];
+
+    #[test]
+    fn test_integrators_constant_thrust() {
+        let delta = 1.0 / 60.0;
+        let ticks = 120;
+        let t = delta * ticks as f32;
+
+        for integrator in INTEGRATORS {
+            let mut state = create_test_physics_state();
+            state.mass = Real::from_f32(2.0);
+            state.current_thrust = 1.0;
+            for _ in 0..ticks {
+                state = state.integrate(delta, integrator);
+            }
+
+            // a = 100N / 2kg, x = a t^2 / 2
+            assert!((state.vel.to_vec2().x - 50.0 * t).abs() < 1e-3);
+            let expected_pos = 0.5 * 50.0 * t * t;
+            let tolerance = match integrator {
+                Integrator::ExplicitEuler | Integrator::SemiImplicitEuler => {
+                    50.0 * t * delta
+                }
+                Integrator::VelocityVerlet | Integrator::Rk4 => 1e-2,
+            };
+            assert!(
+                (state.pos.to_vec2().x - expected_pos).abs() <= tolerance,
+                "{integrator:?}: {} != {expected_pos}",
+                state.pos.x
+            );
+        }
+    }
+
+    #[test]
+    fn test_integrators_beam_oscillation() {
+        let delta = 1.0 / 60.0;
+        let beam = ElasticBeamInfo {
+            anchor: BeamAnchor::Entity(Entity::from_raw(1)),
+            neutral_length: 10.0,
+            stiffness: 4.0,
+            max_length: 100.0,
+        };
+        // Two unit masses => relative motion has w^2 = k (1/m_a + 1/m_b)
+        let w = (beam.stiffness * 2.0_f32).sqrt();
+        let stretch = 5.0;
+        // Stop before the beam goes slack at a quarter period
+        let ticks = (0.9 * FRAC_PI_2 / w / delta) as usize;
+        let t = ticks as f32 * delta;
+
+        let separation = |integrator| {
+            let mut a = create_test_physics_state();
+            let mut b = create_test_physics_state();
+            b.pos.x = Real::from_f32(beam.neutral_length + stretch);
+
+            for _ in 0..ticks {
+                a = a.integrate(delta, integrator);
+                b = b.integrate(delta, integrator);
+                a.integrate_beam(&beam, Some(&mut b), delta, integrator);
+            }
+            // Momentum is conserved
+            assert_approx_eq!(a.vel.to_vec2().x + b.vel.to_vec2().x, 0.0);
+            (b.pos.x - a.pos.x).to_f32()
+        };
+
+        let expected = beam.neutral_length + stretch * (w * t).cos();
+        for integrator in [Integrator::VelocityVerlet, Integrator::Rk4] {
+            let error = (separation(integrator) - expected).abs();
+            assert!(error < 2e-2, "{integrator:?} error {error}");
+        }
+        let euler_error =
+            (separation(Integrator::ExplicitEuler) - expected).abs();
+        let rk4_error = (separation(Integrator::Rk4) - expected).abs();
+        assert!(rk4_error < euler_error);
+    }
+
+    #[test]
+    fn test_thrust_burns_propellant() {
+        let delta = 1.0 / 60.0;
+        // 10kg dry + 10kg propellant, 100N at 1000 m/s exhaust = 0.1 kg/s
+        let state = PhysicsState {
+            mass: Real::from_f32(20.0),
+            current_thrust: 1.0,
+            propellant: Real::from_f32(10.0),
+            exhaust_velocity: 1000.0,
+            ..create_test_physics_state()
+        };
+
+        let next_state = state.integrate(delta, Integrator::ExplicitEuler);
+        assert_approx_eq!(next_state.propellant.to_f32(), 10.0 - 0.1 * delta);
+        assert_approx_eq!(next_state.mass.to_f32(), 20.0 - 0.1 * delta);
+        assert_approx_eq!(next_state.current_thrust, 1.0);
+
+        // Reverse thrust burns just as much
+        let reverse = PhysicsState {
+            current_thrust: -1.0,
+            ..state.clone()
+        }
+        .integrate(delta, Integrator::ExplicitEuler);
+        assert_approx_eq!(
+            reverse.propellant.to_f32(),
+            next_state.propellant.to_f32()
+        );
+
+        // Free thrust never touches the tank
+        let free = PhysicsState {
+            exhaust_velocity: 0.0,
+            ..state
+        }
+        .integrate(delta, Integrator::ExplicitEuler);
+        assert_eq!(free.propellant.to_f32(), 10.0);
+        assert_eq!(free.mass.to_f32(), 20.0);
+    }
+
+    #[test]
+    fn test_thrust_cuts_out_when_empty() {
+        // Enough propellant for half a second of thrust
+        let state = PhysicsState {
+            mass: Real::from_f32(2.0),
+            current_thrust: 1.0,
+            propellant: Real::from_f32(0.05),
+            exhaust_velocity: 1000.0,
+            ..create_test_physics_state()
+        };
+        assert!(!state.is_out_of_propellant());
 
-        state.integrate_beam(&mut far_state, delta);
+        let next_state = state.integrate(1.0, Integrator::Rk4);
+        assert_eq!(next_state.propellant.to_f32(), 0.0);
+        assert_approx_eq!(next_state.mass.to_f32(), 1.95);
+        assert_eq!(next_state.current_thrust, 0.0);
+        assert!(next_state.is_out_of_propellant());
+        assert_abs_diff_le_x!(next_state.vel.to_vec2().x, state.delta_v(), 0.1);
+
+        // Scheduled thrust has no effect on an empty tank
+        let coasting = PhysicsState {
+            current_thrust: 1.0,
+            ..next_state.clone()
+        }
+        .integrate(1.0, Integrator::Rk4);
+        assert_eq!(coasting.vel, next_state.vel);
+        assert_eq!(coasting.mass, next_state.mass);
+    }
+
+    #[test]
+    fn test_delta_v_matches_rocket_equation() {
+        let state = PhysicsState {
+            mass: Real::from_f32(30.0),
+            current_thrust: 1.0,
+            propellant: Real::from_f32(20.0),
+            exhaust_velocity: 500.0,
+            ..create_test_physics_state()
+        };
+        let expected = 500.0 * 3.0_f32.ln();
+        assert_approx_eq!(state.delta_v(), expected);
+        assert_eq!(create_test_physics_state().delta_v(), f32::INFINITY);
 
-        assert!(state.elastic_beam.is_none());
-        assert!(far_state.elastic_beam.is_none());
+        // Burning the whole tank reaches the predicted velocity
+        let delta = 1.0 / 60.0;
+        let mut current = state;
+        while !current.is_out_of_propellant() {
+            current = current.integrate(delta, Integrator::Rk4);
+        }
+        assert_abs_diff_le_x!(current.vel.to_vec2().x, expected, 0.5);
+        assert_eq!(current.delta_v(), 0.0);
+    }
+
+    #[test]
+    fn test_rotate_to_slews_at_bounded_rate() {
+        let delta = 1.0 / 60.0;
+        // 4 Nm on 2 kg*m^2 = 2 rad/s^2
+        let mut state = PhysicsState {
+            moment_of_inertia: 2.0,
+            max_torque: 4.0,
+            ..create_test_physics_state()
+        };
+        let max_dv = state.max_angular_accel() * delta;
+        state.apply_input_event(&ControlInput::RotateTo(FRAC_PI_2));
+        assert_eq!(state.rotation.to_f32(), 0.0);
+        assert_eq!(state.target_rotation, Some(FRAC_PI_2));
+
+        let mut ticks = 0;
+        while state.target_rotation.is_some() {
+            let next = state.integrate(delta, Integrator::ExplicitEuler);
+            assert_abs_diff_le_x!(
+                next.ang_vel.to_f32(),
+                state.ang_vel.to_f32(),
+                max_dv + 1e-5
+            );
+            state = next;
+            ticks += 1;
+            assert!(ticks < 600, "slew never settled");
+        }
+        assert_abs_diff_le_x!(state.rotation.to_f32(), FRAC_PI_2, 1e-4);
+        assert_eq!(state.ang_vel.to_f32(), 0.0);
+
+        // Close to the minimum time to turn from rest: 2 * sqrt(angle / accel)
+        let min_ticks = 2.0 * (FRAC_PI_2 / 2.0).sqrt() / delta;
+        assert!(ticks as f32 >= min_ticks - 1.);
+        assert!((ticks as f32) < min_ticks * 1.1);
+    }
+
+    #[test]
+    fn test_rotate_to_takes_shortest_way_round() {
+        let mut state = PhysicsState {
+            rotation: Real::from_f32(0.1),
+            moment_of_inertia: 1.0,
+            max_torque: 1.0,
+            ..create_test_physics_state()
+        };
+        state.apply_input_event(&ControlInput::RotateTo(2. * PI - 0.1));
+        let next = state.integrate(1.0 / 60.0, Integrator::ExplicitEuler);
+        assert!(next.ang_vel.to_f32() < 0.0);
+        assert!(next.rotation < state.rotation);
+    }
+
+    #[test]
+    fn test_rotate_to_snaps_without_torque_limit() {
+        let mut state = PhysicsState {
+            ang_vel: Real::from_f32(1.0),
+            ..create_test_physics_state()
+        };
+        state.apply_input_event(&ControlInput::RotateTo(PI));
+        assert_eq!(state.rotation.to_f32(), PI);
+        assert_eq!(state.ang_vel.to_f32(), 0.0);
+        assert_eq!(state.target_rotation, None);
+    }
+
+    #[test]
+    fn test_thrust_while_slewing_curves_trajectory() {
+        let delta = 1.0 / 60.0;
+        let mut state = PhysicsState {
+            moment_of_inertia: 1.0,
+            max_torque: 2.0,
+            ..create_test_physics_state()
+        };
+        state.apply_input_event(&ControlInput::SetThrustAndRotation(
+            1.0, FRAC_PI_2,
+        ));
+        assert_eq!(state.rotation.to_f32(), 0.0);
+
+        for _ in 0..120 {
+            state = state.integrate(delta, Integrator::Rk4);
+        }
+        // Burned along +X while turning, then along +Y once aligned
+        assert!(state.vel.to_vec2().x > 10.0);
+        assert!(state.vel.y > state.vel.x);
+        assert_eq!(state.target_rotation, None);
     }
 }
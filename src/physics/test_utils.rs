@@ -3,9 +3,11 @@ use bevy::utils::default;
 
 use crate::{
     physics::{
+        Integrator,
         PhysicsBundle,
         PhysicsSimulationPlugin,
         PhysicsState,
+        Real,
         SimulationConfig,
     },
     prelude::*,
@@ -18,19 +20,27 @@ pub const TEST_CONFIG: SimulationConfig = SimulationConfig {
     time_dilation: 1.0,
     paused: false,
     prediction_ticks: 2,
+    integrator: Integrator::ExplicitEuler,
+    history_ticks: 1,
+    parallel_islands: true,
 };
 
 #[macro_export]
 macro_rules! states_eq {
     ($a:expr, $b:expr) => {
-        assert_abs_diff_le_x!($a.pos.x, $b.pos.x, 1e-3);
-        assert_abs_diff_le_x!($a.pos.y, $b.pos.y, 1e-3);
-        assert_abs_diff_le_x!($a.vel.x, $b.vel.x, 1e-3);
-        assert_abs_diff_le_x!($a.vel.y, $b.vel.y, 1e-3);
+        assert_abs_diff_le_x!($a.pos.to_vec2().x, $b.pos.to_vec2().x, 1e-3);
+        assert_abs_diff_le_x!($a.pos.to_vec2().y, $b.pos.to_vec2().y, 1e-3);
+        assert_abs_diff_le_x!($a.vel.to_vec2().x, $b.vel.to_vec2().x, 1e-3);
+        assert_abs_diff_le_x!($a.vel.to_vec2().y, $b.vel.to_vec2().y, 1e-3);
         assert_abs_diff_le_x!($a.current_thrust, $b.current_thrust, 1e-3);
         assert_abs_diff_le_x!($a.max_thrust, $b.max_thrust, 1e-3);
-        assert_abs_diff_le_x!($a.rotation, $b.rotation, 1e-3);
-        assert_abs_diff_le_x!($a.mass, $b.mass, 1e-3);
+        assert_abs_diff_le_x!($a.rotation.to_f32(), $b.rotation.to_f32(), 1e-3);
+        assert_abs_diff_le_x!($a.mass.to_f32(), $b.mass.to_f32(), 1e-3);
+        assert_abs_diff_le_x!(
+            $a.propellant.to_f32(),
+            $b.propellant.to_f32(),
+            1e-3
+        );
         assert_eq!($a.alive, $b.alive);
     };
 }
@@ -56,31 +66,37 @@ impl TestStateBuilder {
     pub fn new() -> Self {
         Self {
             state: PhysicsState {
-                elastic_beam: None,
-                pos: Vec2::ZERO,
-                vel: Vec2::ZERO,
-                rotation: 0.0,
-                ang_vel: 0.0,
-                mass: 1.0,
+                elastic_beams: Vec::new(),
+                pos: RealVec2::ZERO,
+                vel: RealVec2::ZERO,
+                rotation: Real::ZERO,
+                ang_vel: Real::ZERO,
+                mass: Real::ONE,
                 current_thrust: 0.0,
                 max_thrust: 100.0,
+                propellant: Real::ZERO,
+                exhaust_velocity: 0.0,
+                moment_of_inertia: 0.0,
+                max_torque: 0.0,
+                target_rotation: None,
+                health: 1.0,
                 alive: true,
             },
         }
     }
 
     pub fn pos(mut self, x: f32, y: f32) -> Self {
-        self.state.pos = Vec2::new(x, y);
+        self.state.pos = RealVec2::from_vec2(Vec2::new(x, y));
         self
     }
 
     pub fn vel(mut self, x: f32, y: f32) -> Self {
-        self.state.vel = Vec2::new(x, y);
+        self.state.vel = RealVec2::from_vec2(Vec2::new(x, y));
         self
     }
 
     pub fn mass(mut self, mass: f32) -> Self {
-        self.state.mass = mass;
+        self.state.mass = Real::from_f32(mass);
         self
     }
 
@@ -90,6 +106,27 @@ impl TestStateBuilder {
         self
     }
 
+    pub fn propellant(
+        mut self,
+        propellant: f32,
+        exhaust_velocity: f32,
+    ) -> Self {
+        self.state.propellant = Real::from_f32(propellant);
+        self.state.exhaust_velocity = exhaust_velocity;
+        self
+    }
+
+    pub fn torque(mut self, moment_of_inertia: f32, max_torque: f32) -> Self {
+        self.state.moment_of_inertia = moment_of_inertia;
+        self.state.max_torque = max_torque;
+        self
+    }
+
+    pub fn health(mut self, health: f32) -> Self {
+        self.state.health = health;
+        self
+    }
+
     pub fn alive(mut self, alive: bool) -> Self {
         self.state.alive = alive;
         self
@@ -225,12 +262,12 @@ impl CollisionScenario {
             "Entity A alive state mismatch"
         );
         if let Some(expected_pos) = self.expected_a.pos {
-            assert_approx_eq!(a_final.pos.x, expected_pos.x);
-            assert_approx_eq!(a_final.pos.y, expected_pos.y);
+            assert_approx_eq!(a_final.pos.to_vec2().x, expected_pos.x);
+            assert_approx_eq!(a_final.pos.to_vec2().y, expected_pos.y);
         }
         if let Some(expected_vel) = self.expected_a.vel {
-            assert_approx_eq!(a_final.vel.x, expected_vel.x);
-            assert_approx_eq!(a_final.vel.y, expected_vel.y);
+            assert_approx_eq!(a_final.vel.to_vec2().x, expected_vel.x);
+            assert_approx_eq!(a_final.vel.to_vec2().y, expected_vel.y);
         }
 
         // Assert B's results
@@ -239,12 +276,12 @@ impl CollisionScenario {
             "Entity B alive state mismatch"
         );
         if let Some(expected_pos) = self.expected_b.pos {
-            assert_approx_eq!(b_final.pos.x, expected_pos.x);
-            assert_approx_eq!(b_final.pos.y, expected_pos.y);
+            assert_approx_eq!(b_final.pos.to_vec2().x, expected_pos.x);
+            assert_approx_eq!(b_final.pos.to_vec2().y, expected_pos.y);
         }
         if let Some(expected_vel) = self.expected_b.vel {
-            assert_approx_eq!(b_final.vel.x, expected_vel.x);
-            assert_approx_eq!(b_final.vel.y, expected_vel.y);
+            assert_approx_eq!(b_final.vel.to_vec2().x, expected_vel.x);
+            assert_approx_eq!(b_final.vel.to_vec2().y, expected_vel.y);
         }
     }
 }
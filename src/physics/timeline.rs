@@ -1,20 +1,54 @@
-use super::*;
-use crate::prelude::*;
+use std::borrow::Cow;
+
+use bevy::{
+    ecs::system::SystemParam,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+
+use super::{bounds::sweep_start, collisions::Material, Real, *};
+use crate::{prelude::*, Selected};
 
 /// Stores scheduled inputs and computed future states for an entity
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Timeline {
     /// Computed physics states for future simulation ticks
+    /// Ticks covered by `coasts` have no stored state, use `Timeline::state`
+    /// to read any tick
     pub future_states: BTreeMap<u64, PhysicsState>,
+    /// Stretches where the entity drifts free of any force, keyed by their
+    /// first tick
+    /// Their states are computed in closed form when queried
+    pub coasts: BTreeMap<u64, Coast>,
     /// Ordered list of future control inputs
+    /// Each tick holds its inputs in the order they are applied
     /// Future states and sim_events are a function of
     /// prev state and input events
-    pub input_events: BTreeMap<u64, ControlInput>,
+    pub input_events: BTreeMap<u64, Vec<ControlInput>>,
     /// Ordered list of future sim events
+    /// Each tick holds every collision the entity was in, in the order they
+    /// were resolved
     /// These are created by computing future states
-    pub sim_events: BTreeMap<u64, Collision>,
+    pub sim_events: BTreeMap<u64, Vec<Collision>>,
+    /// Elastic beams that broke at each tick, involving this entity at
+    /// either end
+    /// Like sim_events, these are created by computing future states
+    pub broken_beams: BTreeMap<u64, Vec<BeamBroken>>,
+    /// Beam connects at each tick that couldn't reach their anchor, so the
+    /// beam never formed
+    /// Like sim_events, these are created by computing future states
+    #[serde(default)]
+    pub rejected_connects: BTreeMap<u64, Vec<ControlInput>>,
+    /// Debris this entity breaks into when destroyed in a collision
+    /// The fragments are spawned as their own entities, starting at the tick
+    /// of the collision
+    pub fragmentations: BTreeMap<u64, Fragmentation>,
     /// Last tick that has valid computed states
     pub last_computed_tick: u64,
+    /// First tick of the states computed in coarse steps, which run to the
+    /// last state
+    /// See `PredictionPolicy`
+    #[serde(default)]
+    pub coarse_from: Option<u64>,
     /// Tick range that was modified most recently
     pub last_updated_range: Option<RangeInclusive<u64>>,
 }
@@ -23,73 +57,419 @@ impl Default for Timeline {
     fn default() -> Self {
         Self {
             future_states: default(),
+            coasts: default(),
             input_events: default(),
             sim_events: default(),
+            broken_beams: default(),
+            rejected_connects: default(),
+            fragmentations: default(),
             last_computed_tick: default(),
+            coarse_from: None,
             last_updated_range: None,
         }
     }
 }
 
+impl MapEntities for Timeline {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for state in self.future_states.values_mut() {
+            state.map_entities(entity_mapper);
+        }
+        for coast in self.coasts.values_mut() {
+            coast.origin.map_entities(entity_mapper);
+        }
+        for input in self.input_events.values_mut().flatten() {
+            input.map_entities(entity_mapper);
+        }
+        for collision in self.sim_events.values_mut().flatten() {
+            collision.map_entities(entity_mapper);
+        }
+        for broken in self.broken_beams.values_mut().flatten() {
+            broken.map_entities(entity_mapper);
+        }
+        for input in self.rejected_connects.values_mut().flatten() {
+            input.map_entities(entity_mapper);
+        }
+        for fragmentation in self.fragmentations.values_mut() {
+            fragmentation.map_entities(entity_mapper);
+        }
+    }
+}
+
+/// Straight line motion at constant spin from a known state
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Coast {
+    /// Last tick covered
+    pub end_tick: u64,
+    /// Tick the motion is extrapolated from, before the first covered tick
+    pub origin_tick: u64,
+    /// State at `origin_tick`
+    pub origin: PhysicsState,
+    pub seconds_per_tick: f32,
+}
+
+impl Coast {
+    pub fn state(&self, tick: u64) -> PhysicsState {
+        self.origin
+            .coasted(tick - self.origin_tick, self.seconds_per_tick)
+    }
+}
+
 impl Timeline {
-    pub fn state(&self, tick: u64) -> Option<&PhysicsState> {
-        self.future_states.get(&tick)
+    /// State at `tick`, computed on the fly if the entity is coasting
+    pub fn state(&self, tick: u64) -> Option<Cow<'_, PhysicsState>> {
+        if let Some(state) = self.future_states.get(&tick) {
+            return Some(Cow::Borrowed(state));
+        }
+        self.coast_at(tick)
+            .map(|coast| Cow::Owned(coast.state(tick)))
     }
 
+    /// State at `tick` to modify, which is stored first if it was coasted
+    /// The coasted states around it are kept
     pub fn state_mut(&mut self, tick: u64) -> Option<&mut PhysicsState> {
+        if let Some(coast) = self.coast_at(tick) {
+            let state = coast.state(tick);
+            self.split_coast(tick);
+            self.future_states.insert(tick, state);
+        }
         self.future_states.get_mut(&tick)
     }
 
+    /// Store the state computed for `tick`, dropping coasted states from
+    /// `tick` on
+    pub fn set_state(&mut self, tick: u64, state: PhysicsState) {
+        self.truncate_coasts(tick);
+        self.future_states.insert(tick, state);
+    }
+
+    /// Continue the force-free motion of the state at `tick - 1` through
+    /// `tick`
+    pub fn coast_to(&mut self, tick: u64, seconds_per_tick: f32) {
+        self.truncate_coasts(tick);
+        self.future_states.remove(&tick);
+        if let Some(coast) = self.coasts.values_mut().next_back() {
+            if coast.end_tick == tick - 1
+                && coast.seconds_per_tick == seconds_per_tick
+            {
+                coast.end_tick = tick;
+                return;
+            }
+        }
+        let origin = self
+            .state(tick - 1)
+            .expect("Coasting needs the previous state")
+            .into_owned();
+        self.coasts.insert(
+            tick,
+            Coast {
+                end_tick: tick,
+                origin_tick: tick - 1,
+                origin,
+                seconds_per_tick,
+            },
+        );
+    }
+
+    /// Coast covering `tick`, if its state isn't stored
+    pub fn coast_at(&self, tick: u64) -> Option<&Coast> {
+        let (_, coast) = self.coasts.range(..=tick).next_back()?;
+        (tick <= coast.end_tick).then_some(coast)
+    }
+
+    /// Replace the state at `tick` with `coast`, so that it carries on from
+    /// the same origin
+    pub fn resume_coast(&mut self, tick: u64, coast: Coast) {
+        self.future_states.remove(&tick);
+        self.coasts.insert(
+            tick,
+            Coast {
+                end_tick: tick,
+                ..coast
+            },
+        );
+    }
+
+    /// Take `tick` out of the coast covering it
+    fn split_coast(&mut self, tick: u64) {
+        let Some((&start, _)) = self.coasts.range(..=tick).next_back() else {
+            return;
+        };
+        let coast = self.coasts.remove(&start).unwrap();
+        if tick > start {
+            self.coasts.insert(
+                start,
+                Coast {
+                    end_tick: tick - 1,
+                    ..coast.clone()
+                },
+            );
+        }
+        if tick < coast.end_tick {
+            self.coasts.insert(tick + 1, coast);
+        }
+    }
+
+    /// Drop coasted states from `tick` on
+    fn truncate_coasts(&mut self, tick: u64) {
+        self.coasts.retain(|start, _| *start < tick);
+        if let Some(coast) = self.coasts.values_mut().next_back() {
+            coast.end_tick = coast.end_tick.min(tick - 1);
+        }
+    }
+
+    /// Drop every state before `tick`
+    pub fn drop_states_before(&mut self, tick: u64) {
+        self.future_states = self.future_states.split_off(&tick);
+        if let Some((&start, _)) = self.coasts.range(..tick).next_back() {
+            let coast = self.coasts.remove(&start).unwrap();
+            if coast.end_tick >= tick {
+                self.coasts.insert(tick, coast);
+            }
+        }
+        self.coasts = self.coasts.split_off(&tick);
+    }
+
+    /// Store `state` as the end of a coarse step over the `ticks` ticks from
+    /// `tick`, with the ticks before it interpolated in a straight line from
+    /// the state at `tick - 1`
+    pub fn set_coarse_step(
+        &mut self,
+        tick: u64,
+        ticks: u64,
+        state: PhysicsState,
+        seconds_per_tick: f32,
+    ) {
+        let end_tick = tick + ticks - 1;
+        self.truncate_coasts(tick);
+        let stale = self
+            .future_states
+            .range(tick..end_tick)
+            .map(|(tick, _)| *tick)
+            .collect::<Vec<_>>();
+        for tick in stale {
+            self.future_states.remove(&tick);
+        }
+        if ticks > 1 {
+            let prev = self
+                .state(tick - 1)
+                .expect("Coarse steps need the previous state")
+                .into_owned();
+            let seconds = Real::from_f32(ticks as f32 * seconds_per_tick);
+            let origin = PhysicsState {
+                vel: (state.pos - prev.pos) / seconds,
+                ang_vel: (state.rotation - prev.rotation) / seconds,
+                ..prev
+            };
+            self.coasts.insert(
+                tick,
+                Coast {
+                    end_tick: end_tick - 1,
+                    origin_tick: tick - 1,
+                    origin,
+                    seconds_per_tick,
+                },
+            );
+        }
+        self.future_states.insert(end_tick, state);
+        self.coarse_from = self.coarse_from.or(Some(tick));
+    }
+
+    /// Take the states from `tick` on out of the timeline, see
+    /// `append_states` to put them back
+    pub fn split_states_off(&mut self, tick: u64) -> Timeline {
+        let mut tail = Timeline {
+            future_states: self.future_states.split_off(&tick),
+            coasts: self.coasts.split_off(&tick),
+            ..default()
+        };
+        if let Some(coast) = self.coasts.values_mut().next_back() {
+            if coast.end_tick >= tick {
+                tail.coasts.insert(tick, coast.clone());
+                coast.end_tick = tick - 1;
+            }
+        }
+        tail
+    }
+
+    /// Put back states split off past the last state
+    pub fn append_states(&mut self, mut tail: Timeline) {
+        self.future_states.append(&mut tail.future_states);
+        self.coasts.append(&mut tail.coasts);
+    }
+
+    /// First tick with a state, stored or coasted
+    pub fn first_tick(&self) -> Option<u64> {
+        let stored = self.future_states.keys().next();
+        let coasted = self.coasts.keys().next();
+        stored.into_iter().chain(coasted).min().copied()
+    }
+
+    /// Last tick with a state, stored or coasted
+    pub fn last_tick(&self) -> Option<u64> {
+        let stored = self.future_states.keys().next_back();
+        let coasted = self.coasts.values().map(|coast| &coast.end_tick);
+        stored.into_iter().chain(coasted).max().copied()
+    }
+
+    /// Every state in tick order, stored or coasted
+    pub fn states(
+        &self,
+    ) -> impl Iterator<Item = (u64, Cow<'_, PhysicsState>)> + '_ {
+        self.first_tick()
+            .zip(self.last_tick())
+            .into_iter()
+            .flat_map(|(first, last)| first..=last)
+            .filter_map(|tick| Some((tick, self.state(tick)?)))
+    }
+
+    /// Schedule `event` at `tick`, after any inputs already at that tick
     pub fn add_input_event(&mut self, tick: u64, event: ControlInput) {
-        self.input_events.insert(tick, event);
+        self.input_events.entry(tick).or_default().push(event);
+        self.last_computed_tick = self.last_computed_tick.min(tick - 1);
+    }
+
+    /// Schedule `event` at `tick` in place of the first input of the same
+    /// kind, or after the existing inputs if there is none
+    pub fn replace_input_event(&mut self, tick: u64, event: ControlInput) {
+        let inputs = self.input_events.entry(tick).or_default();
+        let kind = std::mem::discriminant(&event);
+        match inputs
+            .iter_mut()
+            .find(|input| std::mem::discriminant(*input) == kind)
+        {
+            Some(input) => *input = event,
+            None => inputs.push(event),
+        }
         self.last_computed_tick = self.last_computed_tick.min(tick - 1);
     }
 
+    /// Remove the first input at `tick` equal to `event`, keeping the order
+    /// of the rest
     pub fn remove_input_event(
         &mut self,
         tick: u64,
         event: ControlInput,
     ) -> bool {
-        let existing = self.input_events.get(&tick);
-        if existing != Some(&event) {
+        let Some(inputs) = self.input_events.get_mut(&tick) else {
+            return false;
+        };
+        let Some(index) = inputs.iter().position(|input| *input == event)
+        else {
             return false;
+        };
+        inputs.remove(index);
+        if inputs.is_empty() {
+            self.input_events.remove(&tick);
         }
-        self.input_events.remove(&tick);
         self.last_computed_tick = self.last_computed_tick.min(tick - 1);
         true
     }
+
+    /// Control inputs applied at `tick`, in application order
+    pub fn inputs(&self, tick: u64) -> &[ControlInput] {
+        self.input_events.get(&tick).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// What `compute_future_states` predicts with, and the bookkeeping it keeps
+/// between runs
+#[derive(SystemParam)]
+pub struct PredictionParams<'w, 's> {
+    sim_config: Res<'w, SimulationConfig>,
+    bounds: Option<Res<'w, ArenaBounds>>,
+    policies: Query<'w, 's, &'static PredictionPolicy>,
+    selected: Option<Res<'w, Selected>>,
+    colliders: Query<'w, 's, &'static Collider>,
+    materials: Query<'w, 's, &'static Material>,
+    state: Local<'s, PredictionState>,
+}
+
+/// Bookkeeping `compute_future_states` keeps between runs
+#[derive(Default)]
+pub struct PredictionState {
+    invalid_set: EntityHashMap<u64>,
+    last_updated_sets: HashMap<u64, EntityHashSet>,
+    coarse_steps: EntityHashMap<u64>,
+    refinements: EntityHashMap<Refinement>,
+    /// Bounds the predictions were computed with
+    last_bounds: Option<ArenaBounds>,
 }
 
 /// Compute future states for all entities
+///
+/// Each entity is predicted up to its own horizon, see `PredictionPolicy`
 pub fn compute_future_states(
-    sim_config: Res<SimulationConfig>,
+    params: PredictionParams,
     mut spatial_index: ResMut<SpatialIndex>,
     mut query: Query<(Entity, &Collider, &mut Timeline)>,
-    mut invalid_set: Local<EntityHashMap<u64>>,
-    mut last_updated_sets: Local<HashMap<u64, EntityHashSet>>,
+    gravity_sources: Query<(Entity, &GravitySource)>,
 ) {
     if query.is_empty() {
         warn!("No entities match compute future states");
         return;
     }
-    let end_tick = sim_config.current_tick + sim_config.prediction_ticks;
-    let seconds_per_tick = 1.0 / sim_config.ticks_per_second as f32;
+    let PredictionParams {
+        sim_config,
+        bounds,
+        policies,
+        selected,
+        colliders,
+        materials,
+        mut state,
+    } = params;
+    let PredictionState {
+        invalid_set,
+        last_updated_sets,
+        coarse_steps,
+        refinements,
+        last_bounds,
+    } = &mut *state;
+    let bounds = bounds.as_deref();
+    let current_tick = sim_config.current_tick;
+    // Every prediction runs into the bounds, so adding, changing or removing
+    // them redoes all of them
+    if *last_bounds != bounds.copied() {
+        *last_bounds = bounds.copied();
+        for (_, _, mut timeline) in query.iter_mut() {
+            timeline.last_computed_tick =
+                timeline.last_computed_tick.min(current_tick);
+        }
+    }
+    let horizon_end = |entity| {
+        current_tick
+            + policies
+                .get(entity)
+                .map_or(sim_config.prediction_ticks, |p| p.horizon_ticks)
+    };
+    let end_tick = query
+        .iter()
+        .map(|(entity, _, _)| horizon_end(entity))
+        .max()
+        .unwrap();
     // Track min tick so we know where to start
     let mut min_tick = u64::MAX;
 
-    reset_last_updated_sets(
-        &sim_config,
-        &mut last_updated_sets,
-        &mut invalid_set,
-    );
+    reset_last_updated_sets(&sim_config, last_updated_sets, invalid_set);
+    coarse_steps.clear();
+    refinements.clear();
 
     // Construct map of tick to set{entities | last_updated == tick}
     for (entity, _, mut timeline) in query.iter_mut() {
+        let mut start_tick = timeline.last_computed_tick;
+        if let Some(refinement) = start_refinement(
+            &sim_config,
+            policies.get(entity).ok(),
+            &mut timeline,
+        ) {
+            start_tick = start_tick.min(refinement.from_tick - 1);
+            refinements.insert(entity, refinement);
+        }
         last_updated_sets
-            .entry(timeline.last_computed_tick)
+            .entry(start_tick)
             .or_default()
             .insert(entity);
-        min_tick = min_tick.min(timeline.last_computed_tick);
+        min_tick = min_tick.min(start_tick);
         // Clear last_updated_range so we can recompute it
         timeline.last_updated_range = None;
     }
@@ -99,6 +479,11 @@ pub fn compute_future_states(
         "min_tick must be >= current tick"
     );
 
+    let sources = gravity_sources
+        .iter()
+        .map(|(e, source)| (e, *source))
+        .collect::<Vec<_>>();
+
     let mut entities_to_invalidate = Vec::new();
     for tick in (min_tick + 1)..=end_tick {
         // Add entities that were last computed the previous tick
@@ -109,9 +494,21 @@ pub fn compute_future_states(
             });
         }
 
+        invalidate_gravity_dependents(
+            &query,
+            &sources,
+            &spatial_index,
+            invalid_set,
+            tick,
+        );
+        // A recomputed source changes the rest of every prediction it reaches
+        if sources.iter().any(|(e, _)| invalid_set.contains_key(e)) {
+            refinements.clear();
+        }
+
         invalidate_sim_events(
             &mut query,
-            &mut invalid_set,
+            invalid_set,
             &mut entities_to_invalidate,
             tick,
         );
@@ -120,227 +517,1198 @@ pub fn compute_future_states(
             let (_, _, timeline) = query.get_mut(entity).unwrap();
         }
 
-        // For each entity in invalid set, apply inputs and integrate physics
-        let mut beam_pairs = Vec::new();
-        for &entity in invalid_set.keys() {
-            let (_, collider, mut timeline) = query.get_mut(entity).unwrap();
-            apply_inputs_and_integrate_phys(
-                tick,
-                seconds_per_tick,
+        // Entities past their own horizon are done
+        let done = invalid_set
+            .iter()
+            .filter(|(entity, _)| {
+                tick > horizon_end(**entity)
+                    && coarse_steps.get(*entity).is_none_or(|end| *end < tick)
+            })
+            .map(|(entity, start_tick)| (*entity, *start_tick))
+            .collect::<Vec<_>>();
+        for (entity, start_tick) in done {
+            invalid_set.remove(&entity);
+            let (_, _, mut timeline) = query.get_mut(entity).unwrap();
+            finish_timeline(
                 entity,
+                start_tick,
                 &mut timeline,
-                collider,
-                Some(&mut spatial_index),
+                &mut spatial_index,
             );
-            // Find any elastic beam connections to add
-            if let Some(beam) =
-                timeline.state(tick).unwrap().elastic_beam.as_ref()
-            {
-                beam_pairs.push((entity, beam.connected_entity));
-            }
         }
 
-        // Integrate beam force on all entities with active beams
-        for (entity, connected_entity) in beam_pairs {
-            // Add debug logging to help track the issue
-            eprintln!("Processing beam pair: {:?}", (entity, connected_entity));
-
-            let [(_, _, mut timeline), (_, _, mut timeline_connected)] =
-                query.get_many_mut([entity, connected_entity]).unwrap();
-            let state = timeline.state_mut(tick).unwrap();
-            let state_connected = timeline_connected.state_mut(tick).unwrap();
+        // Entities in the middle of a coarse step were computed with it
+        let stepping = invalid_set
+            .iter()
+            .filter(|(entity, _)| {
+                coarse_steps.get(*entity).is_some_and(|end| *end >= tick)
+            })
+            .map(|(entity, start_tick)| (*entity, *start_tick))
+            .collect::<Vec<_>>();
+        for (entity, _) in &stepping {
+            invalid_set.remove(entity);
+        }
 
-            // Integrate beam force on both entities
-            state.integrate_beam(state_connected, seconds_per_tick);
+        // Gravity acts from the sources' positions at the start of the tick
+        let gravity = GravityField::at_tick(
+            tick - 1,
+            sources.iter().filter_map(|(e, source)| {
+                query
+                    .get(*e)
+                    .ok()
+                    .map(|(e, _, timeline)| (e, source, timeline))
+            }),
+        );
+        // Prediction stays exact near the selected craft
+        let focus = selected
+            .as_ref()
+            .and_then(|selected| query.get(selected.0).ok())
+            .and_then(|(_, _, timeline)| timeline.state(tick - 1))
+            .map(Cow::into_owned);
+
+        // Integrate every invalid entity, along with whatever is beam-linked
+        // to it since beams pull on both ends. Linked islands can't affect
+        // each other within the tick, so they are integrated in parallel
+        let (groups, mut rejected_connects) =
+            beam_groups(tick, &query, invalid_set);
+        let mut islands = groups
+            .iter()
+            .map(|group| {
+                let mut island =
+                    take_island(group, &mut query, &colliders, &materials);
+                let alone = island.bodies.len() == 1;
+                for body in &mut island.bodies {
+                    if let Some(rejected) =
+                        rejected_connects.remove(&body.entity)
+                    {
+                        body.integrate = true;
+                        body.rejected_connects = rejected;
+                    }
+                    if alone && !refinements.contains_key(&body.entity) {
+                        body.coarse_step = coarse_step(
+                            tick,
+                            &sim_config,
+                            policies.get(body.entity).ok(),
+                            &body.timeline,
+                            focus.as_ref(),
+                            sources.iter().any(|(e, _)| *e == body.entity),
+                        );
+                    }
+                }
+                island
+            })
+            .collect::<Vec<_>>();
+        let config = &*sim_config;
+        for_each_parallel(&mut islands, config.parallel_islands, |island| {
+            island.integrate(tick, config, &gravity, bounds)
+        });
+        for island in islands {
+            for body in island.bodies.iter().filter(|b| b.coarse_step > 1) {
+                coarse_steps.insert(body.entity, tick + body.coarse_step - 1);
+            }
+            island.restore(tick, &mut query, &mut spatial_index);
+        }
 
-            // Add to invalid set if not already present
-            invalid_set.entry(connected_entity).or_insert(tick);
+        for (entity, start_tick) in stepping {
+            invalid_set.insert(entity, start_tick);
+            let (_, collider, timeline) = query.get(entity).unwrap();
+            let state = timeline.state(tick).unwrap();
+            let prev_pos = timeline.state(tick - 1).unwrap().pos;
+            let prev_pos =
+                sweep_start(bounds, prev_pos.to_vec2(), state.pos.to_vec2());
+            if state.alive {
+                spatial_index.insert(
+                    tick,
+                    collider,
+                    SpatialItem::swept(entity, prev_pos, &state),
+                );
+            }
         }
 
+        let context = CollisionContext {
+            sim_config: config,
+            bounds,
+            colliders: &colliders,
+            materials: &materials,
+        };
         resolve_collisions(
             tick,
-            seconds_per_tick,
+            &context,
             &mut spatial_index,
             &mut query,
-            &mut invalid_set,
+            invalid_set,
         );
+
+        // A coarse step hit part way through starts over from the hit
+        coarse_steps.retain(|entity, _| {
+            query
+                .get(*entity)
+                .is_ok_and(|(_, _, tl)| !tl.sim_events.contains_key(&tick))
+        });
+
+        finish_refinements(tick, refinements, &mut query, invalid_set);
     }
 
     for (entity, start_tick) in invalid_set.drain() {
         let (_, _, mut timeline) = query.get_mut(entity).unwrap();
-        timeline.last_updated_range = Some(start_tick..=end_tick);
+        finish_timeline(entity, start_tick, &mut timeline, &mut spatial_index);
     }
 }
 
-/// Add sim_events to invalid set
-/// Note: when more than one sim_event per tick is supported, this must
-/// be done iteratively
-fn invalidate_sim_events(
-    query: &mut Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
-    invalid_set: &mut EntityHashMap<u64>,
-    entities_to_invalidate: &mut Vec<Entity>,
+/// Coarse states of an entity being recomputed one tick at a time, as they
+/// come within its `PredictionPolicy::exact_ticks`
+///
+/// Only the coarse step they are in is recomputed, the rest is kept
+struct Refinement {
+    /// First coarse tick
+    from_tick: u64,
+    /// Last tick of the coarse step
+    stop_tick: u64,
+    /// `Timeline::last_computed_tick` before the refinement
+    last_computed_tick: u64,
+    /// States after the coarse step
+    tail: Timeline,
+}
+
+/// Set aside the states after the next coarse step of `timeline` if it has
+/// come within the policy's exact ticks
+///
+/// Steps with collisions are recomputed along with everything after them
+fn start_refinement(
+    sim_config: &SimulationConfig,
+    policy: Option<&PredictionPolicy>,
+    timeline: &mut Timeline,
+) -> Option<Refinement> {
+    let from_tick = timeline.coarse_from?;
+    let exact_end = sim_config.current_tick + policy?.exact_ticks.max(1);
+    if from_tick > exact_end || from_tick > timeline.last_computed_tick {
+        return None;
+    }
+    let (&stop_tick, _) = timeline.future_states.range(from_tick..).next()?;
+    if timeline
+        .sim_events
+        .range(from_tick..=stop_tick)
+        .next()
+        .is_some()
+    {
+        return None;
+    }
+    Some(Refinement {
+        from_tick,
+        stop_tick,
+        last_computed_tick: timeline.last_computed_tick,
+        tail: timeline.split_states_off(stop_tick + 1),
+    })
+}
+
+/// Put back the rest of the prediction of entities whose refinement ends at
+/// `tick`, unless they hit something along the way
+fn finish_refinements(
     tick: u64,
+    refinements: &mut EntityHashMap<Refinement>,
+    query: &mut Query<(Entity, &Collider, &mut Timeline)>,
+    invalid_set: &mut EntityHashMap<u64>,
 ) {
-    for &entity in invalid_set.keys() {
-        let (_, _, mut timeline) = query.get_mut(entity).unwrap();
-        if let Some(event) = timeline.sim_events.remove(&tick) {
-            entities_to_invalidate.push(event.other);
-        }
-    }
-    for entity in entities_to_invalidate.drain(..) {
+    let finished = refinements
+        .iter()
+        .filter(|(_, refinement)| refinement.stop_tick == tick)
+        .map(|(entity, _)| *entity)
+        .collect::<Vec<_>>();
+    for entity in finished {
+        let refinement = refinements.remove(&entity).unwrap();
+        let Some(&start_tick) = invalid_set.get(&entity) else {
+            continue;
+        };
         let Ok((_, _, mut timeline)) = query.get_mut(entity) else {
-            warn!("Entity not found in query");
             continue;
         };
-        // Remove sim event from other entity
-        timeline.sim_events.remove(&tick);
-        // Add to invalid set if not already present
-        invalid_set.entry(entity).or_insert(tick);
+        // Invalidated for another reason, or the refined path hit something
+        if start_tick < refinement.from_tick
+            || timeline
+                .sim_events
+                .range(start_tick..=tick)
+                .next()
+                .is_some()
+        {
+            continue;
+        }
+        invalid_set.remove(&entity);
+        timeline.append_states(refinement.tail);
+        timeline.last_computed_tick = refinement.last_computed_tick;
+        timeline.coarse_from =
+            (tick < refinement.last_computed_tick).then_some(tick + 1);
+        timeline.last_updated_range = Some(start_tick..=tick);
     }
 }
 
-pub fn apply_inputs_and_integrate_phys(
+/// Ticks to integrate `timeline` over at once from `tick`
+///
+/// 1 unless its policy allows coarse steps there and it is away from the
+/// `focus` state of the selected craft. Gravity sources and entities with
+/// elastic beams are always integrated every tick, and steps end before the
+/// next input so that it lands on the first tick of a step
+fn coarse_step(
     tick: u64,
-    seconds_per_tick: f32,
+    sim_config: &SimulationConfig,
+    policy: Option<&PredictionPolicy>,
+    timeline: &Timeline,
+    focus: Option<&PhysicsState>,
+    is_source: bool,
+) -> u64 {
+    let Some(policy) = policy.filter(|policy| policy.coarse_step > 1) else {
+        return 1;
+    };
+    if is_source || tick <= sim_config.current_tick + policy.exact_ticks.max(1)
+    {
+        return 1;
+    }
+    let Some(prev) = timeline.state(tick - 1) else {
+        return 1;
+    };
+    if !prev.alive || !prev.elastic_beams.is_empty() {
+        return 1;
+    }
+    if focus.is_some_and(|focus| {
+        focus.pos.to_vec2().distance(prev.pos.to_vec2()) <= policy.focus_radius
+    }) {
+        return 1;
+    }
+    timeline
+        .input_events
+        .range(tick + 1..)
+        .next()
+        .map_or(policy.coarse_step, |(next, _)| {
+            (next - tick).min(policy.coarse_step)
+        })
+}
+
+/// Record the range of `timeline` recomputed from `start_tick`, dropping
+/// any states left over past its last computed tick
+fn finish_timeline(
     entity: Entity,
+    start_tick: u64,
     timeline: &mut Timeline,
-    collider: &Collider,
-    spatial_index: Option<&mut SpatialIndex>,
+    spatial_index: &mut SpatialIndex,
 ) {
-    // clear sim events since these should be regenerated
-    timeline.sim_events.remove(&tick);
-
-    let mut state = timeline
-        .state(tick - 1)
-        .expect(
-            "Previous tick's state must exist bc of last_updated_sets \
-             invariant",
-        )
-        .clone();
-
-    let event = timeline.input_events.get(&tick);
-
-    // Apply control input events
-    state.apply_input_event(event);
-
-    // Integrate physics
-    state = state.integrate(seconds_per_tick);
+    let last_tick = timeline.last_computed_tick;
+    let stale = timeline.split_states_off(last_tick + 1);
+    for (tick, _) in stale.states() {
+        spatial_index.remove(tick, &entity);
+    }
+    if timeline.coarse_from.is_some_and(|from| from > last_tick) {
+        timeline.coarse_from = None;
+    }
+    timeline.last_updated_range = Some(start_tick..=last_tick);
+}
 
-    if state.alive {
-        if let Some(spatial_index) = spatial_index {
-            spatial_index.insert(
-                tick,
-                collider,
-                SpatialItem::from_state(entity, &state),
-            );
+/// Entities joined by an elastic beam at the start or (as last computed) end
+/// of `tick`, in both directions
+fn beam_partners(
+    query: &Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
+    tick: u64,
+) -> EntityHashMap<Vec<Entity>> {
+    let mut partners = EntityHashMap::<Vec<Entity>>::default();
+    for (entity, _, timeline) in query.iter() {
+        let states = [timeline.state(tick - 1), timeline.state(tick)];
+        for state in states.into_iter().flatten() {
+            for beam in &state.elastic_beams {
+                if let BeamAnchor::Entity(partner) = beam.anchor {
+                    partners.entry(entity).or_default().push(partner);
+                    partners.entry(partner).or_default().push(entity);
+                }
+            }
         }
     }
-    timeline.future_states.insert(tick, state);
-    timeline.last_computed_tick = tick;
+    partners
 }
 
-fn resolve_collisions(
+/// Group the entities to integrate at `tick` into islands linked by elastic
+/// beams
+///
+/// The invalid set is first closed over beam links, since beams pull on both
+/// ends. Returns the groups along with the rejected beam connects of every
+/// entity to integrate. Entities that don't exist yet at the start of the tick
+/// are dropped from the invalid set, but stay in the group of any beam
+/// pointing at them
+fn beam_groups(
     tick: u64,
-    seconds_per_tick: f32,
-    spatial_index: &mut SpatialIndex,
-    query: &mut Query<(Entity, &Collider, &mut Timeline)>,
+    query: &Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
     invalid_set: &mut EntityHashMap<u64>,
-) {
-    // Gather collision pairs
-    let mut collisions: HashSet<InteractionGroup> = default();
-    for &entity in invalid_set.keys() {
-        let (_, collider, timeline) = query.get(entity).unwrap();
-        let state = timeline.state(tick).expect("Just added");
+) -> (Vec<Vec<Entity>>, EntityHashMap<Vec<ControlInput>>) {
+    let beam_partners = beam_partners(query, tick);
+    let mut pending = invalid_set.keys().copied().collect::<Vec<_>>();
+    let mut visited = EntityHashSet::default();
+    let mut members = Vec::new();
+    let mut links = Vec::new();
+    let mut to_integrate = EntityHashMap::default();
+    while let Some(entity) = pending.pop() {
+        if !visited.insert(entity) {
+            continue;
+        }
+        let Ok((_, _, timeline)) = query.get(entity) else {
+            continue;
+        };
+        members.push(entity);
+        let Some(prev_state) = timeline.state(tick - 1) else {
+            // Spawned ahead of time (e.g. collision fragments) and doesn't
+            // exist yet. It joins once its first tick is reached
+            invalid_set.remove(&entity);
+            continue;
+        };
+        invalid_set.entry(entity).or_insert(tick);
 
-        if let Some(collision) =
-            spatial_index.collides(entity, tick, state.pos, collider)
+        let rejected = rejected_beam_connects(tick, entity, query);
+        let mut partners =
+            beam_partners.get(&entity).cloned().unwrap_or_default();
+        // Beams connected at this tick link their anchors too, unless the
+        // connect is rejected
+        let inputs = timeline.inputs(tick);
+        if inputs
+            .iter()
+            .any(|input| matches!(input, ControlInput::ElasticBeamConnect(..)))
         {
-            collisions.insert((collision.1.entity, entity).into());
-        };
+            let mut state = prev_state.into_owned();
+            state.apply_input_events(inputs);
+            partners.extend(
+                state
+                    .elastic_beams
+                    .iter()
+                    .filter(|beam| !rejected_anchor(&rejected, beam.anchor))
+                    .filter_map(|beam| match beam.anchor {
+                        BeamAnchor::Entity(partner) => Some(partner),
+                        BeamAnchor::Fixed(_) => None,
+                    }),
+            );
+        }
+        for partner in partners {
+            links.push((entity, partner));
+            pending.push(partner);
+        }
+        to_integrate.insert(entity, rejected);
     }
+    (connected_groups(members, &links), to_integrate)
+}
 
-    // Resolve broad-phase collisions
-    for group in collisions {
-        let [mut a, mut b] = match query.get_many_mut(group.0) {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("Error getting entities for collision group: {e:?}");
-                continue;
-            }
-        };
+/// Split `entities` into groups connected by `links`, each sorted and the
+/// groups ordered by their first entity
+/// Links to entities outside of `entities` are ignored
+fn connected_groups(
+    entities: impl IntoIterator<Item = Entity>,
+    links: &[(Entity, Entity)],
+) -> Vec<Vec<Entity>> {
+    fn root(parent: &mut EntityHashMap<Entity>, mut entity: Entity) -> Entity {
+        while parent[&entity] != entity {
+            let grandparent = parent[&parent[&entity]];
+            parent.insert(entity, grandparent);
+            entity = grandparent;
+        }
+        entity
+    }
 
-        resolve_collision(
-            tick,
-            (a.0, a.1, &mut a.2),
-            (b.0, b.1, &mut b.2),
-            seconds_per_tick,
-            spatial_index,
-        );
+    let mut parent = entities
+        .into_iter()
+        .map(|entity| (entity, entity))
+        .collect::<EntityHashMap<_>>();
+    for &(a, b) in links {
+        if !parent.contains_key(&a) || !parent.contains_key(&b) {
+            continue;
+        }
+        let (a, b) = (root(&mut parent, a), root(&mut parent, b));
+        parent.insert(a.max(b), a.min(b));
+    }
 
-        // All collision participants are invalidated
-        group.0.into_iter().for_each(|e| {
-            invalid_set.entry(e).or_insert(tick);
-        });
+    let mut groups = EntityHashMap::<Vec<Entity>>::default();
+    let entities = parent.keys().copied().collect::<Vec<_>>();
+    for entity in entities {
+        let root = root(&mut parent, entity);
+        groups.entry(root).or_default().push(entity);
     }
+    let mut groups = groups
+        .into_values()
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|group| group[0]);
+    groups
 }
 
-fn resolve_collision(
-    tick: u64,
-    (a_e, a_col, a_tl): (Entity, &Collider, &mut Timeline),
-    (b_e, b_col, b_tl): (Entity, &Collider, &mut Timeline),
-    seconds_per_tick: f32,
-    spatial_index: &mut SpatialIndex,
+/// Run `f` on every item, spread over the compute task pool if `parallel`
+///
+/// The calling thread works through the chunks alongside the pool, so items
+/// are split in at least two even when the pool has a single thread
+fn for_each_parallel<T: Send>(
+    items: &mut [T],
+    parallel: bool,
+    f: impl Fn(&mut T) + Sync,
 ) {
-    // STEP 1: unpack state
-    let a_st = a_tl.future_states.get_mut(&tick).unwrap();
-    let b_st = b_tl.future_states.get_mut(&tick).unwrap();
+    if !parallel || items.len() < 2 {
+        items.iter_mut().for_each(f);
+        return;
+    }
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let chunk_size = items.len().div_ceil(pool.thread_num().max(2));
+    let f = &f;
+    pool.scope(|scope| {
+        for chunk in items.chunks_mut(chunk_size) {
+            scope.spawn(async move { chunk.iter_mut().for_each(f) });
+        }
+    });
+}
 
-    // STEP 2: check for interaction
-    if let Some(_) = spatial_index.collides(a_e, tick, a_st.pos, a_col) {
-        // STEP 3: resolve interaction
-        let (a_result, b_result) = calculate_collision_result(
-            &SpatialItem::from_state(a_e, a_st),
-            &SpatialItem::from_state(b_e, b_st),
-        );
+/// Entities computed together at a tick, because elastic beams or collisions
+/// couple them
+///
+/// Islands share no entities, so computing them in parallel gives the same
+/// results as computing them one after another. Their timelines are moved out
+/// of the world while they are computed
+struct Island<'a> {
+    /// Sorted by entity
+    bodies: Vec<IslandBody<'a>>,
+    /// Collisions to resolve, in order
+    collisions: Vec<InteractionGroup>,
+    /// Changes to the spatial index at the tick, applied in order once the
+    /// island is done
+    index_updates: Vec<IndexUpdate<'a>>,
+}
 
-        a_st.apply_collision_result(&a_result);
-        b_st.apply_collision_result(&b_result);
+struct IslandBody<'a> {
+    entity: Entity,
+    collider: &'a Collider,
+    material: Material,
+    timeline: Timeline,
+    /// Whether to integrate the body, false if it doesn't exist yet
+    integrate: bool,
+    /// Beam connects at the tick that can't reach their anchor
+    rejected_connects: Vec<ControlInput>,
+    /// Ticks to integrate over at once, see `PredictionPolicy`
+    coarse_step: u64,
+}
 
-        match &a_result {
-            EntityCollisionResult::Destroyed => {
-                spatial_index.remove(tick, &a_e)
-            }
-            EntityCollisionResult::Survives { .. } => {
-                spatial_index.insert(
-                    tick,
-                    a_col,
-                    SpatialItem::from_state(a_e, a_st),
-                );
-            }
-        }
-        match &b_result {
-            EntityCollisionResult::Destroyed => {
-                spatial_index.remove(tick, &b_e)
-            }
-            EntityCollisionResult::Survives { .. } => {
-                spatial_index.insert(
-                    tick,
-                    a_col,
-                    SpatialItem::from_state(b_e, b_st),
-                );
-            }
-        }
+/// Item to index for an entity, or None to remove it from the index
+type IndexUpdate<'a> = (Entity, &'a Collider, Option<SpatialItem>);
+
+/// Move the timelines of `entities` out of the world into an island
+/// Entities without a timeline are left out
+fn take_island<'a>(
+    entities: &[Entity],
+    query: &mut Query<(Entity, &Collider, &mut Timeline)>,
+    colliders: &'a Query<&Collider>,
+    materials: &Query<&Material>,
+) -> Island<'a> {
+    let bodies = entities
+        .iter()
+        .filter_map(|&entity| {
+            let collider = colliders.get(entity).ok()?;
+            let (_, _, mut timeline) = query.get_mut(entity).ok()?;
+            Some(IslandBody {
+                entity,
+                collider,
+                material: materials.get(entity).copied().unwrap_or_default(),
+                timeline: std::mem::take(&mut *timeline),
+                integrate: false,
+                rejected_connects: Vec::new(),
+                coarse_step: 1,
+            })
+        })
+        .collect();
+    Island {
+        bodies,
+        collisions: Vec::new(),
+        index_updates: Vec::new(),
+    }
+}
 
-        a_tl.sim_events.insert(tick, Collision { other: b_e });
-        b_tl.sim_events.insert(tick, Collision { other: a_e });
+/// Index of `entity` in `bodies`
+fn find_body(bodies: &[IslandBody], entity: Entity) -> Option<usize> {
+    bodies
+        .binary_search_by_key(&entity, |body| body.entity)
+        .ok()
+}
 
-        a_tl.last_computed_tick = tick;
-        b_tl.last_computed_tick = tick;
+/// Two distinct bodies at once
+fn body_pair<'a, 'b>(
+    bodies: &'b mut [IslandBody<'a>],
+    [a, b]: [Entity; 2],
+) -> Option<[&'b mut IslandBody<'a>; 2]> {
+    let (a, b) = (find_body(bodies, a)?, find_body(bodies, b)?);
+    if a == b {
+        return None;
     }
+    let (low, high) = bodies.split_at_mut(a.max(b));
+    let (low, high) = (&mut low[a.min(b)], &mut high[0]);
+    Some(if a < b { [low, high] } else { [high, low] })
 }
 
-/// Reset last_updated_sets and invalid_set
-/// This is used to reduce allocations when computing future states
+impl Island<'_> {
+    fn timeline_mut(&mut self, entity: Entity) -> Option<&mut Timeline> {
+        let index = find_body(&self.bodies, entity)?;
+        Some(&mut self.bodies[index].timeline)
+    }
+
+    /// Apply inputs and integrate every body to integrate, then apply beam
+    /// forces in a fixed order since beams sharing an entity affect each
+    /// other
+    fn integrate(
+        &mut self,
+        tick: u64,
+        sim_config: &SimulationConfig,
+        gravity: &GravityField,
+        bounds: Option<&ArenaBounds>,
+    ) {
+        let mut beams = Vec::new();
+        for body in self.bodies.iter_mut().filter(|body| body.integrate) {
+            let timeline = &mut body.timeline;
+            if body.coarse_step > 1 {
+                integrate_coarse_step(
+                    tick,
+                    body.coarse_step,
+                    sim_config,
+                    gravity,
+                    body.entity,
+                    timeline,
+                );
+            } else {
+                apply_inputs_and_integrate_phys(
+                    tick,
+                    sim_config,
+                    gravity,
+                    body.entity,
+                    timeline,
+                    body.collider,
+                    None,
+                );
+            }
+            if let Some(bounds) = bounds {
+                bounds.apply_to_timeline(tick + body.coarse_step - 1, timeline);
+            }
+            if !body.rejected_connects.is_empty() {
+                let rejected = &body.rejected_connects;
+                let state = timeline.state_mut(tick).unwrap();
+                state
+                    .elastic_beams
+                    .retain(|beam| !rejected_anchor(rejected, beam.anchor));
+                timeline
+                    .rejected_connects
+                    .insert(tick, std::mem::take(&mut body.rejected_connects));
+            }
+            let state = timeline.state(tick).unwrap();
+            let prev_pos = timeline.state(tick - 1).unwrap().pos;
+            let prev_pos =
+                sweep_start(bounds, prev_pos.to_vec2(), state.pos.to_vec2());
+            if state.alive {
+                let item = SpatialItem::swept(body.entity, prev_pos, &state);
+                self.index_updates.push((
+                    body.entity,
+                    body.collider,
+                    Some(item),
+                ));
+            }
+            for (index, beam) in state.elastic_beams.iter().enumerate() {
+                beams.push((body.entity, index, beam.clone()));
+            }
+        }
+
+        beams.sort_by_key(|(owner, index, _)| (*owner, *index));
+        for (owner, _, beam) in beams {
+            if let Err(reason) = self.integrate_beam_joint(
+                tick,
+                owner,
+                &beam,
+                sim_config.seconds_per_tick(),
+                sim_config.integrator,
+            ) {
+                self.break_beam(tick, owner, &beam, reason);
+            }
+        }
+    }
+
+    /// Apply the force of `owner`'s beam at `tick` to both ends
+    fn integrate_beam_joint(
+        &mut self,
+        tick: u64,
+        owner: Entity,
+        beam: &ElasticBeamInfo,
+        seconds_per_tick: f32,
+        integrator: Integrator,
+    ) -> Result<(), BeamBreakReason> {
+        let intact = match beam.anchor {
+            BeamAnchor::Fixed(_) => {
+                let timeline = self.timeline_mut(owner).unwrap();
+                let state = timeline.state_mut(tick).unwrap();
+                if !state.alive {
+                    return Err(BeamBreakReason::EndpointLost);
+                }
+                state.integrate_beam(beam, None, seconds_per_tick, integrator)
+            }
+            BeamAnchor::Entity(partner) => {
+                let Some([body, partner_body]) =
+                    body_pair(&mut self.bodies, [owner, partner])
+                else {
+                    return Err(BeamBreakReason::EndpointLost);
+                };
+                let state = body.timeline.state_mut(tick).unwrap();
+                let Some(partner_state) = partner_body.timeline.state_mut(tick)
+                else {
+                    return Err(BeamBreakReason::EndpointLost);
+                };
+                if !state.alive || !partner_state.alive {
+                    return Err(BeamBreakReason::EndpointLost);
+                }
+                state.integrate_beam(
+                    beam,
+                    Some(partner_state),
+                    seconds_per_tick,
+                    integrator,
+                )
+            }
+        };
+        if intact {
+            Ok(())
+        } else {
+            Err(BeamBreakReason::Overstretched)
+        }
+    }
+
+    /// Remove `owner`'s beam from `tick` on and record it on both ends
+    fn break_beam(
+        &mut self,
+        tick: u64,
+        owner: Entity,
+        beam: &ElasticBeamInfo,
+        reason: BeamBreakReason,
+    ) {
+        let broken = BeamBroken {
+            owner,
+            anchor: beam.anchor,
+            reason,
+        };
+        if let BeamAnchor::Entity(partner) = beam.anchor {
+            if let Some(partner_tl) = self.timeline_mut(partner) {
+                partner_tl
+                    .broken_beams
+                    .entry(tick)
+                    .or_default()
+                    .push(broken.clone());
+            }
+        }
+        let timeline = self.timeline_mut(owner).unwrap();
+        timeline
+            .state_mut(tick)
+            .unwrap()
+            .elastic_beams
+            .retain(|b| b.anchor != beam.anchor);
+        timeline.broken_beams.entry(tick).or_default().push(broken);
+    }
+
+    /// Resolve the island's collisions in order
+    fn resolve_collisions(
+        &mut self,
+        tick: u64,
+        seconds_per_tick: f32,
+        bounds: Option<&ArenaBounds>,
+    ) {
+        for group in std::mem::take(&mut self.collisions) {
+            let Some([a, b]) = body_pair(&mut self.bodies, group.0) else {
+                continue;
+            };
+            resolve_collision(
+                tick,
+                (a.entity, a.collider, &a.material, &mut a.timeline),
+                (b.entity, b.collider, &b.material, &mut b.timeline),
+                seconds_per_tick,
+                bounds,
+                &mut self.index_updates,
+            );
+        }
+    }
+
+    /// Move the timelines back into the world and apply the index changes
+    fn restore(
+        self,
+        tick: u64,
+        query: &mut Query<(Entity, &Collider, &mut Timeline)>,
+        spatial_index: &mut SpatialIndex,
+    ) {
+        for body in self.bodies {
+            if let Ok((_, _, mut timeline)) = query.get_mut(body.entity) {
+                *timeline = body.timeline;
+            }
+        }
+        for (entity, collider, item) in self.index_updates {
+            match item {
+                Some(item) => spatial_index.insert(tick, collider, item),
+                None => spatial_index.remove(tick, &entity),
+            }
+        }
+    }
+}
+
+/// Beam connect inputs at `tick` that can't reach their anchor
+///
+/// Range is checked between the positions at the start of the tick, when the
+/// inputs are applied
+fn rejected_beam_connects(
+    tick: u64,
+    entity: Entity,
+    query: &Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
+) -> Vec<ControlInput> {
+    let Ok((_, _, timeline)) = query.get(entity) else {
+        return Vec::new();
+    };
+    let start_pos = |e| {
+        query
+            .get(e)
+            .ok()
+            .and_then(|(_, _, timeline)| timeline.state(tick - 1))
+            .map(|state| state.pos.to_vec2())
+    };
+    timeline
+        .inputs(tick)
+        .iter()
+        .filter_map(|input| match input {
+            ControlInput::ElasticBeamConnect(anchor, params) => {
+                Some((*input, *anchor, params.max_length))
+            }
+            _ => None,
+        })
+        .filter(|(_, anchor, max_length)| {
+            let anchor_pos = match anchor {
+                BeamAnchor::Entity(partner) => start_pos(*partner),
+                BeamAnchor::Fixed(pos) => Some(*pos),
+            };
+            !matches!(
+                (start_pos(entity), anchor_pos),
+                (Some(a), Some(b)) if a.distance(b) <= *max_length
+            )
+        })
+        .map(|(input, ..)| input)
+        .collect()
+}
+
+/// Whether one of the `rejected` connects is for `anchor`
+fn rejected_anchor(rejected: &[ControlInput], anchor: BeamAnchor) -> bool {
+    rejected.iter().any(|input| {
+        matches!(input, ControlInput::ElasticBeamConnect(a, _) if *a == anchor)
+    })
+}
+
+/// Add entities pulled by a gravity source that is being recomputed this tick
+/// to the invalid set
+///
+/// A source without a cutoff affects every entity. Otherwise the spatial index
+/// from the previous tick is searched for entities within the cutoff.
+fn invalidate_gravity_dependents(
+    query: &Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
+    sources: &[(Entity, GravitySource)],
+    spatial_index: &SpatialIndex,
+    invalid_set: &mut EntityHashMap<u64>,
+    tick: u64,
+) {
+    for (source_e, source) in sources {
+        if !invalid_set.contains_key(source_e) {
+            continue;
+        }
+        let Some(cutoff) = source.cutoff else {
+            for (entity, _, _) in query.iter() {
+                invalid_set.entry(entity).or_insert(tick);
+            }
+            return;
+        };
+        let Some(source_state) = query
+            .get(*source_e)
+            .ok()
+            .and_then(|(_, _, timeline)| timeline.state(tick - 1))
+        else {
+            continue;
+        };
+
+        let range = BRect::from_center_half_size(
+            source_state.pos.to_vec2(),
+            Vec2::splat(cutoff),
+        );
+        for entity in spatial_index.entities_within(tick - 1, range) {
+            invalid_set.entry(entity).or_insert(tick);
+        }
+    }
+}
+
+/// Add sim_events to invalid set
+/// Recomputing an entity drops all of its collisions at `tick`, so everything
+/// it collided with has to be recomputed too, which in turn pulls in whatever
+/// those collided with
+fn invalidate_sim_events(
+    query: &mut Query<'_, '_, (Entity, &Collider, &mut Timeline)>,
+    invalid_set: &mut EntityHashMap<u64>,
+    entities_to_invalidate: &mut Vec<Entity>,
+    tick: u64,
+) {
+    entities_to_invalidate.extend(invalid_set.keys());
+    while let Some(entity) = entities_to_invalidate.pop() {
+        let Ok((_, _, mut timeline)) = query.get_mut(entity) else {
+            warn!("Entity not found in query");
+            continue;
+        };
+        // Add to invalid set if not already present
+        invalid_set.entry(entity).or_insert(tick);
+        // Each event is removed once, so this terminates
+        if let Some(events) = timeline.sim_events.remove(&tick) {
+            entities_to_invalidate.extend(events.iter().map(|e| e.other));
+        }
+    }
+}
+
+pub fn apply_inputs_and_integrate_phys(
+    tick: u64,
+    sim_config: &SimulationConfig,
+    gravity: &GravityField,
+    entity: Entity,
+    timeline: &mut Timeline,
+    collider: &Collider,
+    spatial_index: Option<&mut SpatialIndex>,
+) {
+    // clear sim events since these should be regenerated
+    timeline.sim_events.remove(&tick);
+    timeline.broken_beams.remove(&tick);
+    timeline.rejected_connects.remove(&tick);
+    timeline.fragmentations.remove(&tick);
+
+    let mut state = timeline
+        .state(tick - 1)
+        .expect(
+            "Previous tick's state must exist bc of last_updated_sets \
+             invariant",
+        )
+        .into_owned();
+
+    let prev_pos = state.pos.to_vec2();
+    let seconds_per_tick = sim_config.seconds_per_tick();
+
+    // Drifting entities aren't integrated or stored every tick, their states
+    // are extrapolated from where the drift started
+    let inert = state.is_inert()
+        && timeline.inputs(tick).is_empty()
+        && !gravity.reaches(
+            entity,
+            state.pos.to_vec2(),
+            state.vel.to_vec2(),
+            seconds_per_tick,
+        );
+    if inert {
+        timeline.coast_to(tick, seconds_per_tick);
+    } else {
+        // Apply control input events
+        state.apply_input_events(timeline.inputs(tick));
+
+        // Integrate physics
+        state = state.integrate_with(
+            seconds_per_tick,
+            sim_config.integrator,
+            |t, pos| gravity.acceleration(entity, t, pos),
+        );
+        timeline.set_state(tick, state);
+    }
+
+    if let Some(spatial_index) = spatial_index {
+        let state = timeline.state(tick).unwrap();
+        if state.alive {
+            spatial_index.insert(
+                tick,
+                collider,
+                SpatialItem::swept(entity, prev_pos, &state),
+            );
+        }
+    }
+    // Every state from here on is recomputed, one tick at a time so far
+    if timeline.coarse_from.is_some_and(|from| from <= tick) {
+        timeline.coarse_from = None;
+    }
+    timeline.last_computed_tick = tick;
+}
+
+/// Apply the inputs at `tick` and integrate over the `ticks` ticks from it at
+/// once, see `PredictionPolicy`
+///
+/// Drifting entities coast through the step instead, which is exact
+pub fn integrate_coarse_step(
+    tick: u64,
+    ticks: u64,
+    sim_config: &SimulationConfig,
+    gravity: &GravityField,
+    entity: Entity,
+    timeline: &mut Timeline,
+) {
+    let end_tick = tick + ticks - 1;
+    // Any coarse states from here on are recomputed
+    if timeline.coarse_from.is_some_and(|from| from >= tick) {
+        timeline.coarse_from = None;
+    }
+    for tick in tick..=end_tick {
+        timeline.sim_events.remove(&tick);
+        timeline.broken_beams.remove(&tick);
+        timeline.rejected_connects.remove(&tick);
+        timeline.fragmentations.remove(&tick);
+    }
+
+    let mut state = timeline
+        .state(tick - 1)
+        .expect("Coarse steps start from the previous tick's state")
+        .into_owned();
+    let seconds_per_tick = sim_config.seconds_per_tick();
+    let seconds = ticks as f32 * seconds_per_tick;
+
+    let inert = state.is_inert()
+        && timeline.inputs(tick).is_empty()
+        && !gravity.reaches(
+            entity,
+            state.pos.to_vec2(),
+            state.vel.to_vec2(),
+            seconds,
+        );
+    if inert {
+        for tick in tick..=end_tick {
+            timeline.coast_to(tick, seconds_per_tick);
+        }
+    } else {
+        state.apply_input_events(timeline.inputs(tick));
+        state =
+            state.integrate_with(seconds, sim_config.integrator, |t, pos| {
+                gravity.acceleration(entity, t, pos)
+            });
+        timeline.set_coarse_step(tick, ticks, state, seconds_per_tick);
+    }
+    timeline.last_computed_tick = end_tick;
+}
+
+/// What collisions are resolved against that stays the same for every tick
+struct CollisionContext<'a, 'w, 's> {
+    sim_config: &'a SimulationConfig,
+    bounds: Option<&'a ArenaBounds>,
+    colliders: &'a Query<'w, 's, &'static Collider>,
+    materials: &'a Query<'w, 's, &'static Material>,
+}
+
+/// Resolve the collisions of the invalid entities at `tick`
+///
+/// Entities that hit each other form islands, which are resolved in parallel
+/// since collisions in one island can't affect another
+fn resolve_collisions(
+    tick: u64,
+    context: &CollisionContext,
+    spatial_index: &mut SpatialIndex,
+    query: &mut Query<(Entity, &Collider, &mut Timeline)>,
+    invalid_set: &mut EntityHashMap<u64>,
+) {
+    let CollisionContext {
+        sim_config,
+        bounds,
+        colliders,
+        materials,
+    } = *context;
+    // Sweep every live invalid entity through the index
+    let mut sweeps = Vec::new();
+    for &entity in invalid_set.keys() {
+        let (_, _, timeline) = query.get(entity).unwrap();
+        let state = timeline.state(tick).expect("Just added");
+        if !state.alive {
+            continue;
+        }
+        let pos = state.pos.to_vec2();
+        let prev_pos = timeline
+            .state(tick - 1)
+            .map_or(pos, |prev| prev.pos.to_vec2());
+        let prev_pos = sweep_start(bounds, prev_pos, pos);
+        sweeps.push((
+            entity,
+            colliders.get(entity).unwrap(),
+            prev_pos,
+            pos,
+            state.rotation.to_f32(),
+            Vec::new(),
+        ));
+    }
+    let index = &*spatial_index;
+    for_each_parallel(
+        &mut sweeps,
+        sim_config.parallel_islands,
+        |(entity, collider, from, to, rotation, hits)| {
+            *hits =
+                index.sweep_all(*entity, tick, *from, *to, *rotation, collider);
+        },
+    );
+
+    // Gather collision pairs, with the earliest time of impact for each
+    let mut collisions: HashMap<InteractionGroup, f32> = default();
+    for (entity, _, _, _, _, hits) in &sweeps {
+        for (toi, other) in hits {
+            let earliest = collisions
+                .entry((other.entity, *entity).into())
+                .or_insert(*toi);
+            *earliest = earliest.min(*toi);
+        }
+    }
+
+    // Resolve broad-phase collisions in order of impact, so pileups play out
+    // the same way whatever order the entities were visited in
+    let mut collisions = collisions
+        .into_iter()
+        .map(|(group, toi)| (toi, group))
+        .collect::<Vec<_>>();
+    collisions.sort_by(|(a_toi, a), (b_toi, b)| {
+        a_toi.total_cmp(b_toi).then(a.cmp(b))
+    });
+
+    let links = collisions
+        .iter()
+        .map(|(_, group)| (group.0[0], group.0[1]))
+        .collect::<Vec<_>>();
+    let groups =
+        connected_groups(links.iter().flat_map(|&(a, b)| [a, b]), &links);
+    let mut islands = groups
+        .iter()
+        .map(|group| take_island(group, query, colliders, materials))
+        .collect::<Vec<_>>();
+    let island_of = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(i, group)| group.iter().map(move |&e| (e, i)))
+        .collect::<EntityHashMap<_>>();
+    for (_, group) in collisions {
+        let island = &mut islands[island_of[&group.0[0]]];
+        if group
+            .0
+            .iter()
+            .any(|&e| find_body(&island.bodies, e).is_none())
+        {
+            eprintln!("Error getting entities for collision group: {group:?}");
+            continue;
+        }
+        island.collisions.push(group);
+
+        // All collision participants are invalidated
+        group.0.into_iter().for_each(|e| {
+            invalid_set.entry(e).or_insert(tick);
+        });
+    }
+
+    let seconds_per_tick = sim_config.seconds_per_tick();
+    for_each_parallel(&mut islands, sim_config.parallel_islands, |island| {
+        island.resolve_collisions(tick, seconds_per_tick, bounds)
+    });
+    for island in islands {
+        island.restore(tick, query, spatial_index);
+    }
+}
+
+fn resolve_collision<'a>(
+    tick: u64,
+    (a_e, a_col, a_mat, a_tl): (Entity, &'a Collider, &Material, &mut Timeline),
+    (b_e, b_col, b_mat, b_tl): (Entity, &'a Collider, &Material, &mut Timeline),
+    seconds_per_tick: f32,
+    bounds: Option<&ArenaBounds>,
+    index_updates: &mut Vec<IndexUpdate<'a>>,
+) {
+    // STEP 1: unpack state
+    let (a_item, b_item) = {
+        let a_st = a_tl.state(tick).unwrap();
+        let b_st = b_tl.state(tick).unwrap();
+        if !a_st.alive || !b_st.alive {
+            return;
+        }
+        let a_from = a_tl.state(tick - 1).map_or(a_st.pos, |s| s.pos);
+        let b_from = b_tl.state(tick - 1).map_or(b_st.pos, |s| s.pos);
+        let start = |from: RealVec2, to: RealVec2| {
+            sweep_start(bounds, from.to_vec2(), to.to_vec2())
+        };
+        (
+            SpatialItem::swept(a_e, start(a_from, a_st.pos), &a_st),
+            SpatialItem::swept(b_e, start(b_from, b_st.pos), &b_st),
+        )
+    };
+    if !is_closing(&a_item, &b_item) {
+        return;
+    }
+
+    // STEP 2: check for interaction along both paths through the tick
+    if let Some(contact) = find_contact((&a_item, a_col), (&b_item, b_col)) {
+        // STEP 3: resolve interaction
+        let (a_result, b_result) = calculate_collision_result(
+            (&a_item, a_mat),
+            (&b_item, b_mat),
+            &contact,
+            seconds_per_tick,
+        );
+
+        // Destroyed bodies may break up into fragments
+        let a_debris = match a_result {
+            EntityCollisionResult::Destroyed { post_vel, .. } => {
+                calculate_fragmentation(
+                    (&a_item, a_mat),
+                    &b_item,
+                    post_vel,
+                    a_col.bounding_radius(),
+                )
+            }
+            EntityCollisionResult::Survives { .. } => None,
+        };
+        let b_debris = match b_result {
+            EntityCollisionResult::Destroyed { post_vel, .. } => {
+                calculate_fragmentation(
+                    (&b_item, b_mat),
+                    &a_item,
+                    post_vel,
+                    b_col.bounding_radius(),
+                )
+            }
+            EntityCollisionResult::Survives { .. } => None,
+        };
+
+        // Coasting bodies get a stored state where they are hit
+        let a_st = a_tl.state_mut(tick).unwrap();
+        a_st.apply_collision_result(&a_result);
+        let a_update = match &a_result {
+            EntityCollisionResult::Destroyed { .. } => None,
+            EntityCollisionResult::Survives { .. } => {
+                Some(SpatialItem::swept(a_e, a_item.prev_pos, a_st))
+            }
+        };
+        index_updates.push((a_e, a_col, a_update));
+        let b_st = b_tl.state_mut(tick).unwrap();
+        b_st.apply_collision_result(&b_result);
+        let b_update = match &b_result {
+            EntityCollisionResult::Destroyed { .. } => None,
+            EntityCollisionResult::Survives { .. } => {
+                Some(SpatialItem::swept(b_e, b_item.prev_pos, b_st))
+            }
+        };
+        index_updates.push((b_e, b_col, b_update));
+
+        a_tl.sim_events.entry(tick).or_default().push(Collision {
+            other: b_e,
+            time_of_impact: contact.time_of_impact,
+            damage: a_result.damage(),
+        });
+        b_tl.sim_events.entry(tick).or_default().push(Collision {
+            other: a_e,
+            time_of_impact: contact.time_of_impact,
+            damage: b_result.damage(),
+        });
+
+        if let Some(debris) = a_debris {
+            a_tl.fragmentations.insert(tick, debris);
+        }
+        if let Some(debris) = b_debris {
+            b_tl.fragmentations.insert(tick, debris);
+        }
+
+        a_tl.last_computed_tick = tick;
+        b_tl.last_computed_tick = tick;
+    }
+}
+
+/// Reset last_updated_sets and invalid_set
+/// This is used to reduce allocations when computing future states
 fn reset_last_updated_sets(
     sim_config: &SimulationConfig,
     last_updated_sets: &mut HashMap<u64, EntityHashSet>,
@@ -357,7 +1725,7 @@ fn reset_last_updated_sets(
     invalid_set.clear();
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct InteractionGroup(pub [Entity; 2]);
 
 impl From<(Entity, Entity)> for InteractionGroup {
@@ -371,10 +1739,15 @@ mod tests {
     use std::{f32::consts::PI, time::Duration};
 
     use assertables::{assert_abs_diff_le_x, assert_approx_eq};
-    use bevy::{app::App, time::Time};
+    use bevy::{
+        app::App,
+        ecs::system::RunSystemOnce,
+        tasks::TaskPoolBuilder,
+        time::Time,
+    };
 
     use super::{test_utils::*, *};
-    use crate::states_eq;
+    use crate::{physics::collisions::CollisionOutcome, states_eq};
 
     fn spawn_entity_with_states(
         world: &mut World,
@@ -382,12 +1755,14 @@ mod tests {
         states: impl IntoIterator<Item = (u64, PhysicsState)>,
         events: impl IntoIterator<Item = (u64, ControlInput)>,
     ) -> Entity {
-        let collider = Collider(BRect::from_corners(-dim / 2., dim / 2.));
+        let collider = Collider::from_dim(dim);
         let mut timeline = Timeline {
             future_states: BTreeMap::from_iter(states),
-            input_events: BTreeMap::from_iter(events),
             ..default()
         };
+        for (tick, event) in events {
+            timeline.input_events.entry(tick).or_default().push(event);
+        }
 
         if let Some((tick, _)) = timeline.future_states.last_key_value() {
             timeline.last_computed_tick = *tick;
@@ -402,7 +1777,7 @@ mod tests {
                     .1
                     .clone(),
                 timeline: timeline.clone(),
-                collider,
+                collider: collider.clone(),
             })
             .id();
 
@@ -447,7 +1822,7 @@ mod tests {
         let a_tl = app.world().entity(a).get::<Timeline>().unwrap();
         let b_tl = app.world().entity(b).get::<Timeline>().unwrap();
 
-        fn s<'a>(tl: &'a Timeline, tick: u64) -> &'a PhysicsState {
+        fn s<'a>(tl: &'a Timeline, tick: u64) -> Cow<'a, PhysicsState> {
             tl.state(tick).unwrap()
         }
 
@@ -459,11 +1834,493 @@ mod tests {
         states_eq!(s(a_tl, 2), a_st.b().pos(20., 0.).b());
         states_eq!(s(a_tl, 3), a_st.b().pos(30., 0.).alive(false).b());
 
-        states_eq!(s(b_tl, 0), b_st.b().b());
-        states_eq!(s(b_tl, 1), b_st.b().b());
-        states_eq!(s(b_tl, 2), b_st.b().b());
-        states_eq!(s(b_tl, 3), b_st.b().vel(1., 0.).b());
-        states_eq!(s(b_tl, 4), b_st.b().pos(31., 0.).vel(1., 0.).b());
+        states_eq!(s(b_tl, 0), b_st.b().b());
+        states_eq!(s(b_tl, 1), b_st.b().b());
+        states_eq!(s(b_tl, 2), b_st.b().b());
+        // B is pushed off when A touches it 0.8s into tick 3
+        states_eq!(s(b_tl, 3), b_st.b().pos(30.2, 0.).vel(1., 0.).b());
+        states_eq!(s(b_tl, 4), b_st.b().pos(31.2, 0.).vel(1., 0.).b());
+    }
+
+    #[test]
+    fn test_inert_body_coasts() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 5,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let a_st = TestStateBuilder::new().vel(10., 0.).build();
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(a_st.clone(), dim, 0, []))
+            .id();
+        // Thrusting, so it is integrated every tick
+        let b_st = TestStateBuilder::new().pos(0., 50.).thrust(1., 1.).build();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st.clone(), dim, 0, []))
+            .id();
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        // Only the state it started from is stored
+        assert_eq!(tl(a).future_states.keys().collect::<Vec<_>>(), [&0]);
+        assert_eq!(tl(a).last_tick(), Some(6));
+        for tick in 1..=6 {
+            let pos = 10. * tick as f32;
+            states_eq!(tl(a).state(tick).unwrap(), a_st.b().pos(pos, 0.).b());
+        }
+        assert_eq!(tl(b).future_states.len(), 7);
+        assert!(tl(b).coasts.is_empty());
+
+        // Dropping history re-anchors the coast without changing its states
+        let before = tl(a).states().skip(3).collect::<Vec<_>>();
+        let mut dropped = tl(a).clone();
+        dropped.drop_states_before(3);
+        assert_eq!(dropped.first_tick(), Some(3));
+        assert_eq!(dropped.states().collect::<Vec<_>>(), before);
+
+        // Modifying a coasted tick stores it and keeps the coast around it
+        dropped.state_mut(4).unwrap().health = 0.5;
+        assert_eq!(dropped.coasts.keys().collect::<Vec<_>>(), [&3, &5]);
+        assert_eq!(dropped.state(4).unwrap().health, 0.5);
+        assert_eq!(dropped.state(6), before.last().map(|(_, s)| s.clone()));
+    }
+
+    #[test]
+    fn test_arena_bounds_wrap_predictions() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 3,
+                ..TEST_CONFIG
+            })
+            .insert_resource(ArenaBounds::new(
+                Rect::new(-25., -25., 25., 25.),
+                BoundaryMode::Wrap,
+            ))
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let a_st = TestStateBuilder::new().vel(10., 0.).build();
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(a_st, dim, 0, []))
+            .id();
+        // Across the arena from where `a` wraps
+        let b_st = TestStateBuilder::new().pos(-5., 0.).build();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, dim, 0, []))
+            .id();
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        let xs = (1..=4)
+            .map(|tick| tl(a).state(tick).unwrap().pos.to_vec2().x)
+            .collect::<Vec<_>>();
+        assert_eq!(xs, [10., 20., -20., -10.]);
+        // Swept in from beyond the edge, not across the arena
+        assert!(tl(a).sim_events.is_empty());
+        assert!(tl(b).sim_events.is_empty());
+        let spatial_index = app.world().resource::<SpatialIndex>();
+        let found = spatial_index
+            .entities_within(3, BRect::from_center_half_size(Vec2::ZERO, dim))
+            .collect::<Vec<_>>();
+        assert!(found.is_empty());
+
+        // Changing or removing the bounds redoes the predictions
+        app.insert_resource(ArenaBounds::new(
+            Rect::new(-25., -25., 25., 25.),
+            BoundaryMode::Reflect,
+        ));
+        app.update();
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        let xs = (1..=4)
+            .map(|tick| tl(a).state(tick).unwrap().pos.to_vec2().x)
+            .collect::<Vec<_>>();
+        assert_eq!(xs, [10., 20., 20., 10.]);
+
+        app.world_mut().remove_resource::<ArenaBounds>();
+        app.update();
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        let xs = (1..=4)
+            .map(|tick| tl(a).state(tick).unwrap().pos.to_vec2().x)
+            .collect::<Vec<_>>();
+        assert_eq!(xs, [10., 20., 30., 40.]);
+    }
+
+    #[test]
+    fn test_prediction_horizon_per_entity() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 6,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let st = TestStateBuilder::new().vel(10., 0.).build();
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(st.clone(), dim, 0, []))
+            .id();
+        let b_st = st.b().pos(0., 50.).b();
+        let b = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(b_st, dim, 0, []),
+                PredictionPolicy::horizon(2),
+            ))
+            .id();
+
+        app.update();
+
+        let tl = |app: &App, e: Entity| {
+            app.world().entity(e).get::<Timeline>().unwrap().clone()
+        };
+        assert_eq!(tl(&app, a).last_tick(), Some(7));
+        assert_eq!(tl(&app, b).last_tick(), Some(3));
+        assert_eq!(tl(&app, b).last_updated_range, Some(1..=3));
+        let in_index = |app: &App, tick| {
+            let rect =
+                BRect::from_corners(Vec2::splat(-500.), Vec2::splat(500.));
+            let mut entities = app
+                .world()
+                .resource::<SpatialIndex>()
+                .entities_within(tick, rect)
+                .collect::<Vec<_>>();
+            entities.sort();
+            entities
+        };
+        assert_eq!(in_index(&app, 3), [a, b]);
+        assert_eq!(in_index(&app, 5), [a]);
+
+        // The horizon moves along with the current tick
+        app.world_mut()
+            .resource_mut::<SimulationConfig>()
+            .current_tick = 2;
+        app.update();
+        assert_eq!(tl(&app, a).last_tick(), Some(8));
+        assert_eq!(tl(&app, b).last_tick(), Some(4));
+        states_eq!(tl(&app, b).state(4).unwrap(), st.b().pos(40., 50.).b());
+    }
+
+    #[test]
+    fn test_coarse_prediction_is_refined() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 12,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let st = TestStateBuilder::new().thrust(1., 1.).build();
+        let policy = PredictionPolicy::horizon(12).with_coarse_step(4, 2, 0.);
+        let coarse = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(st.clone(), dim, 0, []),
+                policy,
+            ))
+            .id();
+        let fine_st = st.b().pos(0., 50.).b();
+        let fine = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(fine_st, dim, 0, []))
+            .id();
+
+        app.update();
+
+        let tl = |app: &App, e: Entity| {
+            app.world().entity(e).get::<Timeline>().unwrap().clone()
+        };
+        // Integrated a tick at a time up to the exact ticks, then in steps
+        // of 4 past the horizon
+        let coarse_tl = tl(&app, coarse);
+        assert_eq!(
+            coarse_tl.future_states.keys().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 7, 11, 15]
+        );
+        assert_eq!(coarse_tl.coarse_from, Some(4));
+        assert_eq!(coarse_tl.last_computed_tick, 15);
+        // Straight between the ends of a step
+        let (from, to) =
+            (coarse_tl.state(3).unwrap(), coarse_tl.state(7).unwrap());
+        assert_approx_eq!(
+            coarse_tl.state(5).unwrap().pos.to_vec2().x,
+            (from.pos.to_vec2().x + to.pos.to_vec2().x) / 2.
+        );
+
+        // As the current tick moves on, coarse states are recomputed before
+        // they come within the exact ticks
+        for current_tick in 2..=10 {
+            app.world_mut()
+                .resource_mut::<SimulationConfig>()
+                .current_tick = current_tick;
+            app.update();
+
+            let (coarse_tl, fine_tl) = (tl(&app, coarse), tl(&app, fine));
+            let exact_end = current_tick + 2;
+            for tick in 1..=exact_end {
+                let state = coarse_tl.state(tick).unwrap();
+                let fine_state = fine_tl.state(tick).unwrap();
+                assert_eq!(state.pos.x, fine_state.pos.x, "tick {tick}");
+                assert_eq!(state.vel, fine_state.vel, "tick {tick}");
+            }
+            assert!(coarse_tl.coarse_from.unwrap() > exact_end);
+            assert!(coarse_tl.last_tick().unwrap() >= current_tick + 12);
+        }
+    }
+
+    #[test]
+    fn test_coarse_prediction_exact_near_selected() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 12,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let policy = PredictionPolicy::horizon(12).with_coarse_step(4, 2, 100.);
+        let spawn = |app: &mut App, st: PhysicsState| {
+            app.world_mut()
+                .spawn((PhysicsBundle::new_with_events(st, dim, 0, []), policy))
+                .id()
+        };
+        let st = TestStateBuilder::new().thrust(1., 1.).build();
+        let ship = spawn(&mut app, st.clone());
+        let near = spawn(&mut app, st.b().pos(0., 50.).b());
+        let far = spawn(&mut app, st.b().pos(0., 500.).b());
+        app.insert_resource(Selected(ship));
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        for e in [ship, near] {
+            assert_eq!(tl(e).future_states.len(), 14);
+            assert_eq!(tl(e).coarse_from, None);
+        }
+        assert_eq!(tl(far).coarse_from, Some(4));
+    }
+
+    #[test]
+    fn test_fast_body_does_not_tunnel() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 2,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        // Crosses the whole asteroid within a single tick
+        let bullet_st = TestStateBuilder::new().vel(100., 0.).mass(1.).build();
+        let bullet = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                bullet_st,
+                Vec2::splat(1.),
+                0,
+                [],
+            ))
+            .id();
+        let asteroid_st = TestStateBuilder::new().pos(50., 0.).mass(100.).b();
+        let asteroid = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                asteroid_st,
+                Vec2::splat(4.),
+                0,
+                [],
+            ))
+            .id();
+
+        app.update();
+
+        let bullet_tl = app.world().entity(bullet).get::<Timeline>().unwrap();
+        let asteroid_tl =
+            app.world().entity(asteroid).get::<Timeline>().unwrap();
+
+        assert!(!bullet_tl.state(1).unwrap().alive);
+        assert!(asteroid_tl.state(1).unwrap().alive);
+        let collision = &bullet_tl.sim_events.get(&1).unwrap()[0];
+        assert_eq!(collision.other, asteroid);
+        // Touches once the 1m bullet reaches the 4m asteroid's edge
+        assert_approx_eq!(collision.time_of_impact, 0.475);
+        assert_eq!(asteroid_tl.sim_events.get(&1).unwrap()[0].other, bullet);
+    }
+
+    #[test]
+    fn test_lighter_body_fragments_at_collision_tick() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 6,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let ship_st = TestStateBuilder::new().vel(10., 0.).mass(100.).b();
+        let ship = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(ship_st, dim, 0, []))
+            .id();
+        let asteroid_st = TestStateBuilder::new().pos(50., 0.).mass(10.).b();
+        let asteroid = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(asteroid_st, dim, 0, []))
+            .id();
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        // Ship reaches the asteroid during tick 5
+        assert!(tl(asteroid).sim_events.contains_key(&5));
+        assert!(!tl(asteroid).state(5).unwrap().alive);
+        assert!(tl(ship).fragmentations.is_empty());
+
+        // Q = 1/2 * (100 / 10) * 10^2 = 500 J/kg
+        let debris = tl(asteroid).fragmentations.get(&5).unwrap();
+        assert_eq!(debris.outcome, CollisionOutcome::Fracturing);
+        assert_eq!(debris.fragments.len(), 2);
+        for fragment in &debris.fragments {
+            assert_approx_eq!(fragment.mass.to_f32(), 5.);
+            assert!(fragment.alive);
+        }
+        assert_eq!(tl(asteroid).fragmentations.len(), 1);
+    }
+
+    #[test]
+    fn test_collision_deals_partial_damage() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 4,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let a_st = TestStateBuilder::new().vel(10., 0.).health(10.).b();
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(a_st.clone(), dim, 0, []))
+            .id();
+        let b_st = TestStateBuilder::new().pos(30., 0.).mass(9.).b();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st.clone(), dim, 0, []))
+            .id();
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        // Q = 1/2 * (9 / 1) * 10^2 = 450 J/kg is enough to destroy a body
+        // with 1 health, but not one with 10
+        let hit = &tl(a).sim_events.get(&3).unwrap()[0];
+        assert_eq!(hit.other, b);
+        assert_approx_eq!(hit.damage, 4.5);
+        assert_approx_eq!(tl(a).state(2).unwrap().health, 10.);
+        assert_approx_eq!(tl(a).state(3).unwrap().health, 5.5);
+        states_eq!(
+            tl(a).state(3).unwrap(),
+            a_st.b().pos(28.2, 0.).vel(1., 0.).b()
+        );
+
+        // Q = 1/2 * (1 / 9) * 10^2 ~= 5.6 J/kg
+        assert_abs_diff_le_x!(
+            tl(b).sim_events.get(&3).unwrap()[0].damage,
+            0.0556,
+            1e-3
+        );
+        assert_abs_diff_le_x!(tl(b).state(5).unwrap().health, 0.9444, 1e-3);
+
+        // Left touching at the same velocity, so they don't collide again
+        for tick in 4..=5 {
+            assert!(!tl(a).sim_events.contains_key(&tick));
+            assert!(!tl(b).sim_events.contains_key(&tick));
+        }
+        states_eq!(
+            tl(a).state(5).unwrap(),
+            a_st.b().pos(30.2, 0.).vel(1., 0.).b()
+        );
+        assert_approx_eq!(tl(a).state(5).unwrap().health, 5.5);
+    }
+
+    #[test]
+    fn test_bouncy_material_rebounds() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 3,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let bouncy = Material {
+            restitution: 1.,
+            ..default()
+        };
+        let a_st = TestStateBuilder::new().vel(10., 0.).b();
+        let a = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(a_st.clone(), dim, 0, []),
+                bouncy,
+            ))
+            .id();
+        let b_st = TestStateBuilder::new().pos(30., 0.).b();
+        let b = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(b_st.clone(), dim, 0, []),
+                bouncy,
+            ))
+            .id();
+
+        app.update();
+
+        let s = |e: Entity, tick| {
+            app.world()
+                .entity(e)
+                .get::<Timeline>()
+                .unwrap()
+                .state(tick)
+                .unwrap()
+                .into_owned()
+        };
+        // Q = 1/2 * 10^2 = 50 J/kg, half the default toughness
+        assert_approx_eq!(s(a, 3).health, 0.5);
+        assert_approx_eq!(s(b, 3).health, 0.5);
+
+        // Equal masses trade velocities when they touch 0.8s into tick 3
+        states_eq!(s(a, 3), a_st.b().pos(28., 0.).vel(0., 0.).b());
+        states_eq!(s(b, 3), b_st.b().pos(32., 0.).vel(10., 0.).b());
+        states_eq!(s(a, 4), a_st.b().pos(28., 0.).vel(0., 0.).b());
+        states_eq!(s(b, 4), b_st.b().pos(42., 0.).vel(10., 0.).b());
     }
 
     #[test]
@@ -525,6 +2382,61 @@ mod tests {
         assert_eq!(b_tl.last_updated_range, Some(3..=5));
     }
 
+    #[test]
+    fn test_pileup_records_every_collision() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 4,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        // A ploughs into B and C, sitting side by side, at the same time
+        let dim = Vec2::splat(2.);
+        let a_st = TestStateBuilder::new().vel(10., 0.).mass(10.).b();
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(a_st, dim, 0, []))
+            .id();
+        let [b, c] = [1.5, -1.5].map(|y| {
+            let st = TestStateBuilder::new().pos(30., y).b();
+            app.world_mut()
+                .spawn(PhysicsBundle::new_with_events(st, dim, 0, []))
+                .id()
+        });
+
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        let others = |e: Entity| {
+            tl(e).sim_events.get(&3).map_or_else(Vec::new, |events| {
+                events.iter().map(|event| event.other).collect()
+            })
+        };
+        assert_eq!(others(a), vec![b, c]);
+        assert_eq!(others(b), vec![a]);
+        assert_eq!(others(c), vec![a]);
+        assert!(tl(a).state(3).unwrap().alive);
+        assert!(!tl(b).state(3).unwrap().alive);
+        assert!(!tl(c).state(3).unwrap().alive);
+
+        // Turning A around undoes both collisions
+        app.world_mut()
+            .entity_mut(a)
+            .get_mut::<Timeline>()
+            .unwrap()
+            .add_input_event(2, ControlInput::SetThrustAndRotation(1., PI));
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        for e in [a, b, c] {
+            assert!(!tl(e).sim_events.contains_key(&3));
+            assert!(tl(e).state(3).unwrap().alive);
+        }
+    }
+
     #[test]
     fn test_collision_invalidates() {
         let mut app = App::new();
@@ -563,8 +2475,8 @@ mod tests {
         let a_tl = app.world().entity(a).get::<Timeline>().unwrap();
         let b_tl = app.world().entity(b).get::<Timeline>().unwrap();
 
-        fn s<'a>(tl: &'a Timeline, tick: u64) -> &'a PhysicsState {
-            tl.future_states.get(&tick).unwrap()
+        fn s<'a>(tl: &'a Timeline, tick: u64) -> Cow<'a, PhysicsState> {
+            tl.state(tick).unwrap()
         }
 
         assert_eq!(a_tl.last_updated_range, Some(1..=5));
@@ -578,8 +2490,9 @@ mod tests {
         states_eq!(s(b_tl, 0), b_st.b().b());
         states_eq!(s(b_tl, 1), b_st.b().b());
         states_eq!(s(b_tl, 2), b_st.b().b());
-        states_eq!(s(b_tl, 3), b_st.b().vel(1., 0.).b());
-        states_eq!(s(b_tl, 4), b_st.b().pos(31., 0.).vel(1., 0.).b());
+        // B is pushed off when A touches it 0.8s into tick 3
+        states_eq!(s(b_tl, 3), b_st.b().pos(30.2, 0.).vel(1., 0.).b());
+        states_eq!(s(b_tl, 4), b_st.b().pos(31.2, 0.).vel(1., 0.).b());
     }
 
     #[test]
@@ -621,8 +2534,8 @@ mod tests {
         let a_tl = app.world().entity(a).get::<Timeline>().unwrap();
         let b_tl = app.world().entity(b).get::<Timeline>().unwrap();
 
-        fn s<'a>(tl: &'a Timeline, tick: u64) -> &'a PhysicsState {
-            tl.future_states.get(&tick).unwrap()
+        fn s<'a>(tl: &'a Timeline, tick: u64) -> Cow<'a, PhysicsState> {
+            tl.state(tick).unwrap()
         }
 
         assert_eq!(
@@ -638,30 +2551,30 @@ mod tests {
         states_eq!(
             s(a_tl, 1),
             PhysicsState {
-                pos: (10., 0.).into(),
-                vel: (-90., 0.).into(),
+                pos: RealVec2::from_vec2(Vec2::new(10., 0.)),
+                vel: RealVec2::from_vec2(Vec2::new(-90., 0.)),
                 current_thrust: 1.0,
-                rotation: PI,
+                rotation: Real::from_f32(PI),
                 ..a_st.clone()
             }
         );
         states_eq!(
             s(a_tl, 2),
             PhysicsState {
-                pos: (-80., 0.).into(),
-                vel: (-190., 0.).into(),
+                pos: RealVec2::from_vec2(Vec2::new(-80., 0.)),
+                vel: RealVec2::from_vec2(Vec2::new(-190., 0.)),
                 current_thrust: 1.0,
-                rotation: PI,
+                rotation: Real::from_f32(PI),
                 ..a_st.clone()
             }
         );
         states_eq!(
             s(a_tl, 3),
             PhysicsState {
-                pos: (-270., 0.).into(),
-                vel: (-190., 100.).into(),
+                pos: RealVec2::from_vec2(Vec2::new(-270., 0.)),
+                vel: RealVec2::from_vec2(Vec2::new(-190., 100.)),
                 current_thrust: 1.0,
-                rotation: PI / 2.,
+                rotation: Real::from_f32(PI / 2.),
                 ..a_st.clone()
             }
         );
@@ -672,6 +2585,82 @@ mod tests {
         states_eq!(s(b_tl, 3), b_st.b().vel(0., 0.).b());
     }
 
+    #[test]
+    fn test_inputs_on_same_tick_all_apply() {
+        let mut app = App::new();
+        app.insert_resource(SpatialIndex::default())
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let anchor = BeamAnchor::Fixed(Vec2::ZERO);
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                TestStateBuilder::new().mass(1.).build(),
+                Vec2::splat(2.),
+                0,
+                [
+                    (1, ControlInput::SetThrustAndRotation(1., 0.)),
+                    (1, ControlInput::ElasticBeamConnect(anchor, default())),
+                ],
+            ))
+            .id();
+        app.update();
+
+        let tl = app.world().entity(a).get::<Timeline>().unwrap();
+        let state = tl.state(1).unwrap();
+        assert_eq!(state.current_thrust, 1.);
+        assert_eq!(state.elastic_beams.len(), 1);
+        assert_eq!(state.elastic_beams[0].anchor, anchor);
+    }
+
+    #[test]
+    fn test_input_events_keep_order_per_tick() {
+        let mut timeline = Timeline {
+            last_computed_tick: 5,
+            ..default()
+        };
+        timeline.add_input_event(3, ControlInput::SetThrust(1.));
+        timeline.add_input_event(3, ControlInput::SetRotation(PI));
+        timeline.add_input_event(3, ControlInput::SetThrust(0.5));
+        assert_eq!(timeline.last_computed_tick, 2);
+        assert_eq!(
+            timeline.inputs(3),
+            &[
+                ControlInput::SetThrust(1.),
+                ControlInput::SetRotation(PI),
+                ControlInput::SetThrust(0.5),
+            ]
+        );
+
+        // Later inputs win when applied
+        let mut state = TestStateBuilder::new().build();
+        state.apply_input_events(timeline.inputs(3));
+        assert_eq!(state.current_thrust, 0.5);
+        assert_eq!(state.rotation.to_f32(), PI);
+
+        // Removal only takes out the matching input
+        assert!(!timeline.remove_input_event(3, ControlInput::SetThrust(2.)));
+        assert!(timeline.remove_input_event(3, ControlInput::SetThrust(1.)));
+        assert_eq!(
+            timeline.inputs(3),
+            &[ControlInput::SetRotation(PI), ControlInput::SetThrust(0.5)]
+        );
+
+        timeline.replace_input_event(3, ControlInput::SetThrust(0.25));
+        assert_eq!(
+            timeline.inputs(3),
+            &[ControlInput::SetRotation(PI), ControlInput::SetThrust(0.25)]
+        );
+
+        assert!(timeline.remove_input_event(3, ControlInput::SetRotation(PI)));
+        assert!(timeline.remove_input_event(3, ControlInput::SetThrust(0.25)));
+        assert!(!timeline.input_events.contains_key(&3));
+    }
+
     #[test]
     fn test_elastic_beam_connection() {
         let mut app = App::new();
@@ -695,6 +2684,7 @@ mod tests {
             .world_mut()
             .spawn(PhysicsBundle::new_with_events(b_st.clone(), dim, 0, []))
             .id();
+        let anchor = BeamAnchor::Entity(b);
 
         // Now spawn A with the correct entity ID for the beam connection
         let a = app
@@ -705,9 +2695,9 @@ mod tests {
                 0,
                 [
                     // Connect beam at tick 2 using actual entity ID
-                    (2, ControlInput::ElasticBeamConnect(b)),
+                    (2, ControlInput::ElasticBeamConnect(anchor, default())),
                     // Disconnect at tick 4 using actual entity ID
-                    (4, ControlInput::ElasticBeamDisconnect(b)),
+                    (4, ControlInput::ElasticBeamDisconnect(anchor)),
                 ],
             ))
             .id();
@@ -719,28 +2709,553 @@ mod tests {
         let b_tl = app.world().entity(b).get::<Timeline>().unwrap();
 
         // Helper to get state at tick
-        fn s<'a>(tl: &'a Timeline, tick: u64) -> &'a PhysicsState {
-            tl.future_states.get(&tick).unwrap()
+        fn s<'a>(tl: &'a Timeline, tick: u64) -> Cow<'a, PhysicsState> {
+            tl.state(tick).unwrap()
         }
 
         // Before connection (tick 1)
-        assert!(s(a_tl, 1).elastic_beam.is_none());
-        assert!(s(b_tl, 1).elastic_beam.is_none());
-        assert_approx_eq!(s(a_tl, 1).vel.x, 0.0);
+        assert!(s(a_tl, 1).elastic_beams.is_empty());
+        assert!(s(b_tl, 1).elastic_beams.is_empty());
+        assert_approx_eq!(s(a_tl, 1).vel.to_vec2().x, 0.0);
 
         // After connection (tick 3)
         let state_3 = s(a_tl, 3);
-        dbg!(state_3);
-        assert!(state_3.elastic_beam.is_some());
-        let beam = state_3.elastic_beam.as_ref().unwrap();
-        assert_eq!(beam.connected_entity, b);
-        // Should be pulled toward B
-        assert!(state_3.vel.x > 0.0);
+        dbg!(&state_3);
+        assert_eq!(state_3.elastic_beams.len(), 1);
+        assert_eq!(state_3.elastic_beams[0].anchor, anchor);
+        // Should be pulled toward B, and B toward A
+        assert!(state_3.vel.to_vec2().x > 0.0);
+        assert!(s(b_tl, 3).vel.to_vec2().x < 0.0);
 
         // After disconnection (tick 5)
-        assert!(s(a_tl, 5).elastic_beam.is_none());
+        assert!(s(a_tl, 5).elastic_beams.is_empty());
         // Velocity should persist but not increase
-        let vel_at_disconnect = s(a_tl, 4).vel.x;
-        assert_approx_eq!(s(a_tl, 5).vel.x, vel_at_disconnect);
+        let vel_at_disconnect = s(a_tl, 4).vel.to_vec2().x;
+        assert_approx_eq!(s(a_tl, 5).vel.to_vec2().x, vel_at_disconnect);
+    }
+
+    #[test]
+    fn test_elastic_beam_params_and_range() {
+        let mut app = App::new();
+        let config = SimulationConfig {
+            current_tick: 1,
+            prediction_ticks: 4,
+            ..TEST_CONFIG
+        };
+        app.insert_resource(SpatialIndex::default())
+            .insert_resource(config.clone())
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let tether = BeamParams {
+            neutral_length: 2.,
+            stiffness: 1.,
+            max_length: 20.,
+        };
+        let connect =
+            |e| ControlInput::ElasticBeamConnect(BeamAnchor::Entity(e), tether);
+        let mut spawn = |(x, y), events: Vec<(u64, ControlInput)>| {
+            let st = TestStateBuilder::new().pos(x, y).mass(1.).build();
+            app.world_mut()
+                .spawn(PhysicsBundle::new_with_events(st, dim, 0, events))
+                .id()
+        };
+
+        let near = spawn((15., 0.), vec![]);
+        let far = spawn((-50., 10.), vec![]);
+        let pulled = spawn((0., 0.), vec![(2, connect(near))]);
+        let rejected = spawn((0., 10.), vec![(2, connect(far))]);
+        app.update();
+
+        let state = |e: Entity, tick: u64| {
+            app.world()
+                .entity(e)
+                .get::<Timeline>()
+                .unwrap()
+                .state(tick)
+                .unwrap()
+                .into_owned()
+        };
+
+        // Short stiff tether: 13m of stretch at k = 1 on equal masses
+        let beams = state(pulled, 2).elastic_beams;
+        assert_eq!(
+            beams,
+            vec![ElasticBeamInfo::new(BeamAnchor::Entity(near), tether)]
+        );
+        assert!(state(pulled, 2).vel.to_vec2().x > 5.);
+
+        // 50m is beyond the 20m tether, so the connect never takes effect
+        for tick in 2..=5 {
+            assert!(state(rejected, tick).elastic_beams.is_empty());
+            assert_eq!(state(rejected, tick).vel.to_vec2(), Vec2::ZERO);
+            assert_eq!(state(far, tick).vel.to_vec2(), Vec2::ZERO);
+        }
+        // and is recorded as rejected rather than as a break
+        let timeline = |e| app.world().get::<Timeline>(e).unwrap();
+        assert_eq!(
+            timeline(rejected).rejected_connects,
+            BTreeMap::from([(2, vec![connect(far)])])
+        );
+        for e in [rejected, far, pulled] {
+            assert!(timeline(e).broken_beams.is_empty());
+        }
+        assert!(timeline(far).rejected_connects.is_empty());
+        assert!(timeline(pulled).rejected_connects.is_empty());
+    }
+
+    #[test]
+    fn test_multiple_beams_and_fixed_anchor() {
+        let mut app = App::new();
+        app.insert_resource(SpatialIndex::default())
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 3,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let b_st = TestStateBuilder::new().pos(20., 0.).mass(1.).build();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, dim, 0, []))
+            .id();
+        // A connects to B, then also to a fixed anchor the other side
+        let a_st = TestStateBuilder::new().mass(1.).build();
+        let fixed = BeamAnchor::Fixed(Vec2::new(-20., 0.));
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                a_st,
+                dim,
+                0,
+                [
+                    (
+                        1,
+                        ControlInput::ElasticBeamConnect(
+                            BeamAnchor::Entity(b),
+                            default(),
+                        ),
+                    ),
+                    (2, ControlInput::ElasticBeamConnect(fixed, default())),
+                ],
+            ))
+            .id();
+        app.update();
+
+        let s = |e: Entity, tick: u64| {
+            app.world()
+                .entity(e)
+                .get::<Timeline>()
+                .unwrap()
+                .state(tick)
+                .unwrap()
+                .into_owned()
+        };
+        assert_eq!(s(a, 1).elastic_beams.len(), 1);
+        assert_eq!(s(a, 2).elastic_beams.len(), 2);
+        assert_eq!(s(a, 2).elastic_beams[1].anchor, fixed);
+
+        // 10m of stretch at k = 0.25 shared by two unit masses
+        assert_approx_eq!(s(a, 1).vel.to_vec2().x, 2.5);
+        assert_approx_eq!(s(b, 1).vel.to_vec2().x, -2.5);
+        // B keeps adding 2.5 m/s, the anchor 17.5m away at the start of the
+        // tick takes back 7.5m * 0.25 on its own
+        assert_approx_eq!(s(a, 2).vel.to_vec2().x, 2.5 + 2.5 - 1.875);
+        assert_approx_eq!(s(b, 2).vel.to_vec2().x, -5.);
+    }
+
+    #[test]
+    fn test_beam_breaks_both_ends() {
+        let mut app = App::new();
+        app.insert_resource(SpatialIndex::default())
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 3,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let params = BeamParams {
+            neutral_length: 15.,
+            stiffness: 0.01,
+            max_length: 20.,
+        };
+        // B starts within range but flies off at 10 m/s
+        let b_st = TestStateBuilder::new().pos(15., 0.).vel(10., 0.).b();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, dim, 0, []))
+            .id();
+        let anchor = BeamAnchor::Entity(b);
+        let a = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(
+                TestStateBuilder::new().b(),
+                dim,
+                0,
+                [(1, ControlInput::ElasticBeamConnect(anchor, params))],
+            ))
+            .id();
+        // C holds a beam to an entity that's already gone
+        let gone = app.world_mut().spawn_empty().id();
+        app.world_mut().despawn(gone);
+        let mut c_st = TestStateBuilder::new().pos(0., 50.).b();
+        c_st.elastic_beams =
+            vec![ElasticBeamInfo::new(BeamAnchor::Entity(gone), params)];
+        let c = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(c_st, dim, 0, []))
+            .id();
+        app.update();
+
+        let tl = |e: Entity| app.world().entity(e).get::<Timeline>().unwrap();
+        let broken = BeamBroken {
+            owner: a,
+            anchor,
+            reason: BeamBreakReason::Overstretched,
+        };
+        assert_eq!(tl(a).broken_beams.get(&1), Some(&vec![broken.clone()]));
+        assert_eq!(tl(b).broken_beams.get(&1), Some(&vec![broken]));
+        assert!(tl(a).state(1).unwrap().elastic_beams.is_empty());
+        assert_eq!(tl(a).state(1).unwrap().vel.to_vec2(), Vec2::ZERO);
+
+        assert_eq!(
+            tl(c).broken_beams.get(&1),
+            Some(&vec![BeamBroken {
+                owner: c,
+                anchor: BeamAnchor::Entity(gone),
+                reason: BeamBreakReason::EndpointLost,
+            }])
+        );
+        assert!(tl(c).state(2).unwrap().elastic_beams.is_empty());
+    }
+
+    #[test]
+    fn test_for_each_parallel_runs_on_task_pool() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::{Duration, Instant},
+        };
+
+        ComputeTaskPool::get_or_init(|| {
+            TaskPoolBuilder::new().num_threads(2).build()
+        });
+        // Each item waits for the other to start, which only happens in time
+        // if they run at the same time on different threads
+        let overlapped = |parallel: bool, wait: Duration| {
+            let started = AtomicUsize::new(0);
+            let mut items = [false; 2];
+            for_each_parallel(&mut items, parallel, |overlapped| {
+                started.fetch_add(1, Ordering::SeqCst);
+                let deadline = Instant::now() + wait;
+                while started.load(Ordering::SeqCst) < 2
+                    && Instant::now() < deadline
+                {
+                    std::thread::yield_now();
+                }
+                *overlapped = started.load(Ordering::SeqCst) == 2;
+            });
+            items
+        };
+        assert_eq!(overlapped(true, Duration::from_secs(10)), [true; 2]);
+        assert_eq!(overlapped(false, Duration::from_millis(50)), [false, true]);
+    }
+
+    #[test]
+    fn test_parallel_islands_match_serial() {
+        // Single core runners would otherwise compute with one pool thread
+        ComputeTaskPool::get_or_init(|| {
+            TaskPoolBuilder::new().num_threads(2).build()
+        });
+        let timelines = |parallel_islands: bool| {
+            let mut app = App::new();
+            app.insert_resource(SpatialIndex::default())
+                .insert_resource(SimulationConfig {
+                    current_tick: 1,
+                    prediction_ticks: 10,
+                    parallel_islands,
+                    ..TEST_CONFIG
+                });
+
+            let dim = Vec2::splat(2.);
+            let world = app.world_mut();
+            // Several beam-linked pairs and head-on collisions far apart,
+            // plus a pileup of three
+            for i in 0..4 {
+                let y = 100. * i as f32;
+                let b_st = TestStateBuilder::new().pos(20., y).mass(1.).build();
+                let b = world
+                    .spawn(PhysicsBundle::new_with_events(b_st, dim, 0, []))
+                    .id();
+                let a_st = TestStateBuilder::new().pos(0., y).mass(2.).build();
+                world.spawn(PhysicsBundle::new_with_events(
+                    a_st,
+                    dim,
+                    0,
+                    [(
+                        1,
+                        ControlInput::ElasticBeamConnect(
+                            BeamAnchor::Entity(b),
+                            default(),
+                        ),
+                    )],
+                ));
+
+                let c_st =
+                    TestStateBuilder::new().pos(500., y).vel(10., 0.).build();
+                let d_st =
+                    TestStateBuilder::new().pos(530., y).vel(-10., 0.).build();
+                for st in [c_st, d_st] {
+                    world.spawn(PhysicsBundle::new_with_events(st, dim, 0, []));
+                }
+            }
+            for x in [0., 3., 6.] {
+                let st = TestStateBuilder::new()
+                    .pos(x - 1000., 0.)
+                    .vel(10. - x, 0.)
+                    .build();
+                world.spawn(PhysicsBundle::new_with_events(st, dim, 0, []));
+            }
+            world.run_system_once(compute_future_states).unwrap();
+
+            let mut query = app.world_mut().query::<(Entity, &Timeline)>();
+            let mut timelines = query
+                .iter(app.world())
+                .map(|(e, timeline)| (e, timeline.clone()))
+                .collect::<Vec<_>>();
+            timelines.sort_by_key(|(e, _)| *e);
+            timelines
+        };
+
+        let parallel = timelines(true);
+        assert!(parallel
+            .iter()
+            .any(|(_, timeline)| !timeline.sim_events.is_empty()));
+        assert_eq!(parallel, timelines(false));
+    }
+
+    #[test]
+    fn test_gravity_source_pulls_entities() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 3,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let source = GravitySource {
+            mass: 1e13,
+            softening_radius: 0.,
+            cutoff: None,
+        };
+        let well_st = TestStateBuilder::new().mass(1e6).build();
+        let well = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(well_st, dim, 0, []),
+                source,
+            ))
+            .id();
+
+        let probe_st = TestStateBuilder::new().pos(100., 0.).build();
+        let probe = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(probe_st, dim, 0, []))
+            .id();
+
+        app.update();
+
+        let probe_tl = app.world().entity(probe).get::<Timeline>().unwrap();
+        let well_tl = app.world().entity(well).get::<Timeline>().unwrap();
+
+        // a = G M / r^2 towards the well
+        let accel = source.acceleration(Vec2::ZERO, Vec2::new(100., 0.));
+        assert!(accel.x < 0.);
+        assert_approx_eq!(probe_tl.state(1).unwrap().vel.to_vec2().x, accel.x);
+        assert!(probe_tl.state(4).unwrap().pos.to_vec2().x < 100.);
+
+        // Sources don't pull on themselves
+        assert_eq!(well_tl.state(4).unwrap().vel.to_vec2(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_gravity_source_change_invalidates_dependents() {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                prediction_ticks: 6,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let well_st = TestStateBuilder::new().build();
+        let well = app
+            .world_mut()
+            .spawn((
+                PhysicsBundle::new_with_events(well_st, dim, 0, []),
+                GravitySource {
+                    mass: 1e13,
+                    softening_radius: 1.,
+                    cutoff: Some(500.),
+                },
+            ))
+            .id();
+
+        let near_st = TestStateBuilder::new().pos(100., 0.).build();
+        let near = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(near_st, dim, 0, []))
+            .id();
+        let far_st = TestStateBuilder::new().pos(5000., 0.).build();
+        let far = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(far_st, dim, 0, []))
+            .id();
+
+        app.update();
+        let before = app
+            .world()
+            .entity(near)
+            .get::<Timeline>()
+            .unwrap()
+            .state(7)
+            .unwrap()
+            .into_owned();
+
+        // Move the well away from the probe from tick 3 onwards
+        app.world_mut()
+            .entity_mut(well)
+            .get_mut::<Timeline>()
+            .unwrap()
+            .add_input_event(3, ControlInput::SetThrustAndRotation(1., PI));
+        app.update();
+
+        let near_tl = app.world().entity(near).get::<Timeline>().unwrap();
+        let far_tl = app.world().entity(far).get::<Timeline>().unwrap();
+
+        // Entities within the cutoff are recomputed from the changed tick,
+        // entities outside are left alone
+        assert_eq!(near_tl.last_updated_range, Some(3..=7));
+        assert_eq!(far_tl.last_updated_range, None);
+        assert!(near_tl.state(7).unwrap().vel.x > before.vel.x);
+    }
+
+    /// Run a scenario exercising thrust, propellant, rotation, gravity, beams
+    /// and collisions, and hash the bits of every computed state
+    #[cfg(feature = "fixed-point")]
+    fn timeline_digest() -> u64 {
+        let mut app = App::new();
+        app.init_resource::<SpatialIndex>()
+            .insert_resource(SimulationConfig {
+                current_tick: 1,
+                ticks_per_second: 60,
+                prediction_ticks: 600,
+                integrator: Integrator::Rk4,
+                ..TEST_CONFIG
+            })
+            .add_systems(Update, compute_future_states);
+
+        let dim = Vec2::splat(2.);
+        let well_st = TestStateBuilder::new().pos(-200., 50.).mass(1e6).build();
+        app.world_mut().spawn((
+            PhysicsBundle::new_with_events(well_st, dim, 0, []),
+            GravitySource {
+                mass: 1e14,
+                softening_radius: 5.,
+                cutoff: None,
+            },
+        ));
+
+        let b_st = TestStateBuilder::new().pos(40., 10.).mass(4.).build();
+        let b = app
+            .world_mut()
+            .spawn(PhysicsBundle::new_with_events(b_st, dim, 0, []))
+            .id();
+
+        let a_st = TestStateBuilder::new()
+            .vel(5., 0.)
+            .mass(10.)
+            .thrust(0., 20.)
+            .propellant(5., 300.)
+            .build();
+        app.world_mut().spawn(PhysicsBundle::new_with_events(
+            a_st,
+            dim,
+            0,
+            [
+                (2, ControlInput::SetThrustAndRotation(1., 0.3)),
+                (30, ControlInput::SetAngVel(0.5)),
+                (
+                    60,
+                    ControlInput::ElasticBeamConnect(
+                        BeamAnchor::Entity(b),
+                        default(),
+                    ),
+                ),
+                (120, ControlInput::SetThrust(-0.5)),
+            ],
+        ));
+
+        let c_st = TestStateBuilder::new().pos(150., 0.).vel(-30., 0.).b();
+        app.world_mut()
+            .spawn(PhysicsBundle::new_with_events(c_st, dim, 0, []));
+
+        app.update();
+
+        // FNV-1a over every state in entity and tick order
+        let mut hash = 0xcbf29ce484222325_u64;
+        let mut write = |bits: i64| {
+            for byte in bits.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        };
+        let mut query = app.world_mut().query::<(Entity, &Timeline)>();
+        let mut timelines = query.iter(app.world()).collect::<Vec<_>>();
+        timelines.sort_by_key(|(e, _)| *e);
+        for (_, timeline) in timelines {
+            for (_, state) in timeline.states() {
+                for value in [
+                    state.pos.x,
+                    state.pos.y,
+                    state.vel.x,
+                    state.vel.y,
+                    state.rotation,
+                    state.ang_vel,
+                    state.mass,
+                    state.propellant,
+                ] {
+                    write(value.to_bits());
+                }
+                write(state.current_thrust.to_bits() as i64);
+                write(state.alive as i64);
+            }
+        }
+        hash
+    }
+
+    /// Compared against the digest of another build when
+    /// `PARALLAX_TIMELINE_DIGEST` is set, `scripts/check_determinism.sh` runs
+    /// this in a debug build then a release build
+    #[cfg(feature = "fixed-point")]
+    #[test]
+    fn test_fixed_point_timeline_digest() {
+        let digest = format!("{:016x}", timeline_digest());
+        assert_eq!(digest, format!("{:016x}", timeline_digest()));
+        println!("timeline digest: {digest}");
+
+        if let Ok(expected) = std::env::var("PARALLAX_TIMELINE_DIGEST") {
+            assert_eq!(
+                digest, expected,
+                "Timelines differ from the build that computed {expected}"
+            );
+        }
     }
 }
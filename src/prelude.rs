@@ -18,6 +18,14 @@ pub use strum::{EnumIter, EnumString, IntoEnumIterator};
 
 pub use crate::{
     crafts::*,
-    physics::{PhysicsBundle, PhysicsState, Timeline, TimelineEvent},
+    physics::{
+        PhysicsBundle,
+        PhysicsState,
+        Planar,
+        RealVec2,
+        Scalar,
+        Timeline,
+        TimelineEvent,
+    },
     utils::*,
 };
@@ -0,0 +1,885 @@
+//! Match recording and deterministic replay
+//!
+//! A `Recording` holds everything needed to play a match again: the seed of
+//! the global RNG, the bodies present when recording started, and every
+//! accepted timeline edit and weapon fire stamped with the tick it was
+//! processed on. `RecorderPlugin` collects them from the events those systems
+//! send. It is saved as versioned JSON.
+//!
+//! Replaying restores the recorded bodies into a fresh world like a
+//! `Snapshot`, and sends the events again on their ticks, so they go through
+//! `process_timeline_events` and the weapon systems exactly like the originals.
+//! Since the simulation is deterministic the bodies end up in the same states,
+//! which `verify` checks.
+//!
+//! A rewind throws away what happened since the tick it goes back to, while
+//! inputs scheduled in that time stay in the timelines, so recording stops
+//! where a rewind starts.
+//!
+//! Bodies are saved as `BodySnapshot`s, so only their simulation components
+//! are rebuilt. Debris of breakable bodies is spawned by the simulation, see
+//! `sync_fragments`, so replays reproduce it along with its collisions. Only
+//! the recorded bodies are mapped to their replayed counterparts and compared,
+//! so recordings with events referring to entities spawned during the match,
+//! like projectiles or fragments, are rejected
+
+use std::path::Path;
+
+use anyhow::bail;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    physics::{
+        process_timeline_events,
+        ArenaBounds,
+        ControlInput,
+        Integrator,
+        PhysicsEnabled,
+        PhysicsSimulationPlugin,
+        Rewinding,
+        SimTick,
+        SimulationConfig,
+        SimulationSet,
+        SimulationUpdate,
+        TimelineEventAccepted,
+        TimelineEventRemovalRequest,
+        TimelineEventRequest,
+    },
+    prelude::*,
+    snapshot::{self, BodySnapshot, Snapshot},
+    subsystems::{
+        plasma_cannon::{
+            FirePlasmaCannon,
+            PlasmaCannonFired,
+            PlasmaCannonPlugin,
+        },
+        unguided_missile::{
+            FireUnguidedMissile,
+            UnguidedMissileFired,
+            UnguidedMissilePlugin,
+        },
+    },
+    utils::{entity_bits, EntityRemap, VersionedJson},
+    ParallaxProtocolArenaPlugin,
+};
+
+/// Version of the recording file format, bumped on incompatible changes
+pub const FORMAT_VERSION: u32 = 6;
+
+/// Everything needed to replay a match
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// Format the recording was written with, see `FORMAT_VERSION`
+    pub version: u32,
+    /// Seed the global RNG was started with
+    pub seed: u64,
+    pub ticks_per_second: u64,
+    pub prediction_ticks: u64,
+    /// Ticks of history kept, which decides how long destroyed bodies stay
+    /// around before they are despawned
+    pub history_ticks: u64,
+    pub integrator: Integrator,
+    #[serde(default)]
+    pub bounds: Option<ArenaBounds>,
+    /// Tick recording started on, bodies are recorded in their state at it
+    pub start_tick: u64,
+    /// Bodies present when recording started, with their timelines cut down
+    /// to the state at `start_tick` and the inputs scheduled after it
+    pub bodies: Vec<BodySnapshot>,
+    /// Accepted events in the order they were processed
+    pub events: Vec<RecordedEvent>,
+    /// Tick recording finished on
+    pub end_tick: u64,
+    /// States of the recorded bodies still around at `end_tick`
+    pub final_states: Vec<RecordedState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedState {
+    #[serde(with = "entity_bits")]
+    pub entity: Entity,
+    pub state: PhysicsState,
+}
+
+/// An accepted event along with the tick it was processed on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RecordedEvent {
+    AddInput {
+        tick: u64,
+        #[serde(with = "entity_bits")]
+        entity: Entity,
+        input_tick: u64,
+        input: ControlInput,
+    },
+    RemoveInput {
+        tick: u64,
+        #[serde(with = "entity_bits")]
+        entity: Entity,
+        input_tick: u64,
+        input: ControlInput,
+    },
+    FirePlasmaCannon {
+        tick: u64,
+        #[serde(with = "entity_bits")]
+        shooter: Entity,
+    },
+    FireUnguidedMissile {
+        tick: u64,
+        #[serde(with = "entity_bits")]
+        shooter: Entity,
+    },
+}
+
+impl RecordedEvent {
+    /// Tick the event was processed on
+    pub fn tick(&self) -> u64 {
+        match self {
+            RecordedEvent::AddInput { tick, .. }
+            | RecordedEvent::RemoveInput { tick, .. }
+            | RecordedEvent::FirePlasmaCannon { tick, .. }
+            | RecordedEvent::FireUnguidedMissile { tick, .. } => *tick,
+        }
+    }
+
+    /// Every entity the event refers to
+    fn entities(&self) -> Vec<Entity> {
+        match self {
+            RecordedEvent::AddInput { entity, input, .. }
+            | RecordedEvent::RemoveInput { entity, input, .. } => {
+                let mut collect = CollectEntities(vec![*entity]);
+                let mut input = *input;
+                input.map_entities(&mut collect);
+                collect.0
+            }
+            RecordedEvent::FirePlasmaCannon { shooter, .. }
+            | RecordedEvent::FireUnguidedMissile { shooter, .. } => {
+                vec![*shooter]
+            }
+        }
+    }
+}
+
+/// Mapper that collects the entities it's given, leaving them as they are
+struct CollectEntities(Vec<Entity>);
+
+impl EntityMapper for CollectEntities {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.0.push(entity);
+        entity
+    }
+}
+
+impl Recording {
+    /// An entity the events or bodies refer to which isn't one of the
+    /// recorded bodies, and so can't be mapped when replaying
+    pub fn unknown_entity(&self) -> Option<Entity> {
+        let bodies = self
+            .bodies
+            .iter()
+            .map(|body| body.entity)
+            .collect::<EntityHashSet>();
+        let mut collect = CollectEntities(Vec::new());
+        for body in &self.bodies {
+            body.state.clone().map_entities(&mut collect);
+            body.timeline.clone().map_entities(&mut collect);
+        }
+        for event in &self.events {
+            collect.0.extend(event.entities());
+        }
+        collect
+            .0
+            .into_iter()
+            .find(|entity| !bodies.contains(entity))
+    }
+}
+
+impl VersionedJson for Recording {
+    const VERSION: u32 = FORMAT_VERSION;
+    const KIND: &'static str = "recording";
+
+    fn check(&self) -> anyhow::Result<()> {
+        if let Some(entity) = self.unknown_entity() {
+            bail!(
+                "Recording refers to entity {entity}, which isn't one of its \
+                 bodies"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Recording in progress, see `RecorderPlugin`
+#[derive(Resource, Debug)]
+pub struct MatchRecorder {
+    pub recording: Recording,
+    /// Whether the recording already ended, events are no longer recorded
+    stopped: bool,
+}
+
+impl MatchRecorder {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            stopped: false,
+        }
+    }
+
+    pub fn record(&mut self, event: RecordedEvent) {
+        if !self.stopped {
+            self.recording.events.push(event);
+        }
+    }
+
+    /// End the recording at `tick`, with the recorded bodies in the states
+    /// `state` gives for them. Does nothing if it already ended
+    fn stop(
+        &mut self,
+        tick: u64,
+        state: impl FnMut(Entity) -> Option<PhysicsState>,
+    ) {
+        if self.stopped {
+            return;
+        }
+        self.stopped = true;
+        self.recording.end_tick = tick;
+        self.recording.final_states = final_states(&self.recording, state);
+    }
+
+    /// End the recording at the current tick of `world`, unless it already
+    /// ended
+    fn finish(mut self, world: &mut World) -> Recording {
+        let tick = world.resource::<SimulationConfig>().current_tick;
+        let mut states = world.query::<&PhysicsState>();
+        self.stop(tick, |entity| states.get(world, entity).ok().cloned());
+        self.recording
+    }
+}
+
+/// Records accepted timeline edits and weapon fires while a `MatchRecorder`
+/// exists
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        // Weapon plugins are optional, their events are read either way
+        app.add_event::<PlasmaCannonFired>()
+            .add_event::<UnguidedMissileFired>()
+            .add_systems(
+                SimulationUpdate,
+                record_events.in_set(SimulationSet::Cleanup),
+            )
+            .add_observer(stop_recording_on_rewind);
+    }
+}
+
+/// End the recording at the tick a rewind starts from, since the replay
+/// can't go back like the live simulation did
+fn stop_recording_on_rewind(
+    trigger: Trigger<Rewinding>,
+    recorder: Option<ResMut<MatchRecorder>>,
+    states: Query<&PhysicsState>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    let tick = trigger.event().from;
+    info!(tick, "Rewinding, recording stops here");
+    recorder.stop(tick, |entity| states.get(entity).ok().cloned());
+}
+
+/// Push the events accepted this tick to the recording
+///
+/// Runs without a recording too, so that events from before it started
+/// aren't picked up once it does
+fn record_events(
+    tick: Res<SimTick>,
+    mut recorder: Option<ResMut<MatchRecorder>>,
+    mut inputs: EventReader<TimelineEventAccepted>,
+    mut plasma_fires: EventReader<PlasmaCannonFired>,
+    mut missile_fires: EventReader<UnguidedMissileFired>,
+) {
+    let tick = tick.get();
+    let inputs = inputs.read().map(|accepted| {
+        let &TimelineEventAccepted {
+            entity,
+            tick: input_tick,
+            input,
+            removed,
+        } = accepted;
+        if removed {
+            RecordedEvent::RemoveInput {
+                tick,
+                entity,
+                input_tick,
+                input,
+            }
+        } else {
+            RecordedEvent::AddInput {
+                tick,
+                entity,
+                input_tick,
+                input,
+            }
+        }
+    });
+    let plasma_fires =
+        plasma_fires.read().map(|&PlasmaCannonFired(shooter)| {
+            RecordedEvent::FirePlasmaCannon { tick, shooter }
+        });
+    let missile_fires =
+        missile_fires.read().map(|&UnguidedMissileFired(shooter)| {
+            RecordedEvent::FireUnguidedMissile { tick, shooter }
+        });
+    for event in inputs.chain(plasma_fires).chain(missile_fires) {
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(event);
+        }
+    }
+}
+
+/// Start recording, with the current bodies as the initial scenario and
+/// `seed` as the one the global RNG was started with
+pub fn start_recording(seed: u64) -> impl Command {
+    move |world: &mut World| {
+        let config = world.resource::<SimulationConfig>().clone();
+        let bounds = world.get_resource::<ArenaBounds>().copied();
+        let tick = config.current_tick;
+        // Captured in entity order, which replays spawn bodies in to keep
+        // ties between entities broken the same way
+        let bodies = Snapshot::capture(world)
+            .bodies
+            .into_iter()
+            .filter_map(|body| body_at(body, tick))
+            .collect::<Vec<_>>();
+
+        info!(tick, bodies = bodies.len(), "Started recording");
+        world.insert_resource(MatchRecorder::new(Recording {
+            version: FORMAT_VERSION,
+            seed,
+            ticks_per_second: config.ticks_per_second,
+            prediction_ticks: config.prediction_ticks,
+            history_ticks: config.history_ticks,
+            integrator: config.integrator,
+            bounds,
+            start_tick: tick,
+            bodies,
+            events: Vec::new(),
+            end_tick: tick,
+            final_states: Vec::new(),
+        }));
+    }
+}
+
+/// `body` at `tick`, keeping only its state then and the inputs scheduled
+/// after it, or `None` if its timeline doesn't reach `tick`
+fn body_at(mut body: BodySnapshot, tick: u64) -> Option<BodySnapshot> {
+    let state = body.timeline.state(tick)?.into_owned();
+    let mut timeline = Timeline {
+        input_events: body.timeline.input_events.split_off(&(tick + 1)),
+        last_computed_tick: tick,
+        ..default()
+    };
+    timeline.future_states.insert(tick, state.clone());
+    // Carry on the drift `state` is part of, so that the replay extrapolates
+    // from the same origin
+    if let Some(coast) = body.timeline.coast_at(tick) {
+        timeline.resume_coast(tick, coast.clone());
+    }
+    body.state = state;
+    body.timeline = timeline;
+    // Its parent's breakup is in the past, so it's a body like any other now
+    body.fragment = None;
+    Some(body)
+}
+
+/// Stop recording, capture the final states and save the recording to
+/// `path`
+pub fn finish_recording(
+    path: impl AsRef<Path> + Send + 'static,
+) -> impl Command {
+    move |world: &mut World| {
+        let Some(recorder) = world.remove_resource::<MatchRecorder>() else {
+            warn!("Finishing recording, but none was started");
+            return;
+        };
+        let recording = recorder.finish(world);
+        info!(
+            end_tick = recording.end_tick,
+            events = recording.events.len(),
+            "Finished recording"
+        );
+        if let Err(err) = recording.save(&path) {
+            error!(?err, "Failed to save recording");
+        }
+    }
+}
+
+/// States of the recorded bodies that are still around, as `state` gives
+/// them for each recorded entity
+fn final_states(
+    recording: &Recording,
+    mut state: impl FnMut(Entity) -> Option<PhysicsState>,
+) -> Vec<RecordedState> {
+    recording
+        .bodies
+        .iter()
+        .filter_map(|body| {
+            Some(RecordedState {
+                entity: body.entity,
+                state: state(body.entity)?,
+            })
+        })
+        .collect()
+}
+
+/// Plays a recording back: spawns its bodies on startup, then sends its
+/// events on their ticks
+pub struct ReplayPlugin {
+    pub recording: Recording,
+}
+
+/// Recording being replayed
+#[derive(Resource, Debug)]
+pub struct Replay {
+    pub recording: Recording,
+    /// Index of the next event to send
+    next_event: usize,
+    /// Recorded entities to their replayed counterparts
+    entities: EntityHashMap<Entity>,
+}
+
+impl Replay {
+    /// Replayed counterpart of a recorded entity, `None` for entities that
+    /// aren't recorded bodies
+    pub fn entity(&self, recorded: Entity) -> Option<Entity> {
+        self.entities.get(&recorded).copied()
+    }
+
+    fn input(&self, mut input: ControlInput) -> ControlInput {
+        input.map_entities(&mut EntityRemap(&self.entities));
+        input
+    }
+
+    /// Whether every event has been sent
+    pub fn is_finished(&self) -> bool {
+        self.next_event >= self.recording.events.len()
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Replay {
+            recording: self.recording.clone(),
+            next_event: 0,
+            entities: default(),
+        })
+        .add_systems(Startup, spawn_recorded_bodies)
+        .add_systems(
+            SimulationUpdate,
+            send_replay_events
+                .in_set(SimulationSet::Inputs)
+                .before(process_timeline_events),
+        );
+    }
+}
+
+fn spawn_recorded_bodies(world: &mut World) {
+    let mut replay = world.remove_resource::<Replay>().unwrap();
+    let recording = &replay.recording;
+    let snapshot = Snapshot {
+        version: snapshot::FORMAT_VERSION,
+        config: SimulationConfig {
+            // The first update advances to the start tick, and events
+            // processed on it are sent then
+            current_tick: recording.start_tick.saturating_sub(1),
+            ticks_per_second: recording.ticks_per_second,
+            prediction_ticks: recording.prediction_ticks,
+            history_ticks: recording.history_ticks,
+            integrator: recording.integrator,
+            ..world.resource::<SimulationConfig>().clone()
+        },
+        bounds: recording.bounds,
+        bodies: recording.bodies.clone(),
+    };
+    replay.entities = snapshot.restore(world);
+    world.insert_resource(replay);
+}
+
+/// Send the events recorded up to the current tick
+fn send_replay_events(
+    mut replay: ResMut<Replay>,
+    tick: Res<SimTick>,
+    mut requests: EventWriter<TimelineEventRequest>,
+    mut removals: EventWriter<TimelineEventRemovalRequest>,
+    mut plasma_fires: EventWriter<FirePlasmaCannon>,
+    mut missile_fires: EventWriter<FireUnguidedMissile>,
+) {
+    while let Some(event) = replay.recording.events.get(replay.next_event) {
+        if event.tick() > tick.get() {
+            break;
+        }
+        let event = event.clone();
+        replay.next_event += 1;
+        // Recordings are checked for these when loaded and verified
+        let entities = event.entities();
+        if let Some(entity) =
+            entities.iter().find(|e| replay.entity(**e).is_none())
+        {
+            error!(?entity, "Skipping replay event for an unrecorded entity");
+            continue;
+        }
+        let map = |recorded: Entity| replay.entities[&recorded];
+        match event {
+            RecordedEvent::AddInput {
+                entity,
+                input_tick,
+                input,
+                ..
+            } => {
+                requests.send(TimelineEventRequest {
+                    entity: map(entity),
+                    tick: input_tick,
+                    input: replay.input(input),
+                });
+            }
+            RecordedEvent::RemoveInput {
+                entity,
+                input_tick,
+                input,
+                ..
+            } => {
+                removals.send(TimelineEventRemovalRequest {
+                    entity: map(entity),
+                    tick: input_tick,
+                    input: replay.input(input),
+                });
+            }
+            RecordedEvent::FirePlasmaCannon { shooter, .. } => {
+                plasma_fires.send(FirePlasmaCannon(map(shooter)));
+            }
+            RecordedEvent::FireUnguidedMissile { shooter, .. } => {
+                missile_fires.send(FireUnguidedMissile(map(shooter)));
+            }
+        }
+    }
+}
+
+/// A body whose replayed final state differs from the recorded one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    /// Entity in the recorded world
+    pub entity: Entity,
+    pub recorded: Option<PhysicsState>,
+    pub replayed: Option<PhysicsState>,
+}
+
+/// Why a recording failed to verify
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    /// The recording refers to an entity that isn't one of its bodies, see
+    /// `Recording::unknown_entity`
+    UnknownEntity(Entity),
+    /// Bodies that ended up in different states than recorded
+    Diverged(Vec<ReplayMismatch>),
+}
+
+/// Replay `recording` headless and check every body ends up in the recorded
+/// final state
+pub fn verify(recording: &Recording) -> Result<(), ReplayError> {
+    if let Some(entity) = recording.unknown_entity() {
+        return Err(ReplayError::UnknownEntity(entity));
+    }
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            ParallaxProtocolArenaPlugin {
+                config: SimulationConfig {
+                    current_tick: recording.start_tick,
+                    ticks_per_second: recording.ticks_per_second,
+                    prediction_ticks: recording.prediction_ticks,
+                    history_ticks: recording.history_ticks,
+                    integrator: recording.integrator,
+                    ..default()
+                },
+                // Run a tick per update
+                physics: PhysicsSimulationPlugin {
+                    should_keep_alive: false,
+                    is_test: true,
+                },
+                client: None,
+            },
+            EntropyPlugin::<WyRand>::with_seed(recording.seed.to_ne_bytes()),
+            PlasmaCannonPlugin,
+            UnguidedMissilePlugin,
+            ReplayPlugin {
+                recording: recording.clone(),
+            },
+        ))
+        .insert_resource(PhysicsEnabled);
+
+    while app.world().resource::<SimulationConfig>().current_tick
+        < recording.end_tick
+    {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let replay = world.remove_resource::<Replay>().unwrap();
+    let mut states = world.query::<&PhysicsState>();
+    let replayed = final_states(recording, |recorded| {
+        states.get(world, replay.entity(recorded)?).ok().cloned()
+    });
+    let mismatches = recording
+        .bodies
+        .iter()
+        .map(|body| body.entity)
+        .filter_map(|entity| {
+            let find = |states: &[RecordedState]| {
+                states
+                    .iter()
+                    .find(|s| s.entity == entity)
+                    .map(|s| s.state.clone())
+            };
+            let recorded = find(&recording.final_states);
+            let replayed = find(&replayed);
+            (recorded != replayed).then_some(ReplayMismatch {
+                entity,
+                recorded,
+                replayed,
+            })
+        })
+        .collect::<Vec<_>>();
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(ReplayError::Diverged(mismatches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        collisions::Material,
+        rewind,
+        BeamAnchor,
+        BeamParams,
+        Real,
+    };
+
+    fn test_app(history_ticks: u64) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ParallaxProtocolArenaPlugin {
+                config: SimulationConfig {
+                    prediction_ticks: 30,
+                    history_ticks,
+                    ..default()
+                },
+                physics: PhysicsSimulationPlugin {
+                    should_keep_alive: false,
+                    is_test: true,
+                },
+                client: None,
+            })
+            .insert_resource(PhysicsEnabled);
+        for _ in 0..3 {
+            app.update();
+        }
+        app
+    }
+
+    /// Take the recording out of `app` and finish it at the current tick
+    fn finish(app: &mut App) -> Recording {
+        let world = app.world_mut();
+        let recorder = world.remove_resource::<MatchRecorder>().unwrap();
+        recorder.finish(world)
+    }
+
+    /// Record a short match where A connects a beam to B and then thrusts
+    fn record() -> Recording {
+        let mut app = test_app(0);
+
+        let world = app.world_mut();
+        let a = world
+            .spawn(PhysicsBundle::new_basic(
+                3,
+                Vec2::ZERO,
+                Vec2::new(1., 0.),
+                0.,
+                100.,
+                1.,
+                Vec2::splat(2.),
+            ))
+            .id();
+        let b = world
+            .spawn((
+                PhysicsBundle::new_basic(
+                    3,
+                    Vec2::new(20., 0.),
+                    Vec2::ZERO,
+                    0.,
+                    0.,
+                    1.,
+                    Vec2::splat(2.),
+                ),
+                Material::default(),
+            ))
+            .id();
+        start_recording(7).apply(world);
+
+        world.send_event(TimelineEventRequest {
+            entity: a,
+            tick: 5,
+            input: ControlInput::ElasticBeamConnect(
+                BeamAnchor::Entity(b),
+                BeamParams::default(),
+            ),
+        });
+        for _ in 0..5 {
+            app.update();
+        }
+        app.world_mut().send_event(TimelineEventRequest {
+            entity: a,
+            tick: 12,
+            input: ControlInput::SetThrustAndRotation(0.5, PI),
+        });
+        for _ in 0..10 {
+            app.update();
+        }
+        finish(&mut app)
+    }
+
+    #[test]
+    fn test_recording_captures_scenario_and_events() {
+        let recording = record();
+        assert_eq!(recording.seed, 7);
+        assert_eq!(recording.start_tick, 3);
+        assert_eq!(recording.end_tick, 18);
+        assert_eq!(recording.bodies.len(), 2);
+        // Timelines are cut down to the start tick
+        for body in &recording.bodies {
+            assert_eq!(body.timeline.last_computed_tick, 3);
+            assert!(body.timeline.future_states.keys().all(|&tick| tick == 3));
+        }
+        assert_eq!(recording.events.len(), 2);
+        assert_eq!(recording.final_states.len(), 2);
+    }
+
+    #[test]
+    fn test_replay_matches_recording() {
+        let recording = record();
+        let json = serde_json::to_string(&recording).unwrap();
+        let loaded = Recording::from_json(&json).unwrap();
+        assert_eq!(loaded, recording);
+        assert_eq!(verify(&loaded), Ok(()));
+    }
+
+    #[test]
+    fn test_replay_keeps_destroyed_bodies_for_the_history() {
+        let mut app = test_app(10);
+        let world = app.world_mut();
+        for (x, vel) in [(0., 200.), (40., -200.)] {
+            world.spawn(PhysicsBundle::new_basic(
+                3,
+                Vec2::new(x, 0.),
+                Vec2::new(vel, 0.),
+                0.,
+                0.,
+                1.,
+                Vec2::splat(2.),
+            ));
+        }
+        start_recording(7).apply(world);
+        for _ in 0..8 {
+            app.update();
+        }
+
+        // Both are destroyed, but still around within the history
+        let recording = finish(&mut app);
+        assert_eq!(recording.history_ticks, 10);
+        assert_eq!(recording.final_states.len(), 2);
+        assert!(recording.final_states.iter().all(|s| !s.state.alive));
+        assert_eq!(verify(&recording), Ok(()));
+    }
+
+    #[test]
+    fn test_rewind_stops_recording() {
+        let mut app = test_app(10);
+        let world = app.world_mut();
+        let a = world
+            .spawn(PhysicsBundle::new_basic(
+                3,
+                Vec2::ZERO,
+                Vec2::new(1., 0.),
+                0.,
+                100.,
+                1.,
+                Vec2::splat(2.),
+            ))
+            .id();
+        start_recording(7).apply(world);
+        let thrust = |world: &mut World, tick| {
+            world.send_event(TimelineEventRequest {
+                entity: a,
+                tick,
+                input: ControlInput::SetThrust(1.),
+            });
+        };
+        thrust(world, 6);
+        for _ in 0..5 {
+            app.update();
+        }
+
+        // Going back ends the recording, what happens after isn't recorded
+        rewind(5).apply(app.world_mut());
+        thrust(app.world_mut(), 7);
+        for _ in 0..5 {
+            app.update();
+        }
+        let recording = finish(&mut app);
+        assert_eq!(recording.end_tick, 8);
+        assert_eq!(recording.events.len(), 1);
+        assert_eq!(verify(&recording), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_reports_divergence() {
+        let mut recording = record();
+        recording.final_states[0].state.pos.x += Real::ONE;
+        let Err(ReplayError::Diverged(mismatches)) = verify(&recording) else {
+            panic!("Replay should have diverged");
+        };
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].entity, recording.final_states[0].entity);
+    }
+
+    #[test]
+    fn test_rejects_other_versions() {
+        let mut recording = record();
+        recording.version = FORMAT_VERSION + 1;
+        let json = serde_json::to_string(&recording).unwrap();
+        assert!(Recording::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unrecorded_entities() {
+        let mut recording = record();
+        // Something spawned during the match, which replays can't map
+        let spawned = Entity::from_raw(1000);
+        recording.events.push(RecordedEvent::FirePlasmaCannon {
+            tick: recording.end_tick,
+            shooter: spawned,
+        });
+        assert_eq!(recording.unknown_entity(), Some(spawned));
+        assert_eq!(
+            verify(&recording),
+            Err(ReplayError::UnknownEntity(spawned))
+        );
+        let json = serde_json::to_string(&recording).unwrap();
+        assert!(Recording::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Saving and loading the whole simulation
+//!
+//! A `Snapshot` holds the `SimulationConfig`, including the current tick, and
+//! every simulated body with its full timeline: kept history, scheduled
+//! inputs and computed future states. Restoring it gives each body a new
+//! entity and remaps every entity reference, like beam anchors and collision
+//! records, so the simulation carries on exactly where it was saved.
+//!
+//! Only simulation components are saved. Sprites and other visuals aren't,
+//! whoever loads a snapshot into a client has to add their own
+
+use std::path::Path;
+
+use bevy::ecs::entity::MapEntities;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    physics::{
+        collisions::{Collider, Material, SpatialIndex},
+        ArenaBounds,
+        Breakable,
+        Fragment,
+        GravitySource,
+        PredictionPolicy,
+        SimulationConfig,
+    },
+    prelude::*,
+    subsystems::{
+        plasma_cannon::PlasmaCannon,
+        unguided_missile::{MissileProjectile, UnguidedMissile},
+    },
+    time_control::TimeController,
+    utils::{entity_bits, EntityRemap, VersionedJson},
+    Health,
+};
+
+/// Version of the snapshot file format, bumped on incompatible changes
+pub const FORMAT_VERSION: u32 = 3;
+
+/// The whole simulation at one tick
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// Format the snapshot was written with, see `FORMAT_VERSION`
+    pub version: u32,
+    pub config: SimulationConfig,
+    #[serde(default)]
+    pub bounds: Option<ArenaBounds>,
+    /// Bodies ordered by entity
+    pub bodies: Vec<BodySnapshot>,
+}
+
+/// A simulated body and its optional gameplay components
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BodySnapshot {
+    /// Entity in the saved world
+    #[serde(with = "entity_bits")]
+    pub entity: Entity,
+    pub state: PhysicsState,
+    pub timeline: Timeline,
+    pub collider: Collider,
+    pub material: Option<Material>,
+    pub gravity_source: Option<GravitySource>,
+    pub prediction_policy: Option<PredictionPolicy>,
+    pub health: Option<Health>,
+    pub breakable: bool,
+    /// Link to the body this one broke off from, if that was saved too
+    pub fragment: Option<Fragment>,
+    pub plasma_cannon: Option<PlasmaCannon>,
+    pub unguided_missile: Option<UnguidedMissile>,
+    pub missile: Option<MissileProjectile>,
+}
+
+impl Snapshot {
+    /// Capture every body with a timeline
+    pub fn capture(world: &mut World) -> Snapshot {
+        let config = world.resource::<SimulationConfig>().clone();
+        let mut query = world.query::<(
+            Entity,
+            &PhysicsState,
+            &Timeline,
+            &Collider,
+            (
+                Option<&Material>,
+                Option<&GravitySource>,
+                Option<&PredictionPolicy>,
+                Option<&Health>,
+            ),
+            (Has<Breakable>, Option<&Fragment>),
+            (
+                Option<&PlasmaCannon>,
+                Option<&UnguidedMissile>,
+                Option<&MissileProjectile>,
+            ),
+        )>();
+        let mut bodies = Vec::new();
+        for (
+            entity,
+            state,
+            timeline,
+            collider,
+            (material, gravity_source, prediction_policy, health),
+            (breakable, fragment),
+            (plasma_cannon, unguided_missile, missile),
+        ) in query.iter(world)
+        {
+            bodies.push(BodySnapshot {
+                entity,
+                state: state.clone(),
+                timeline: timeline.clone(),
+                collider: collider.clone(),
+                material: material.copied(),
+                gravity_source: gravity_source.copied(),
+                prediction_policy: prediction_policy.copied(),
+                health: health.cloned(),
+                breakable,
+                fragment: fragment.copied(),
+                plasma_cannon: plasma_cannon.cloned(),
+                unguided_missile: unguided_missile.cloned(),
+                missile: missile.cloned(),
+            });
+        }
+        // Restoring spawns bodies in this order, which keeps ties between
+        // entities broken the same way
+        bodies.sort_by_key(|body| body.entity);
+        let entities = bodies
+            .iter()
+            .map(|body| body.entity)
+            .collect::<EntityHashSet>();
+        for body in &mut bodies {
+            if body
+                .fragment
+                .is_some_and(|fragment| !entities.contains(&fragment.parent))
+            {
+                body.fragment = None;
+            }
+        }
+
+        Snapshot {
+            version: FORMAT_VERSION,
+            config,
+            bounds: world.get_resource::<ArenaBounds>().copied(),
+            bodies,
+        }
+    }
+
+    /// Replace the simulation in `world` with this snapshot
+    ///
+    /// Bodies already in the world are despawned. Returns the saved entities
+    /// mapped to the ones they were restored as
+    pub fn restore(&self, world: &mut World) -> EntityHashMap<Entity> {
+        let mut existing = world.query_filtered::<Entity, With<Timeline>>();
+        let existing = existing.iter(world).collect::<Vec<_>>();
+        for entity in existing {
+            world.entity_mut(entity).despawn_recursive();
+        }
+
+        let config = &self.config;
+        world.insert_resource(config.clone());
+        if let Some(mut controller) = world.get_resource_mut::<TimeController>()
+        {
+            controller.set_dilation(config.time_dilation);
+            if config.paused {
+                controller.pause();
+            } else {
+                controller.resume();
+            }
+        }
+
+        // Reserve every entity first so references between bodies can be
+        // mapped
+        let entities = self
+            .bodies
+            .iter()
+            .map(|body| (body.entity, world.spawn_empty().id()))
+            .collect::<EntityHashMap<Entity>>();
+
+        match self.bounds {
+            Some(bounds) => world.insert_resource(bounds),
+            None => {
+                world.remove_resource::<ArenaBounds>();
+            }
+        }
+
+        let mut spatial_index = SpatialIndex::default();
+        for body in &self.bodies {
+            let entity = entities[&body.entity];
+            let mut state = body.state.clone();
+            state.map_entities(&mut EntityRemap(&entities));
+            let mut timeline = body.timeline.clone();
+            timeline.map_entities(&mut EntityRemap(&entities));
+            spatial_index.insert_timeline(
+                entity,
+                &body.collider,
+                &timeline,
+                self.bounds.as_ref(),
+            );
+
+            let fragment = body.fragment.map(|mut fragment| {
+                fragment.map_entities(&mut EntityRemap(&entities));
+                fragment
+            });
+
+            body.insert(
+                &mut world.entity_mut(entity),
+                state,
+                timeline,
+                fragment,
+            );
+        }
+        world.insert_resource(spatial_index);
+
+        info!(
+            tick = config.current_tick,
+            bodies = self.bodies.len(),
+            "Restored snapshot"
+        );
+        entities
+    }
+}
+
+impl VersionedJson for Snapshot {
+    const VERSION: u32 = FORMAT_VERSION;
+    const KIND: &'static str = "snapshot";
+}
+
+impl BodySnapshot {
+    /// Insert the body's components into `entity`, with `state`, `timeline`
+    /// and `fragment` already mapped onto the world's entities
+    pub fn insert(
+        &self,
+        entity: &mut EntityWorldMut,
+        state: PhysicsState,
+        timeline: Timeline,
+        fragment: Option<Fragment>,
+    ) {
+        entity.insert((
+            Transform::from_translation(Vec3::from2(state.pos.to_vec2()))
+                .with_rotation(state.quat()),
+            state,
+            timeline,
+            self.collider.clone(),
+        ));
+        if let Some(material) = self.material {
+            entity.insert(material);
+        }
+        if let Some(gravity_source) = self.gravity_source {
+            entity.insert(gravity_source);
+        }
+        if let Some(prediction_policy) = self.prediction_policy {
+            entity.insert(prediction_policy);
+        }
+        if let Some(health) = &self.health {
+            entity.insert(health.clone());
+        }
+        if self.breakable {
+            entity.insert(Breakable);
+        }
+        if let Some(fragment) = fragment {
+            entity.insert(fragment);
+        }
+        if let Some(plasma_cannon) = &self.plasma_cannon {
+            entity.insert(plasma_cannon.clone());
+        }
+        if let Some(unguided_missile) = &self.unguided_missile {
+            entity.insert(unguided_missile.clone());
+        }
+        if let Some(missile) = &self.missile {
+            entity.insert(missile.clone());
+        }
+    }
+}
+
+/// Save the current simulation to `path`
+pub fn save_snapshot(path: impl AsRef<Path> + Send + 'static) -> impl Command {
+    move |world: &mut World| {
+        let snapshot = Snapshot::capture(world);
+        if let Err(err) = snapshot.save(&path) {
+            error!(?err, "Failed to save snapshot");
+            return;
+        }
+        info!(
+            tick = snapshot.config.current_tick,
+            bodies = snapshot.bodies.len(),
+            "Saved snapshot"
+        );
+    }
+}
+
+/// Replace the simulation with the snapshot saved at `path`
+pub fn load_snapshot(path: impl AsRef<Path> + Send + 'static) -> impl Command {
+    move |world: &mut World| match Snapshot::load(&path) {
+        Ok(snapshot) => {
+            snapshot.restore(world);
+        }
+        Err(err) => error!(?err, "Failed to load snapshot"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        physics::{
+            BeamAnchor,
+            BeamParams,
+            ControlInput,
+            PhysicsEnabled,
+            PhysicsSimulationPlugin,
+            TimelineEventRequest,
+        },
+        ParallaxProtocolArenaPlugin,
+    };
+
+    fn create_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ParallaxProtocolArenaPlugin {
+                config: SimulationConfig {
+                    prediction_ticks: 30,
+                    ..default()
+                },
+                physics: PhysicsSimulationPlugin {
+                    should_keep_alive: false,
+                    is_test: true,
+                },
+                client: None,
+            })
+            .insert_resource(PhysicsEnabled);
+        app
+    }
+
+    /// A connected to B with a beam, with thrust scheduled after the
+    /// snapshot is taken
+    fn running_app() -> (App, Entity, Entity) {
+        let mut app = create_app();
+        app.update();
+
+        let world = app.world_mut();
+        let a = world
+            .spawn(PhysicsBundle::new_basic(
+                1,
+                Vec2::ZERO,
+                Vec2::new(1., 0.),
+                0.,
+                100.,
+                1.,
+                Vec2::splat(2.),
+            ))
+            .id();
+        let b = world
+            .spawn((
+                PhysicsBundle::new_basic(
+                    1,
+                    Vec2::new(20., 0.),
+                    Vec2::ZERO,
+                    0.,
+                    0.,
+                    1.,
+                    Vec2::splat(2.),
+                ),
+                Material::default(),
+                Health(1.),
+            ))
+            .id();
+        world.send_event(TimelineEventRequest {
+            entity: a,
+            tick: 3,
+            input: ControlInput::ElasticBeamConnect(
+                BeamAnchor::Entity(b),
+                BeamParams::default(),
+            ),
+        });
+        world.send_event(TimelineEventRequest {
+            entity: a,
+            tick: 12,
+            input: ControlInput::SetThrustAndRotation(0.5, PI),
+        });
+        for _ in 0..5 {
+            app.update();
+        }
+        (app, a, b)
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let (mut app, _, _) = running_app();
+        let snapshot = Snapshot::capture(app.world_mut());
+        assert_eq!(snapshot.config.current_tick, 6);
+        assert_eq!(snapshot.bodies.len(), 2);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(Snapshot::from_json(&json).unwrap(), snapshot);
+
+        let mut other = snapshot.clone();
+        other.version = FORMAT_VERSION + 1;
+        let json = serde_json::to_string(&other).unwrap();
+        assert!(Snapshot::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_restore_remaps_entities() {
+        let (mut app, a, b) = running_app();
+        let snapshot = Snapshot::capture(app.world_mut());
+
+        let mut restored = create_app();
+        // Shift entity ids so they differ from the saved ones
+        restored
+            .world_mut()
+            .spawn_batch((0..5).map(|_| Name::new("filler")));
+        let entities = snapshot.restore(restored.world_mut());
+        let (new_a, new_b) = (entities[&a], entities[&b]);
+        assert_ne!(new_b, b);
+
+        let world = restored.world();
+        let state = world.get::<PhysicsState>(new_a).unwrap();
+        assert_eq!(state.elastic_beams.len(), 1);
+        assert_eq!(state.elastic_beams[0].anchor, BeamAnchor::Entity(new_b));
+        let timeline = world.get::<Timeline>(new_a).unwrap();
+        assert_eq!(
+            timeline.inputs(12),
+            &[ControlInput::SetThrustAndRotation(0.5, PI)]
+        );
+        assert_eq!(world.get::<Health>(new_b), Some(&Health(1.)));
+        assert_eq!(
+            world.resource::<SimulationConfig>().current_tick,
+            snapshot.config.current_tick
+        );
+    }
+
+    #[test]
+    fn test_restored_simulation_resumes_identically() {
+        let (mut app, a, b) = running_app();
+        let snapshot = Snapshot::capture(app.world_mut());
+        let mut restored = create_app();
+        restored
+            .world_mut()
+            .spawn_batch((0..5).map(|_| Name::new("filler")));
+        let entities = snapshot.restore(restored.world_mut());
+
+        for _ in 0..15 {
+            app.update();
+            restored.update();
+        }
+
+        for entity in [a, b] {
+            let mut expected =
+                app.world().get::<PhysicsState>(entity).unwrap().clone();
+            expected.map_entities(&mut EntityRemap(&entities));
+            let state = restored.world().get::<PhysicsState>(entities[&entity]);
+            assert_eq!(state, Some(&expected));
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use bevy::color::palettes::css;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    physics::{PhysicsBundle, PhysicsState, SimulationConfig},
+    physics::{
+        PhysicsBundle,
+        PhysicsState,
+        PredictionPolicy,
+        SimTick,
+        SimulationConfig,
+        SimulationSet,
+        SimulationUpdate,
+    },
     prelude::*,
     Selected,
 };
@@ -12,12 +21,15 @@ impl Plugin for PlasmaCannonPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<PlasmaCannon>();
         app.add_event::<FirePlasmaCannon>();
+        app.add_event::<PlasmaCannonFired>();
         app.add_systems(Update, debug_keyboard_input);
-        app.add_systems(Update, fire);
+        app.add_systems(SimulationUpdate, fire.in_set(SimulationSet::Gameplay));
     }
 }
 
-#[derive(Component, Reflect, Debug, Default)]
+#[derive(
+    Component, Reflect, Debug, Default, Clone, PartialEq, Serialize, Deserialize,
+)]
 pub struct PlasmaCannon {
     /// Tick when this cannon will be able to fire again
     pub ready_tick: u64,
@@ -26,48 +38,60 @@ pub struct PlasmaCannon {
 #[derive(Event)]
 pub struct FirePlasmaCannon(pub Entity);
 
+/// Sent when a `FirePlasmaCannon` request is accepted and the cannon fires
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct PlasmaCannonFired(pub Entity);
+
 #[derive(Component, Reflect, Debug)]
 struct PlasmaBurst;
 
 impl PlasmaBurst {
-    pub fn bundle(tick: u64, shooter: &PhysicsState) -> impl Bundle {
+    pub fn bundle(
+        tick: u64,
+        ticks_per_second: u64,
+        shooter: &PhysicsState,
+    ) -> impl Bundle {
         (
             PlasmaBurst,
-            PhysicsBundle::new_basic(
+            PhysicsBundle::from_state(
                 tick,
-                shooter.pos + 20. * shooter.dir(),
                 // add an impulse in the forwards direction to account for
-                // firing the burst
-                shooter.vel + 100. * shooter.dir(),
-                shooter.rotation,
-                0.,
-                // high mass makes the collision system always destroy other
-                // object
-                1000.,
+                // firing the burst. High mass makes the collision system
+                // always destroy other object
+                shooter.launch(20., 100., 1000.),
                 Vec2::splat(1.),
             ),
+            // Bursts are short lived, so there is no point predicting them
+            // far ahead
+            PredictionPolicy::horizon(ticks_per_second * 2),
             Sprite::from_color(css::AQUA, Vec2::splat(1.)),
         )
     }
 }
 
-fn fire(
+pub fn fire(
     mut commands: Commands,
-    sim_config: Res<SimulationConfig>, // TODO: replace with 'tick' resource
+    tick: Res<SimTick>,
+    sim_config: Res<SimulationConfig>,
     mut cannons: Query<(&mut PlasmaCannon, &PhysicsState)>,
     mut fire_events: EventReader<FirePlasmaCannon>,
+    mut fired: EventWriter<PlasmaCannonFired>,
 ) {
     for FirePlasmaCannon(shooter) in fire_events.read() {
         let Ok((mut cannon, phys)) = cannons.get_mut(*shooter) else {
             warn!("FirePlasmaCannon event with invalid entity target");
             continue;
         };
-        if cannon.ready_tick <= sim_config.current_tick {
+        if cannon.ready_tick <= tick.get() {
             info!(shooter = shooter.index(), "Firing PlasmaCannon");
-            commands.spawn(PlasmaBurst::bundle(sim_config.current_tick, phys));
+            commands.spawn(PlasmaBurst::bundle(
+                tick.get(),
+                sim_config.ticks_per_second,
+                phys,
+            ));
             // add 5 second cooldown for firing
-            cannon.ready_tick =
-                sim_config.current_tick + sim_config.ticks_per_second * 2;
+            cannon.ready_tick = tick.get() + sim_config.ticks_per_second * 2;
+            fired.send(PlasmaCannonFired(*shooter));
         }
     }
 }
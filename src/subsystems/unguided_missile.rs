@@ -1,7 +1,16 @@
 use bevy::color::palettes::css;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    physics::{PhysicsBundle, PhysicsState, SimulationConfig},
+    physics::{
+        PhysicsBundle,
+        PhysicsState,
+        Real,
+        SimTick,
+        SimulationConfig,
+        SimulationSet,
+        SimulationUpdate,
+    },
     prelude::*,
     Selected,
 };
@@ -13,11 +22,18 @@ impl Plugin for UnguidedMissilePlugin {
         app.register_type::<UnguidedMissile>()
             .register_type::<MissileProjectile>()
             .add_event::<FireUnguidedMissile>()
-            .add_systems(Update, (debug_keyboard_input, fire, apply_missile_thrust));
+            .add_event::<UnguidedMissileFired>()
+            .add_systems(Update, debug_keyboard_input)
+            .add_systems(
+                SimulationUpdate,
+                (fire, apply_missile_thrust).in_set(SimulationSet::Gameplay),
+            );
     }
 }
 
-#[derive(Component, Reflect, Debug, Default)]
+#[derive(
+    Component, Reflect, Debug, Default, Clone, PartialEq, Serialize, Deserialize,
+)]
 pub struct UnguidedMissile {
     /// Tick when this launcher will be able to fire again
     pub ready_tick: u64,
@@ -26,8 +42,15 @@ pub struct UnguidedMissile {
 #[derive(Event)]
 pub struct FireUnguidedMissile(pub Entity);
 
-#[derive(Component, Reflect, Debug)]
-struct MissileProjectile {
+/// Sent when a `FireUnguidedMissile` request is accepted and the launcher
+/// fires
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct UnguidedMissileFired(pub Entity);
+
+#[derive(
+    Component, Reflect, Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct MissileProjectile {
     /// Constant thrust force applied each tick
     thrust: f32,
     /// How long the missile will live (in ticks)
@@ -47,13 +70,11 @@ impl MissileProjectile {
                 lifetime,
                 spawn_tick: tick,
             },
-            PhysicsBundle::new_basic(
+            PhysicsBundle::from_state(
                 tick,
-                shooter.pos + 20. * shooter.dir(), // Spawn in front of shooter
-                shooter.vel + 50. * shooter.dir(), // Initial velocity boost
-                shooter.rotation,
-                0.,           // No rotation
-                10.0,        // Lower mass than PlasmaCannon
+                // Spawn in front of shooter with an initial velocity boost,
+                // lower mass than PlasmaCannon
+                shooter.launch(20., 50., 10.),
                 Vec2::new(2.0, 0.5), // Elongated hitbox
             ),
             Sprite::from_color(css::ORANGE_RED, Vec2::new(4.0, 1.0)), // Elongated sprite
@@ -61,41 +82,43 @@ impl MissileProjectile {
     }
 }
 
-fn fire(
+pub fn fire(
     mut commands: Commands,
+    tick: Res<SimTick>,
     sim_config: Res<SimulationConfig>,
     mut launchers: Query<(&mut UnguidedMissile, &PhysicsState)>,
     mut fire_events: EventReader<FireUnguidedMissile>,
+    mut fired: EventWriter<UnguidedMissileFired>,
 ) {
     for FireUnguidedMissile(shooter) in fire_events.read() {
         let Ok((mut launcher, phys)) = launchers.get_mut(*shooter) else {
             warn!("FireUnguidedMissile event with invalid entity target");
             continue;
         };
-        if launcher.ready_tick <= sim_config.current_tick {
+        if launcher.ready_tick <= tick.get() {
             info!(shooter = shooter.index(), "Firing UnguidedMissile");
-            commands.spawn(MissileProjectile::bundle(sim_config.current_tick, phys));
+            commands.spawn(MissileProjectile::bundle(tick.get(), phys));
             // 3 second cooldown
-            launcher.ready_tick = 
-                sim_config.current_tick + sim_config.ticks_per_second * 3;
+            launcher.ready_tick = tick.get() + sim_config.ticks_per_second * 3;
+            fired.send(UnguidedMissileFired(*shooter));
         }
     }
 }
 
 fn apply_missile_thrust(
     mut commands: Commands,
-    sim_config: Res<SimulationConfig>,
+    tick: Res<SimTick>,
     mut missiles: Query<(Entity, &MissileProjectile, &mut PhysicsState)>,
 ) {
     for (entity, missile, mut physics) in missiles.iter_mut() {
         // Check if missile should be destroyed
-        if sim_config.current_tick >= missile.spawn_tick + missile.lifetime {
+        if tick.get() >= missile.spawn_tick + missile.lifetime {
             commands.entity(entity).despawn();
             continue;
         }
 
         // Apply constant thrust in missile's forward direction
-        let thrust_force = missile.thrust * physics.dir();
+        let thrust_force = physics.dir() * Real::from_f32(missile.thrust);
         physics.vel += thrust_force;
     }
 }
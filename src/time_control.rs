@@ -0,0 +1,358 @@
+//! Time control: slowing down, speeding up and pausing the simulation
+//!
+//! The `TimeController` resource owns the rate the simulation advances at.
+//! Each frame it is advanced by real time and sets
+//! `SimulationConfig::time_dilation`, `SimulationConfig::paused` and the
+//! `Time<Fixed>` timestep to match, so those shouldn't be written directly.
+//!
+//! Dilation changes are eased over a duration rather than applied at once,
+//! and a slow motion window dips to a lower dilation for a while before
+//! easing back to wherever the dilation was heading. Dilations are clamped to
+//! `MIN_DILATION..=MAX_DILATION`, and NaN ones are ignored.
+
+use crate::{physics::SimulationConfig, prelude::*};
+
+/// Slowest the simulation runs, stopping it is done with `pause`
+pub const MIN_DILATION: f32 = 1e-3;
+/// Fastest the simulation runs
+pub const MAX_DILATION: f32 = 1e3;
+
+/// Controls how fast simulation time passes relative to real time
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct TimeController {
+    /// Dilation outside of slow motion windows
+    base: Ramp,
+    /// Slow motion window in progress
+    slow_motion: Option<SlowMotion>,
+    paused: bool,
+}
+
+/// Eased transition between two dilations
+#[derive(Clone, Debug, PartialEq)]
+struct Ramp {
+    from: f32,
+    to: f32,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+/// Dip to `dilation`, easing in and out over `ease` around `hold`
+#[derive(Clone, Debug, PartialEq)]
+struct SlowMotion {
+    dilation: f32,
+    ease: Duration,
+    hold: Duration,
+    elapsed: Duration,
+}
+
+impl TimeController {
+    /// Run at `dilation`, or at 1 if it is NaN
+    pub fn new(dilation: f32) -> Self {
+        Self {
+            base: Ramp::constant(clamp_dilation(dilation).unwrap_or(1.0)),
+            slow_motion: None,
+            paused: false,
+        }
+    }
+
+    /// Current dilation, in virtual seconds per real second
+    pub fn dilation(&self) -> f32 {
+        let base = self.base.value();
+        match &self.slow_motion {
+            // Weighted this way round so that a full weight gives exactly the
+            // slow motion dilation, however far apart the two are
+            Some(slow_motion) => {
+                let weight = slow_motion.weight();
+                slow_motion.dilation * weight + base * (1. - weight)
+            }
+            None => base,
+        }
+    }
+
+    /// Dilation the controller settles on once ramps and slow motion end
+    pub fn target(&self) -> f32 {
+        self.base.to
+    }
+
+    /// Ease from the current dilation to `dilation` over `duration` of real
+    /// time
+    ///
+    /// A slow motion window in progress carries on, and eases back to the new
+    /// target when it ends
+    pub fn ramp_to(&mut self, dilation: f32, duration: Duration) {
+        let Some(dilation) = clamp_dilation(dilation) else {
+            return;
+        };
+        self.base = Ramp {
+            from: self.base.value(),
+            to: dilation,
+            duration,
+            elapsed: Duration::ZERO,
+        };
+    }
+
+    /// Switch to `dilation` right away, ending any ramp or slow motion
+    pub fn set_dilation(&mut self, dilation: f32) {
+        let Some(dilation) = clamp_dilation(dilation) else {
+            return;
+        };
+        self.base = Ramp::constant(dilation);
+        self.slow_motion = None;
+    }
+
+    /// Ease down to `dilation` over `ease`, hold it for `hold`, then ease back
+    /// over `ease`, all in real time
+    ///
+    /// Replaces any slow motion window in progress
+    pub fn slow_motion(
+        &mut self,
+        dilation: f32,
+        hold: Duration,
+        ease: Duration,
+    ) {
+        let Some(dilation) = clamp_dilation(dilation) else {
+            return;
+        };
+        self.slow_motion = Some(SlowMotion {
+            dilation,
+            ease,
+            hold,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Whether a slow motion window is in progress
+    pub fn in_slow_motion(&self) -> bool {
+        self.slow_motion.is_some()
+    }
+
+    /// Stop advancing ticks, ramps and slow motion windows, rather than
+    /// slowing down to a dilation of zero
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advance ramps and slow motion by `delta` of real time
+    pub fn advance(&mut self, delta: Duration) {
+        if self.paused {
+            return;
+        }
+        self.base.elapsed = (self.base.elapsed + delta).min(self.base.duration);
+        if let Some(slow_motion) = &mut self.slow_motion {
+            slow_motion.elapsed += delta;
+            if slow_motion.elapsed >= slow_motion.total() {
+                self.slow_motion = None;
+            }
+        }
+    }
+}
+
+impl Default for TimeController {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Ramp {
+    fn constant(dilation: f32) -> Self {
+        Self {
+            from: dilation,
+            to: dilation,
+            duration: Duration::ZERO,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn value(&self) -> f32 {
+        let t = progress(self.elapsed, self.duration);
+        self.from + (self.to - self.from) * ease_in_out(t)
+    }
+}
+
+impl SlowMotion {
+    fn total(&self) -> Duration {
+        self.ease * 2 + self.hold
+    }
+
+    /// How far into the slow motion dilation the window is, from 0 to 1
+    fn weight(&self) -> f32 {
+        let remaining = self.total().saturating_sub(self.elapsed);
+        let t = progress(self.elapsed, self.ease)
+            .min(progress(remaining, self.ease));
+        ease_in_out(t)
+    }
+}
+
+/// `dilation` clamped to `MIN_DILATION..=MAX_DILATION`, `None` if it is NaN
+fn clamp_dilation(dilation: f32) -> Option<f32> {
+    if dilation.is_nan() {
+        warn!("Ignoring NaN time dilation");
+        return None;
+    }
+    let clamped = dilation.clamp(MIN_DILATION, MAX_DILATION);
+    if clamped != dilation {
+        warn!(dilation, clamped, "Time dilation out of range");
+    }
+    Some(clamped)
+}
+
+/// Fraction of `duration` that `elapsed` covers, clamped to [0, 1]
+fn progress(elapsed: Duration, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (elapsed.as_secs_f32() / duration.as_secs_f32()).clamp(0.0, 1.0)
+}
+
+/// Smoothstep, so dilation changes start and end gently
+fn ease_in_out(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Advance the controller by real time, and apply it to the simulation
+pub fn update_time_controller(
+    time: Res<Time<Real>>,
+    mut controller: ResMut<TimeController>,
+    mut config: ResMut<SimulationConfig>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    controller.advance(time.delta());
+
+    let paused = controller.is_paused();
+    if config.paused != paused {
+        config.paused = paused;
+        info!(paused, "Simulation pause changed");
+    }
+    let dilation = controller.dilation();
+    if config.time_dilation != dilation {
+        config.time_dilation = dilation;
+        debug!(dilation, "Simulation dilation changed");
+    }
+    if config.ticks_per_second == 0 {
+        warn_once!("Simulation has no ticks per second, keeping the timestep");
+        return;
+    }
+    let timestep = Duration::from_secs_f64(
+        1. / (config.ticks_per_second as f64 * dilation as f64),
+    );
+    if fixed_time.timestep() != timestep {
+        fixed_time.set_timestep(timestep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_ramp_eases_to_target() {
+        let mut controller = TimeController::new(1.0);
+        controller.ramp_to(0.5, SECOND);
+        assert_eq!(controller.dilation(), 1.0);
+
+        // Eased, so a quarter of the way in it has moved less than a quarter
+        // of the way
+        controller.advance(SECOND / 4);
+        assert!(controller.dilation() > 0.875);
+        controller.advance(SECOND / 4);
+        assert_eq!(controller.dilation(), 0.75);
+
+        controller.advance(SECOND);
+        assert_eq!(controller.dilation(), 0.5);
+        assert_eq!(controller.target(), 0.5);
+    }
+
+    #[test]
+    fn test_slow_motion_returns_to_base() {
+        let mut controller = TimeController::new(2.0);
+        controller.slow_motion(0.125, SECOND, SECOND / 2);
+
+        controller.advance(SECOND / 2);
+        assert_eq!(controller.dilation(), 0.125);
+        controller.advance(SECOND);
+        assert_eq!(controller.dilation(), 0.125);
+        assert!(controller.in_slow_motion());
+
+        // The base ramping meanwhile is picked up on the way out
+        controller.ramp_to(1.0, Duration::ZERO);
+        controller.advance(SECOND / 2);
+        assert!(!controller.in_slow_motion());
+        assert_eq!(controller.dilation(), 1.0);
+    }
+
+    #[test]
+    fn test_pause_freezes_ramps() {
+        let mut controller = TimeController::new(1.0);
+        controller.ramp_to(4.0, SECOND);
+        controller.pause();
+        controller.advance(SECOND);
+        assert_eq!(controller.dilation(), 1.0);
+
+        controller.toggle_pause();
+        controller.advance(SECOND);
+        assert_eq!(controller.dilation(), 4.0);
+    }
+
+    #[test]
+    fn test_controller_sets_fixed_timestep() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SimulationConfig::default())
+            .insert_resource(TimeController::new(0.5))
+            .add_systems(PreUpdate, update_time_controller);
+        app.update();
+
+        let config = app.world().resource::<SimulationConfig>();
+        assert_eq!(config.time_dilation, 0.5);
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        assert_eq!(timestep, Duration::from_secs_f64(1. / 30.));
+
+        app.world_mut().resource_mut::<TimeController>().pause();
+        app.update();
+        assert!(app.world().resource::<SimulationConfig>().paused);
+
+        // No ticks per second leaves the timestep alone
+        app.world_mut()
+            .resource_mut::<SimulationConfig>()
+            .ticks_per_second = 0;
+        app.update();
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        assert_eq!(timestep, Duration::from_secs_f64(1. / 30.));
+    }
+
+    #[test]
+    fn test_invalid_dilations_are_clamped_or_ignored() {
+        let mut controller = TimeController::new(f32::NAN);
+        assert_eq!(controller.dilation(), 1.0);
+
+        controller.set_dilation(0.0);
+        assert_eq!(controller.dilation(), MIN_DILATION);
+        controller.set_dilation(-2.0);
+        assert_eq!(controller.dilation(), MIN_DILATION);
+        controller.ramp_to(f32::INFINITY, Duration::ZERO);
+        assert_eq!(controller.dilation(), MAX_DILATION);
+
+        controller.set_dilation(f32::NAN);
+        controller.ramp_to(f32::NAN, SECOND);
+        controller.slow_motion(f32::NAN, SECOND, SECOND);
+        assert_eq!(controller.dilation(), MAX_DILATION);
+        assert!(!controller.in_slow_motion());
+
+        controller.slow_motion(-1.0, SECOND, Duration::ZERO);
+        assert_eq!(controller.dilation(), MIN_DILATION);
+    }
+}
@@ -1,16 +1,64 @@
-use std::{f32::consts::FRAC_PI_2, marker::PhantomData};
+use std::{f32::consts::FRAC_PI_2, fs, marker::PhantomData, path::Path};
 
+use anyhow::{bail, Context};
 pub use bevy::math::{Quat, Rect as BRect};
 use bevy::{
     ecs::{
-        entity::EntityHashMap,
+        entity::{EntityHashMap, EntityMapper},
         query::{QueryData, QueryFilter, ROQueryItem, WorldQuery},
     },
     prelude::{Component, Entity, Vec2, Vec3},
     utils::HashMap,
 };
+use serde::{
+    de::{DeserializeOwned, Error},
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+
+#[cfg(feature = "fixed-point")]
+use crate::physics::fixed::I32F32;
+
+/// Coordinate type of the spatial index
+///
+/// With the `fixed-point` feature rects are keyed by `I32F32` truncated to
+/// `SPATIAL_KEY_FRAC_BITS` fractional bits, so that broad-phase queries don't
+/// depend on float comparisons and the rtree's area products fit in an `i64`
+#[cfg(not(feature = "fixed-point"))]
+pub type SpatialKey = f32;
+#[cfg(feature = "fixed-point")]
+pub type SpatialKey = i64;
+
+pub type RRect = rtree_rs::Rect<2, SpatialKey>;
+
+/// Resolution of fixed-point spatial keys, 1/256th of a unit
+#[cfg(feature = "fixed-point")]
+const SPATIAL_KEY_FRAC_BITS: u32 = 8;
+#[cfg(feature = "fixed-point")]
+const SPATIAL_KEY_SHIFT: u32 = 32 - SPATIAL_KEY_FRAC_BITS;
+
+#[cfg(not(feature = "fixed-point"))]
+pub fn to_spatial_key(value: f32) -> SpatialKey {
+    value
+}
 
-pub type RRect = rtree_rs::Rect<2, f32>;
+/// Rounds down to the key resolution
+#[cfg(feature = "fixed-point")]
+pub fn to_spatial_key(value: f32) -> SpatialKey {
+    I32F32::from_f32(value).to_bits() >> SPATIAL_KEY_SHIFT
+}
+
+#[cfg(not(feature = "fixed-point"))]
+pub fn from_spatial_key(key: SpatialKey) -> f32 {
+    key
+}
+
+#[cfg(feature = "fixed-point")]
+pub fn from_spatial_key(key: SpatialKey) -> f32 {
+    I32F32::from_bits(key << SPATIAL_KEY_SHIFT).to_f32()
+}
 
 pub trait RectExt {
     fn to_bevy(&self) -> BRect;
@@ -20,7 +68,12 @@ pub trait RectExt {
 
 impl RectExt for RRect {
     fn to_bevy(&self) -> BRect {
-        BRect::new(self.min[0], self.min[1], self.max[0], self.max[1])
+        BRect::new(
+            from_spatial_key(self.min[0]),
+            from_spatial_key(self.min[1]),
+            from_spatial_key(self.max[0]),
+            from_spatial_key(self.max[1]),
+        )
     }
 
     fn to_rtree(&self) -> RRect {
@@ -28,11 +81,12 @@ impl RectExt for RRect {
     }
 
     fn transalate(&self, by: Vec2) -> Self {
+        let (x, y) = (to_spatial_key(by.x), to_spatial_key(by.y));
         let mut new = *self;
-        new.min[0] += by.x;
-        new.max[0] += by.x;
-        new.min[1] += by.y;
-        new.max[1] += by.y;
+        new.min[0] += x;
+        new.max[0] += x;
+        new.min[1] += y;
+        new.max[1] += y;
         new
     }
 }
@@ -43,7 +97,11 @@ impl RectExt for BRect {
     }
 
     fn to_rtree(&self) -> RRect {
-        RRect::new(self.min.to_array(), self.max.to_array())
+        // Rounded outwards so that the key rect covers this one
+        RRect::new(
+            self.min.to_array().map(to_spatial_key),
+            self.max.to_array().map(|v| -to_spatial_key(-v)),
+        )
     }
 
     fn transalate(&self, by: Vec2) -> Self {
@@ -167,6 +225,45 @@ pub fn intersect_ray_aabb(
     Ok(origin + direction * t)
 }
 
+/// Fraction of the segment from `origin` to `origin + direction` at which it
+/// first enters an AABB
+///
+/// # Returns
+/// * `Some(t)` - With `t` in [0, 1]; 0 when the origin starts inside the AABB
+/// * `None` - If the segment never touches the AABB
+pub fn segment_aabb_entry(
+    min: Vec2,
+    max: Vec2,
+    origin: Vec2,
+    direction: Vec2,
+) -> Option<f32> {
+    const EPSILON: f32 = 1e-6;
+
+    let mut t_enter = 0.0_f32;
+    let mut t_exit = 1.0_f32;
+
+    // Clip the segment against the slab of each axis
+    for axis in 0..2 {
+        let (o, d) = (origin[axis], direction[axis]);
+        if d.abs() < EPSILON {
+            // Parallel to the slab, must already be within it
+            if o < min[axis] || o > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - o) / d;
+        let t1 = (max[axis] - o) / d;
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+
+    Some(t_enter)
+}
+
 #[derive(Debug, PartialEq)]
 pub enum IntersectError {
     OriginOutside,
@@ -257,4 +354,139 @@ mod tests {
         .unwrap();
         assert!((point.x - 1.0).abs() < EPSILON * 10.0);
     }
+
+    #[test]
+    fn test_segment_aabb_entry() {
+        let min = Vec2::new(-1.0, -1.0);
+        let max = Vec2::new(1.0, 1.0);
+
+        // Passes straight through
+        let t = segment_aabb_entry(
+            min,
+            max,
+            Vec2::new(-5.0, 0.0),
+            Vec2::new(10.0, 0.0),
+        );
+        assert_eq!(t, Some(0.4));
+
+        // Starts inside
+        let t = segment_aabb_entry(min, max, Vec2::ZERO, Vec2::X);
+        assert_eq!(t, Some(0.0));
+
+        // Stops short
+        let t = segment_aabb_entry(
+            min,
+            max,
+            Vec2::new(-5.0, 0.0),
+            Vec2::new(3.0, 0.0),
+        );
+        assert_eq!(t, None);
+
+        // Parallel and outside the slab
+        let t = segment_aabb_entry(
+            min,
+            max,
+            Vec2::new(-5.0, 2.0),
+            Vec2::new(10.0, 0.0),
+        );
+        assert_eq!(t, None);
+
+        // Diagonal miss past the corner
+        let t = segment_aabb_entry(
+            min,
+            max,
+            Vec2::new(-3.0, 0.0),
+            Vec2::new(3.0, 3.0),
+        );
+        assert_eq!(t, None);
+
+        // Stationary
+        assert_eq!(
+            segment_aabb_entry(min, max, Vec2::ZERO, Vec2::ZERO),
+            Some(0.0)
+        );
+        assert_eq!(
+            segment_aabb_entry(min, max, Vec2::splat(2.), Vec2::ZERO),
+            None
+        );
+    }
+}
+
+/// Serde helper for `#[serde(with = "entity_bits")]` that stores an `Entity`
+/// as its bits
+///
+/// Entity ids are only meaningful within one world, whoever loads them has to
+/// map them onto their own entities
+pub mod entity_bits {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        entity: &Entity,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(entity.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Entity, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+        Entity::try_from_bits(bits).map_err(D::Error::custom)
+    }
+}
+
+/// Maps entities through a table of old to new ids, leaving entities missing
+/// from it unchanged
+pub struct EntityRemap<'a>(pub &'a EntityHashMap<Entity>);
+
+impl EntityMapper for EntityRemap<'_> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.0.get(&entity).copied().unwrap_or(entity)
+    }
+}
+
+/// File saved as JSON with a `version` field, which has to match `VERSION`
+/// when loading
+pub trait VersionedJson: Serialize + DeserializeOwned {
+    /// Current format version, bumped on incompatible changes
+    const VERSION: u32;
+    /// What the file holds, for error messages
+    const KIND: &'static str;
+
+    /// Check that a loaded value can be used
+    fn check(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    fn from_json(json: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value["version"].as_u64();
+        if version != Some(Self::VERSION as u64) {
+            bail!(
+                "Unsupported {} version {version:?}, expected {}",
+                Self::KIND,
+                Self::VERSION
+            );
+        }
+        let loaded: Self = serde_json::from_value(value)?;
+        loaded.check()?;
+        Ok(loaded)
+    }
 }